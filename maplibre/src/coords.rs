@@ -28,6 +28,29 @@ pub const MAX_ZOOM: usize = 32;
 // Bounds are generated 0..=31
 pub const ZOOM_BOUNDS: [u32; MAX_ZOOM] = create_zoom_bounds::<MAX_ZOOM>();
 
+/// Equatorial radius of the WGS84 ellipsoid in meters, as used by Web Mercator.
+pub const EARTH_RADIUS: f64 = 6378137.0;
+/// Physical size of a pixel in meters, as defined by the OGC for scale denominators.
+pub const STANDARDIZED_PIXEL_SIZE: f64 = 0.00028;
+/// Pixels per inch of the [`STANDARDIZED_PIXEL_SIZE`], about 90.7.
+pub const STANDARDIZED_DPI: f64 = METERS_PER_INCH / STANDARDIZED_PIXEL_SIZE;
+
+const METERS_PER_INCH: f64 = 0.0254;
+
+/// Returns the ground resolution in meters per pixel at the latitude `lat` in degrees and the
+/// fractional `zoom` for tiles which are `tile_size` pixels wide.
+pub fn resolution(lat: f64, zoom: f64, tile_size: f64) -> f64 {
+    let circumference = 2.0 * PI * EARTH_RADIUS;
+    lat.to_radians().cos() * circumference / (tile_size * 2.0_f64.powf(zoom))
+}
+
+/// Returns the scale denominator (e.g. `25000` for a scale of 1:25000) at the latitude `lat`
+/// in degrees and the fractional `zoom`. A pixel is assumed to be
+/// [`STANDARDIZED_PIXEL_SIZE`] meters wide.
+pub fn scale_denominator(lat: f64, zoom: f64, tile_size: f64) -> f64 {
+    resolution(lat, zoom, tile_size) / STANDARDIZED_PIXEL_SIZE
+}
+
 const fn create_zoom_bounds<const DIM: usize>() -> [u32; DIM] {
     let mut result: [u32; DIM] = [0; DIM];
     let mut i = 0;
@@ -165,7 +188,7 @@ impl Display for LatLon {
 
 /// `Zoom` is an exponential scale that defines the zoom of the camera on the map.
 /// We can derive the `ZoomLevel` from `Zoom` by using the `[crate::coords::ZOOM_BOUNDS]`.
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, PartialOrd)]
 pub struct Zoom(f64);
 
 impl Zoom {
@@ -186,6 +209,12 @@ impl Default for Zoom {
     }
 }
 
+impl From<Zoom> for f64 {
+    fn from(zoom: Zoom) -> Self {
+        zoom.0
+    }
+}
+
 impl fmt::Display for Zoom {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}", (self.0 * 100.0).round() / 100.0)
@@ -209,6 +238,22 @@ impl std::ops::Sub for Zoom {
 }
 
 impl Zoom {
+    /// Returns the zoom at which the scale at the equator is 1:`scale_denominator`, if the map
+    /// is displayed with `dpi` pixels per inch. With [`STANDARDIZED_DPI`] the scale denominators
+    /// of OGC tile matrix sets can be used. Because tiles are [`TILE_SIZE`] pixels wide, the
+    /// level `n` of the WMTS tile matrix set `GoogleMapsCompatible` corresponds to the zoom
+    /// `n - 1`.
+    pub fn from_scale_denominator(scale_denominator: f64, dpi: f64) -> Zoom {
+        let resolution = scale_denominator * METERS_PER_INCH / dpi;
+        let circumference = 2.0 * PI * EARTH_RADIUS;
+        Zoom((circumference / (TILE_SIZE * resolution)).log2())
+    }
+
+    /// The inverse of [`Zoom::from_scale_denominator`].
+    pub fn scale_denominator(&self, dpi: f64) -> f64 {
+        resolution(0.0, self.0, TILE_SIZE) * dpi / METERS_PER_INCH
+    }
+
     pub fn scale_to_tile(&self, coords: &WorldTileCoords) -> f64 {
         2.0_f64.powf(coords.z.0 as f64 - self.0)
     }
@@ -224,6 +269,11 @@ impl Zoom {
     pub fn level(&self) -> ZoomLevel {
         ZoomLevel::from(self.0.floor() as u8)
     }
+
+    /// The distance to the zoom level below, for example `0.25` for the zoom `3.25`
+    pub fn fract(&self) -> f64 {
+        self.0 - self.0.floor()
+    }
 }
 
 impl SignificantlyDifferent for Zoom {
@@ -307,21 +357,12 @@ impl From<(u32, u32, ZoomLevel)> for TileCoords {
 /// # Coordinate System Origin
 ///
 /// The origin of the coordinate system is in the upper-left corner.
+///
+/// # Ordering
+///
+/// Coordinates are ordered by `z`, then `x` and then `y`. See [`WorldTileCoords::z_x_y`].
 // FIXME: does Zeroable make sense?
-#[derive(
-    Clone,
-    Copy,
-    Debug,
-    PartialEq,
-    Eq,
-    PartialOrd,
-    Ord,
-    Hash,
-    Default,
-    Serialize,
-    Deserialize,
-    Zeroable,
-)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default, Serialize, Deserialize, Zeroable)]
 #[repr(C)]
 pub struct WorldTileCoords {
     pub x: i32,
@@ -329,7 +370,25 @@ pub struct WorldTileCoords {
     pub z: ZoomLevel,
 }
 
+impl PartialOrd for WorldTileCoords {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for WorldTileCoords {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.z_x_y().cmp(&other.z_x_y())
+    }
+}
+
 impl WorldTileCoords {
+    /// Returns the canonical `(z, x, y)` tuple of this coordinate. The ordering of
+    /// [`WorldTileCoords`] is defined by this tuple.
+    pub fn z_x_y(&self) -> (ZoomLevel, i32, i32) {
+        (self.z, self.x, self.y)
+    }
+
     /// Returns the tile coords according to an addressing scheme. This is not possible if the
     /// coordinates of this [`WorldTileCoords`] exceed their bounds.
     ///
@@ -551,6 +610,16 @@ impl WorldCoords {
         WorldCoords { x, y }
     }
 
+    /// The inverse of [`WorldCoords::from_lat_lon`].
+    pub fn to_lat_lon(&self, zoom: Zoom) -> LatLon {
+        let tile_size = TILE_SIZE * 2.0_f64.powf(zoom.0);
+        let longitude = self.x * 360.0 / tile_size - 180.0;
+        let merc_n = (tile_size / 2.0 - self.y) * 2.0 * PI / tile_size;
+        let latitude = merc_n.sinh().atan() * 180.0 / PI;
+
+        LatLon::new(latitude, longitude)
+    }
+
     pub fn at_ground(x: f64, y: f64) -> Self {
         Self { x, y }
     }
@@ -689,7 +758,8 @@ mod tests {
 
     use crate::{
         coords::{
-            Quadkey, TileCoords, ViewRegion, WorldCoords, WorldTileCoords, Zoom, ZoomLevel, EXTENT,
+            resolution, scale_denominator, LatLon, Quadkey, TileCoords, ViewRegion, WorldCoords,
+            WorldTileCoords, Zoom, ZoomLevel, EXTENT, STANDARDIZED_DPI,
         },
         style::source::TileAddressingScheme,
         util::math::Aabb2,
@@ -710,6 +780,16 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_lat_lon_round_trip() {
+        let zoom = Zoom::new(5.5);
+        let lat_lon = LatLon::new(48.137, 11.575);
+        let back = WorldCoords::from_lat_lon(lat_lon, zoom).to_lat_lon(zoom);
+
+        assert!((back.latitude - lat_lon.latitude).abs() < 1e-9);
+        assert!((back.longitude - lat_lon.longitude).abs() < 1e-9);
+    }
+
     #[test]
     fn world_coords_tests() {
         to_from_world((1, 0, ZoomLevel::from(1)), Zoom::new(1.0));
@@ -765,6 +845,66 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_resolution() {
+        const EQUATOR_RESOLUTION: f64 = 156543.03392804097;
+        let assert_close = |a: f64, b: f64| assert!((a - b).abs() < 1e-6, "{a} != {b}");
+
+        assert_close(resolution(0.0, 0.0, 256.0), EQUATOR_RESOLUTION);
+        assert_close(resolution(0.0, 1.0, 512.0), EQUATOR_RESOLUTION / 4.0);
+        assert_close(
+            resolution(60.0, 10.0, 256.0),
+            EQUATOR_RESOLUTION / 1024.0 / 2.0,
+        );
+        assert_close(
+            scale_denominator(0.0, 0.0, 256.0),
+            EQUATOR_RESOLUTION / 0.00028,
+        );
+    }
+
+    #[test]
+    fn test_zoom_scale_denominator() {
+        let assert_close =
+            |a: f64, b: f64| assert!((a - b).abs() < 1e-9 * b.abs().max(1.0), "{a} != {b}");
+
+        // Levels 1 and 18 of the WMTS tile matrix set GoogleMapsCompatible
+        let zoom = Zoom::from_scale_denominator(279541132.0143589, STANDARDIZED_DPI);
+        assert_close(zoom.0, 0.0);
+        let zoom = Zoom::from_scale_denominator(2132.729583849784, STANDARDIZED_DPI);
+        assert_close(zoom.0, 17.0);
+
+        let zoom = Zoom::new(5.5);
+        assert_close(
+            Zoom::from_scale_denominator(zoom.scale_denominator(96.0), 96.0).0,
+            5.5,
+        );
+        assert_close(
+            zoom.scale_denominator(STANDARDIZED_DPI),
+            scale_denominator(0.0, 5.5, 512.0),
+        );
+    }
+
+    #[test]
+    fn test_world_tile_ordering() {
+        let mut tiles: Vec<WorldTileCoords> = vec![
+            (1, 0, ZoomLevel::from(2)).into(),
+            (0, 1, ZoomLevel::from(1)).into(),
+            (0, 0, ZoomLevel::from(2)).into(),
+            (1, 0, ZoomLevel::from(1)).into(),
+        ];
+        tiles.sort();
+
+        assert_eq!(
+            tiles.iter().map(|tile| tile.z_x_y()).collect::<Vec<_>>(),
+            vec![
+                (ZoomLevel::from(1), 0, 1),
+                (ZoomLevel::from(1), 1, 0),
+                (ZoomLevel::from(2), 0, 0),
+                (ZoomLevel::from(2), 1, 0),
+            ]
+        );
+    }
+
     #[test]
     fn test_view_region() {
         for tile_coords in ViewRegion::new(
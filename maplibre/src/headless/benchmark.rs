@@ -0,0 +1,164 @@
+//! Measures the phases of rendering tiles with a [`HeadlessMap`], so that the counters can be
+//! logged or fed into a benchmarking framework like Criterion.
+
+use std::{
+    ops::AddAssign,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    coords::WorldTileCoords,
+    headless::map::{HeadlessMap, HeadlessMapError, MissingTilePolicy, TileStatus},
+};
+
+/// The aggregated counters of one or more runs of a [`Benchmark`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BenchmarkCounters {
+    /// The number of tiles which were fetched and contained data
+    pub tiles: usize,
+    pub bytes_fetched: usize,
+    /// The number of features which were decoded and tessellated
+    pub features_decoded: usize,
+    pub vertices: usize,
+    pub indices: usize,
+    pub draw_calls: u64,
+    pub fetch_duration: Duration,
+    /// The duration of decoding and tessellating the tiles
+    pub tessellate_duration: Duration,
+    pub render_duration: Duration,
+}
+
+impl BenchmarkCounters {
+    pub fn total_duration(&self) -> Duration {
+        self.fetch_duration + self.tessellate_duration + self.render_duration
+    }
+}
+
+impl AddAssign for BenchmarkCounters {
+    fn add_assign(&mut self, other: Self) {
+        self.tiles += other.tiles;
+        self.bytes_fetched += other.bytes_fetched;
+        self.features_decoded += other.features_decoded;
+        self.vertices += other.vertices;
+        self.indices += other.indices;
+        self.draw_calls += other.draw_calls;
+        self.fetch_duration += other.fetch_duration;
+        self.tessellate_duration += other.tessellate_duration;
+        self.render_duration += other.render_duration;
+    }
+}
+
+/// Fetches, tessellates and renders tiles with the source and style of a [`HeadlessMap`] and
+/// measures each phase.
+///
+/// The tessellation cache of the map is bypassed, so that every run tessellates all tiles.
+/// Tiles which do not exist in the source are skipped.
+pub struct Benchmark<'a> {
+    map: &'a mut HeadlessMap,
+    source_layers: Vec<String>,
+}
+
+impl<'a> Benchmark<'a> {
+    /// Creates a benchmark which requests the source layers which are required by the style
+    /// of `map`.
+    pub fn new(map: &'a mut HeadlessMap) -> Self {
+        let source_layers = map.required_source_layers();
+        Self { map, source_layers }
+    }
+
+    /// Requests the given source layers instead of the ones required by the style.
+    pub fn with_source_layers(mut self, source_layers: Vec<String>) -> Self {
+        self.source_layers = source_layers;
+        self
+    }
+
+    /// Fetches, tessellates and renders `tiles` within a single frame.
+    pub async fn run(
+        &mut self,
+        tiles: &[WorldTileCoords],
+    ) -> Result<BenchmarkCounters, HeadlessMapError> {
+        let mut counters = BenchmarkCounters::default();
+
+        let start = Instant::now();
+        let fetched = self
+            .map
+            .fetch_tiles(tiles.iter().copied(), MissingTilePolicy::Skip)
+            .await?;
+        counters.fetch_duration = start.elapsed();
+
+        let source_layers = self
+            .source_layers
+            .iter()
+            .map(|layer| layer.as_str())
+            .collect::<Vec<_>>();
+
+        let start = Instant::now();
+        let mut tessellated = Vec::with_capacity(fetched.len());
+        for (coords, status) in fetched {
+            let TileStatus::Loaded(data) = status else { continue; };
+            counters.tiles += 1;
+            counters.bytes_fetched += data.len();
+
+            let layers = self
+                .map
+                .tessellate(coords, data, &source_layers)
+                .map_err(|e| HeadlessMapError::Process(coords, e))?;
+            tessellated.push((coords, layers));
+        }
+        counters.tessellate_duration = start.elapsed();
+
+        for layer in tessellated.iter().flat_map(|(_, layers)| layers) {
+            counters.features_decoded += layer.feature_indices.len();
+            counters.vertices += layer.buffer.buffer.vertices.len();
+            counters.indices += layer.buffer.usable_indices as usize;
+        }
+
+        let start = Instant::now();
+        self.map.render_tiles(tessellated);
+        counters.render_duration = start.elapsed();
+        counters.draw_calls = self.map.draw_calls() as u64;
+
+        Ok(counters)
+    }
+
+    /// Runs the benchmark `iterations` times and sums up the counters.
+    pub async fn run_repeated(
+        &mut self,
+        tiles: &[WorldTileCoords],
+        iterations: usize,
+    ) -> Result<BenchmarkCounters, HeadlessMapError> {
+        let mut counters = BenchmarkCounters::default();
+        for _ in 0..iterations {
+            counters += self.run(tiles).await?;
+        }
+        Ok(counters)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::BenchmarkCounters;
+
+    #[test]
+    fn test_aggregate() {
+        let run = BenchmarkCounters {
+            tiles: 2,
+            bytes_fetched: 1000,
+            draw_calls: 5,
+            fetch_duration: Duration::from_millis(20),
+            render_duration: Duration::from_millis(5),
+            ..BenchmarkCounters::default()
+        };
+
+        let mut counters = BenchmarkCounters::default();
+        counters += run.clone();
+        counters += run;
+
+        assert_eq!(counters.tiles, 4);
+        assert_eq!(counters.bytes_fetched, 2000);
+        assert_eq!(counters.draw_calls, 10);
+        assert_eq!(counters.total_duration(), Duration::from_millis(50));
+    }
+}
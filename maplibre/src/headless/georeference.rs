@@ -0,0 +1,179 @@
+//! Georeferences rendered images, so that GIS tools can place them without manual
+//! georeferencing.
+
+use std::{f64::consts::PI, fs, io, path::Path};
+
+use cgmath::Vector2;
+
+use crate::{
+    coords::{WorldCoords, Zoom, EARTH_RADIUS, TILE_SIZE},
+    view_state::ViewState,
+    window::WindowSize,
+};
+
+/// The coordinate reference system of the rendered images.
+pub const WEB_MERCATOR_CRS: &str = "EPSG:3857";
+
+/// Describes where a rendered image is located in the [`WEB_MERCATOR_CRS`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeoReference {
+    pub crs: &'static str,
+    /// The bounding box of the image as `[min_x, min_y, max_x, max_y]` in meters.
+    pub bounds: [f64; 4],
+    /// The width and height of a pixel in meters.
+    pub pixel_size: [f64; 2],
+    /// The affine transform `[a, d, b, e, c, f]`, which maps the pixel `(column, row)` to
+    /// `(a * column + b * row + c, d * column + e * row + f)`. The translation refers to the
+    /// center of the upper left pixel, as in a world file.
+    pub transform: [f64; 6],
+}
+
+impl GeoReference {
+    /// Creates the georeference of an image of `size` pixels, whose upper left, upper right and
+    /// lower left corners are located at the world coordinates at `zoom`.
+    pub fn from_world_corners(
+        upper_left: WorldCoords,
+        upper_right: WorldCoords,
+        lower_left: WorldCoords,
+        zoom: Zoom,
+        size: WindowSize,
+    ) -> Self {
+        let world_size = TILE_SIZE * Zoom::default().scale_delta(&zoom);
+        let circumference = 2.0 * PI * EARTH_RADIUS;
+        let to_meters = |coords: WorldCoords| {
+            (
+                coords.x / world_size * circumference - circumference / 2.0,
+                circumference / 2.0 - coords.y / world_size * circumference,
+            )
+        };
+
+        let upper_left = to_meters(upper_left);
+        let upper_right = to_meters(upper_right);
+        let lower_left = to_meters(lower_left);
+        let lower_right = (
+            upper_right.0 + lower_left.0 - upper_left.0,
+            upper_right.1 + lower_left.1 - upper_left.1,
+        );
+
+        let (width, height) = (size.width() as f64, size.height() as f64);
+        let a = (upper_right.0 - upper_left.0) / width;
+        let d = (upper_right.1 - upper_left.1) / width;
+        let b = (lower_left.0 - upper_left.0) / height;
+        let e = (lower_left.1 - upper_left.1) / height;
+        let c = upper_left.0 + (a + b) / 2.0;
+        let f = upper_left.1 + (d + e) / 2.0;
+
+        let corners = [upper_left, upper_right, lower_left, lower_right];
+        let min_x = corners.iter().map(|p| p.0).fold(f64::INFINITY, f64::min);
+        let min_y = corners.iter().map(|p| p.1).fold(f64::INFINITY, f64::min);
+        let max_x = corners
+            .iter()
+            .map(|p| p.0)
+            .fold(f64::NEG_INFINITY, f64::max);
+        let max_y = corners
+            .iter()
+            .map(|p| p.1)
+            .fold(f64::NEG_INFINITY, f64::max);
+
+        Self {
+            crs: WEB_MERCATOR_CRS,
+            bounds: [min_x, min_y, max_x, max_y],
+            pixel_size: [a.hypot(d), b.hypot(e)],
+            transform: [a, d, b, e, c, f],
+        }
+    }
+
+    /// Creates the georeference of an image of `size` pixels which is rendered from the camera
+    /// of `view_state`. Returns `None` if the camera is pitched, because the image can then not
+    /// be described by an affine transform.
+    pub fn from_view_state(view_state: &ViewState, size: WindowSize) -> Option<Self> {
+        let camera = view_state.camera();
+        if camera.pitch().0.abs() > f64::EPSILON {
+            return None;
+        }
+
+        let inverted_view_proj = view_state.view_projection().invert();
+        let corner = |x: u32, y: u32| {
+            camera
+                .window_to_world_at_ground(
+                    &Vector2::new(x as f64, y as f64),
+                    &inverted_view_proj,
+                    false,
+                )
+                .map(|world| WorldCoords::at_ground(world.x, world.y))
+        };
+
+        Some(Self::from_world_corners(
+            corner(0, 0)?,
+            corner(size.width(), 0)?,
+            corner(0, size.height())?,
+            view_state.zoom(),
+            size,
+        ))
+    }
+
+    /// Returns the georeference of the same image with reversed row order, which is `height`
+    /// pixels high.
+    pub fn flip_y(&self, height: u32) -> Self {
+        let [a, d, b, e, c, f] = self.transform;
+        let rows = height.saturating_sub(1) as f64;
+        Self {
+            transform: [a, d, -b, -e, c + b * rows, f + e * rows],
+            ..self.clone()
+        }
+    }
+
+    /// Formats the transform as an ESRI world file.
+    pub fn world_file(&self) -> String {
+        self.transform
+            .iter()
+            .map(|value| format!("{value:.10}\n"))
+            .collect()
+    }
+
+    /// Writes the world file which belongs to the image at `image_path`. For `frame.png` the
+    /// world file is written to `frame.pgw`.
+    pub fn write_world_file<P: AsRef<Path>>(&self, image_path: P) -> io::Result<()> {
+        let image_path = image_path.as_ref();
+        let extension = match image_path
+            .extension()
+            .and_then(|extension| extension.to_str())
+        {
+            Some("png") => "pgw",
+            Some("jpg") | Some("jpeg") => "jgw",
+            _ => "wld",
+        };
+        fs::write(image_path.with_extension(extension), self.world_file())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::GeoReference;
+    use crate::{
+        coords::{WorldCoords, Zoom, EARTH_RADIUS, TILE_SIZE},
+        window::WindowSize,
+    };
+
+    #[test]
+    fn test_whole_world() {
+        let half = std::f64::consts::PI * EARTH_RADIUS;
+        let reference = GeoReference::from_world_corners(
+            WorldCoords::at_ground(0.0, 0.0),
+            WorldCoords::at_ground(TILE_SIZE, 0.0),
+            WorldCoords::at_ground(0.0, TILE_SIZE),
+            Zoom::default(),
+            WindowSize::new(256, 256).unwrap(),
+        );
+
+        for (actual, expected) in reference.bounds.iter().zip([-half, -half, half, half]) {
+            assert!((actual - expected).abs() < 1e-6);
+        }
+
+        let pixel = 2.0 * half / 256.0;
+        assert!((reference.pixel_size[0] - pixel).abs() < 1e-6);
+        assert!((reference.transform[3] + pixel).abs() < 1e-6);
+        assert!((reference.transform[4] - (-half + pixel / 2.0)).abs() < 1e-6);
+        assert_eq!(reference.world_file().lines().count(), 6);
+    }
+}
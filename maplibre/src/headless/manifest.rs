@@ -0,0 +1,233 @@
+//! Records which inputs produced a rendered frame, see [`HeadlessMap::last_render_manifest`].
+//!
+//! [`HeadlessMap::last_render_manifest`]: crate::headless::map::HeadlessMap::last_render_manifest
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::{coords::WorldTileCoords, style::Style, util::hash::fnv1a};
+
+/// Where a tile came from, as far as it is known by the map.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TileProvenance {
+    pub fetched_at: Option<SystemTime>,
+    pub expires_at: Option<SystemTime>,
+    pub etag: Option<String>,
+    /// The hash of the fetched data, see [`StoredTile::content_hash`]
+    ///
+    /// [`StoredTile::content_hash`]: crate::io::tile_expiry::StoredTile::content_hash
+    pub content_hash: Option<u64>,
+}
+
+impl TileProvenance {
+    /// The hash of the fetched data, unless the tile is expired at `now`.
+    pub fn valid_content_hash(&self, now: SystemTime) -> Option<u64> {
+        match self.expires_at {
+            Some(expires_at) if expires_at <= now => None,
+            _ => self.content_hash,
+        }
+    }
+}
+
+/// A rendered tile and the version of its source data. Times are seconds since the unix epoch.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ManifestTile {
+    pub z: u8,
+    pub x: i32,
+    pub y: i32,
+    /// `None` if the tile was not fetched by the map
+    pub fetched_at: Option<u64>,
+    pub expires_at: Option<u64>,
+    pub etag: Option<String>,
+}
+
+impl ManifestTile {
+    pub fn new(coords: WorldTileCoords, provenance: Option<&TileProvenance>) -> Self {
+        let provenance = provenance.cloned().unwrap_or_default();
+        Self {
+            z: coords.z.into(),
+            x: coords.x,
+            y: coords.y,
+            fetched_at: provenance.fetched_at.and_then(unix_seconds),
+            expires_at: provenance.expires_at.and_then(unix_seconds),
+            etag: provenance.etag,
+        }
+    }
+}
+
+/// The camera of a rendered frame. Angles are in degrees.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ManifestCamera {
+    pub latitude: f64,
+    pub longitude: f64,
+    pub zoom: f64,
+    pub bearing: f64,
+    pub pitch: f64,
+}
+
+/// Describes exactly what was drawn in a frame, so that the inputs which produced an image can be
+/// proven and a re-render which would differ can be detected.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RenderManifest {
+    pub crate_version: String,
+    /// The hash of the style, see [`style_hash`]
+    pub style_hash: String,
+    pub tiles: Vec<ManifestTile>,
+    pub camera: ManifestCamera,
+    /// The size of the rendered image in pixels
+    pub width: u32,
+    pub height: u32,
+    pub pixel_ratio: f64,
+    /// Seconds since the unix epoch
+    pub rendered_at: Option<u64>,
+}
+
+impl RenderManifest {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("manifest is serializable")
+    }
+
+    /// Whether rendering with the inputs of `other` produces the same image, i.e. everything
+    /// except the time of rendering and fetching is equal.
+    pub fn has_same_inputs(&self, other: &RenderManifest) -> bool {
+        let versions = |manifest: &RenderManifest| {
+            manifest
+                .tiles
+                .iter()
+                .map(|tile| (tile.z, tile.x, tile.y, tile.etag.clone()))
+                .collect::<Vec<_>>()
+        };
+
+        self.crate_version == other.crate_version
+            && self.style_hash == other.style_hash
+            && versions(self) == versions(other)
+            && self.camera == other.camera
+            && (self.width, self.height) == (other.width, other.height)
+            && self.pixel_ratio == other.pixel_ratio
+    }
+}
+
+/// A stable hash of the serialized `style`. The keys of objects are sorted before hashing,
+/// because the sources and metadata of a style are serialized in the arbitrary order of a
+/// `HashMap`.
+pub fn style_hash(style: &Style) -> String {
+    let value = serde_json::to_value(style).expect("style is serializable");
+    let json = serde_json::to_vec(&canonical(value)).expect("style is serializable");
+    format!("{:016x}", fnv1a(&json))
+}
+
+/// Rebuilds the objects within `value` with sorted keys.
+fn canonical(value: Value) -> Value {
+    match value {
+        Value::Object(object) => {
+            let mut entries = object.into_iter().collect::<Vec<_>>();
+            entries.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+            Value::Object(
+                entries
+                    .into_iter()
+                    .map(|(key, value)| (key, canonical(value)))
+                    .collect::<Map<_, _>>(),
+            )
+        }
+        Value::Array(values) => Value::Array(values.into_iter().map(canonical).collect()),
+        value => value,
+    }
+}
+
+fn unix_seconds(time: SystemTime) -> Option<u64> {
+    time.duration_since(UNIX_EPOCH)
+        .ok()
+        .map(|duration| duration.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        time::{Duration, UNIX_EPOCH},
+    };
+
+    use super::{style_hash, ManifestCamera, ManifestTile, RenderManifest, TileProvenance};
+    use crate::{
+        coords::{WorldTileCoords, ZoomLevel},
+        style::{
+            source::{Source, VectorSource},
+            Style,
+        },
+    };
+
+    #[test]
+    fn test_manifest() {
+        let provenance = TileProvenance {
+            fetched_at: Some(UNIX_EPOCH + Duration::from_secs(100)),
+            expires_at: None,
+            etag: Some("\"v1\"".to_string()),
+            content_hash: None,
+        };
+        let coords = WorldTileCoords::from((1, 2, ZoomLevel::new(3)));
+
+        let manifest = RenderManifest {
+            crate_version: "0.1.0".to_string(),
+            style_hash: style_hash(&Style::default()),
+            tiles: vec![ManifestTile::new(coords, Some(&provenance))],
+            camera: ManifestCamera {
+                latitude: 0.0,
+                longitude: 0.0,
+                zoom: 3.0,
+                bearing: 0.0,
+                pitch: 0.0,
+            },
+            width: 512,
+            height: 512,
+            pixel_ratio: 1.0,
+            rendered_at: Some(200),
+        };
+
+        let json: serde_json::Value = serde_json::from_str(&manifest.to_json()).unwrap();
+        assert_eq!(json["tiles"][0]["z"], 3);
+        assert_eq!(json["tiles"][0]["fetched_at"], 100);
+        assert_eq!(json["tiles"][0]["etag"], "\"v1\"");
+        assert_eq!(json["style_hash"].as_str().unwrap().len(), 16);
+
+        let mut later = manifest.clone();
+        later.rendered_at = Some(300);
+        assert!(manifest.has_same_inputs(&later));
+        later.tiles[0].etag = Some("\"v2\"".to_string());
+        assert!(!manifest.has_same_inputs(&later));
+    }
+
+    #[test]
+    fn test_style_hash_is_canonical() {
+        let source = |attribution: &str| {
+            Source::Vector(VectorSource {
+                attribution: Some(attribution.to_string()),
+                bounds: None,
+                maxzoom: None,
+                minzoom: None,
+                scheme: None,
+                tiles: None,
+            })
+        };
+        let ids = ["a", "b", "c", "d", "e", "f", "g", "h"];
+
+        // Each map has its own random order of iteration
+        let styles = (0..4)
+            .map(|_| Style {
+                sources: ids
+                    .iter()
+                    .map(|id| (id.to_string(), source(id)))
+                    .collect::<HashMap<_, _>>(),
+                ..Style::default()
+            })
+            .collect::<Vec<_>>();
+
+        let hash = style_hash(&styles[0]);
+        assert!(styles.iter().all(|style| style_hash(style) == hash));
+
+        let mut changed = styles[0].clone();
+        changed.sources.insert("a".to_string(), source("changed"));
+        assert_ne!(style_hash(&changed), hash);
+    }
+}
@@ -1,42 +1,460 @@
-use std::{cell::RefCell, ops::Deref, rc::Rc};
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    fmt, io,
+    ops::Deref,
+    path::PathBuf,
+    rc::Rc,
+    time::{Duration, Instant, SystemTime},
+};
+
+use futures::{stream, StreamExt};
+use thiserror::Error;
 
 use crate::{
     context::MapContext,
-    coords::{WorldCoords, WorldTileCoords, Zoom, ZoomLevel, TILE_SIZE},
-    headless::environment::HeadlessEnvironment,
+    coords::{LatLon, WorldCoords, WorldTileCoords, Zoom, ZoomLevel, EARTH_RADIUS, TILE_SIZE},
+    headless::{
+        environment::HeadlessEnvironment,
+        georeference::GeoReference,
+        manifest::{style_hash, ManifestCamera, ManifestTile, RenderManifest, TileProvenance},
+        overlay::{attribution_text, Overlay},
+        tessellation_cache::{TessellationCache, TessellationCacheKey, TessellationCacheStats},
+        tessellation_store::TessellationStore,
+        window::HeadlessMapWindowConfig,
+    },
     io::{
         apc::{Context, IntoMessage, Message, SendError},
-        source_client::SourceFetchError,
-        source_type::{SourceType, TessellateSource},
+        fallback_source::FallbackTier,
+        gzip,
+        source_client::{HttpClient, SourceFetchError},
+        source_type::SourceType,
+        tile_expiry::StoredTile,
     },
     kernel::Kernel,
     map::MapError,
     plugin::Plugin,
-    render::{eventually::Eventually, Renderer},
+    render::{
+        clip_mask::ClipMask,
+        eventually::Eventually,
+        frustum_culling::{FrustumCullingStats, ViewFrustum},
+        resource::{Head, Surface},
+        settings::Msaa,
+        shaders::GlobalUniforms,
+        ClearColor, MainPassStats, Renderer,
+    },
     schedule::{Schedule, Stage},
-    style::Style,
+    style::{layer::StyleLayer, source::Source, Style, StyleDiff},
     tcs::world::World,
+    tessellation::TessellationError,
+    util::hash::fnv1a,
     vector::{
-        process_vector_tile, AvailableVectorLayerData, DefaultVectorTransferables,
-        LayerTessellated, ProcessVectorContext, VectorBufferPool, VectorLayerData,
+        picking::{FeatureId, VectorRenderMode},
+        process_vector_tile, set_extra_bind_group, AvailableVectorLayerData, BufferPoolError,
+        DefaultVectorTransferables, ExtraBindGroup, LayerTessellated, ProcessVectorContext,
+        ProcessVectorError, UploadErrors, VectorBufferPool, VectorLayerData,
         VectorLayersDataComponent, VectorTileRequest, VectorTransferables,
     },
     view_state::ViewState,
+    window::{MapWindow, MapWindowConfig, WindowSize},
 };
 
+/// The number of tiles which are fetched at the same time by default, see
+/// [`HeadlessMapBuilder::with_fetch_concurrency`].
+pub const DEFAULT_FETCH_CONCURRENCY: usize = 4;
+
+/// How often [`HeadlessMap::shutdown`] checks whether the pending tile requests have finished.
+const SHUTDOWN_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+type HeadlessLayerTessellated =
+    <DefaultVectorTransferables as VectorTransferables>::LayerTessellated;
+
+type ReadyTile = (
+    WorldTileCoords,
+    TileReadiness,
+    Vec<Box<HeadlessLayerTessellated>>,
+);
+
+#[derive(Error, Debug)]
+pub enum HeadlessMapError {
+    #[error("fetching tile {0} failed")]
+    Fetch(WorldTileCoords, #[source] SourceFetchError),
+    #[error("processing tile {0} failed")]
+    Process(WorldTileCoords, #[source] ProcessVectorError),
+    #[error("no tiles are visible for the current camera")]
+    EmptyViewRegion,
+    #[error("the style has no layer {0}")]
+    UnknownLayer(String),
+    #[error("the style has no source {0}")]
+    UnknownSource(String),
+    #[error("neither a tessellation cache nor a tessellation store is configured")]
+    NoTessellationCache,
+    #[error("storing the tessellation of tile {0} failed")]
+    Store(WorldTileCoords, #[source] io::Error),
+    #[error("flushing a source client failed")]
+    Flush(#[source] io::Error),
+}
+
+/// How [`HeadlessMap::fetch_tiles`] handles tiles which do not exist in the source, see
+/// [`SourceFetchError::is_not_found`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MissingTilePolicy {
+    /// The tile is omitted from the result.
+    Skip,
+    /// The tile is returned as [`TileStatus::Empty`].
+    Empty,
+    /// Fetching is aborted with [`HeadlessMapError::Fetch`].
+    Error,
+}
+
+impl Default for MissingTilePolicy {
+    fn default() -> Self {
+        MissingTilePolicy::Empty
+    }
+}
+
+/// A tile which was fetched by [`HeadlessMap::fetch_tiles`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TileStatus {
+    Loaded(Box<[u8]>),
+    /// The source does not contain the tile, so it has no data.
+    Empty,
+}
+
+/// The terminal status of a tile which was awaited with [`HeadlessMap::await_tiles`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TileReadiness {
+    /// The tile was fetched and tessellated.
+    Success,
+    /// The source does not contain the tile.
+    Empty,
+    /// Fetching or tessellating the tile failed.
+    Failed,
+}
+
+/// How long loading a tile took, see [`HeadlessMapBuilder::with_slow_tile_threshold`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TileTimings {
+    pub coords: WorldTileCoords,
+    /// The size of the fetched data
+    pub bytes: usize,
+    /// The number of tessellated features
+    pub features: usize,
+    pub fetch: Duration,
+    pub tessellate: Duration,
+}
+
+impl TileTimings {
+    pub fn total(&self) -> Duration {
+        self.fetch + self.tessellate
+    }
+
+    /// Whether loading took longer than `threshold`. Without a threshold no tile is slow.
+    pub fn is_slow(&self, threshold: Option<Duration>) -> bool {
+        threshold.map_or(false, |threshold| self.total() > threshold)
+    }
+}
+
+/// Formats the timings as `key=value` pairs, so that slow tiles can be found in the logs.
+impl fmt::Display for TileTimings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "coords={} bytes={} features={} total_ms={} fetch_ms={} tessellate_ms={}",
+            self.coords,
+            self.bytes,
+            self.features,
+            self.total().as_millis(),
+            self.fetch.as_millis(),
+            self.tessellate.as_millis()
+        )
+    }
+}
+
+/// Reported for every tile of [`HeadlessMap::pregenerate_with_progress`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PregenerateProgress {
+    /// The tile which was loaded last
+    pub coords: WorldTileCoords,
+    /// The number of tiles which were loaded so far, including the tiles which the sources do
+    /// not contain
+    pub done: usize,
+    /// The number of tiles in the region
+    pub total: usize,
+}
+
+/// Why a style layer was not drawn, see [`RenderReport`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SkipReason {
+    /// The zoom of the camera is outside of the zoom range of the layer.
+    ZoomExcluded,
+    /// The layer has no color.
+    Invisible,
+    /// None of the tiles contain features of the source layer.
+    NoFeatures,
+    /// The buffer pool rejected the geometry of the layer, see [`RenderReport::upload_errors`].
+    TooLarge,
+}
+
+/// Lists which style layers were drawn by [`HeadlessMap::try_render_tile`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RenderReport {
+    /// The ids of the drawn style layers
+    pub drawn_layers: Vec<String>,
+    /// The ids of the skipped style layers
+    pub skipped: Vec<(String, SkipReason)>,
+    /// The features of the drawn tiles which were skipped because their tessellation failed
+    pub tessellation_errors: Vec<TessellationError>,
+    /// The ids of the style layers which were rejected by the buffer pool, together with the
+    /// reason
+    pub upload_errors: Vec<(String, BufferPoolError)>,
+}
+
+impl RenderReport {
+    pub fn was_drawn(&self, layer_id: &str) -> bool {
+        self.drawn_layers.iter().any(|id| id == layer_id)
+    }
+
+    pub fn skip_reason(&self, layer_id: &str) -> Option<SkipReason> {
+        self.skipped
+            .iter()
+            .find(|(id, _)| id == layer_id)
+            .map(|(_, reason)| *reason)
+    }
+
+    /// Adds the `errors` of the buffer pool. Style layers which were rejected for all tiles with
+    /// features, which are counted per source layer in `feature_tiles`, were not drawn.
+    fn add_upload_errors(
+        &mut self,
+        style: &Style,
+        errors: Vec<(String, BufferPoolError)>,
+        feature_tiles: &HashMap<String, usize>,
+    ) {
+        for layer in &style.layers {
+            let Some(source_layer) = &layer.source_layer else { continue; };
+            let rejected = errors.iter().filter(|(id, _)| id == &layer.id).count();
+            if rejected > 0 && rejected >= feature_tiles.get(source_layer).copied().unwrap_or(0) {
+                self.drawn_layers.retain(|id| id != &layer.id);
+                self.skipped.push((layer.id.clone(), SkipReason::TooLarge));
+            }
+        }
+        self.upload_errors = errors;
+    }
+}
+
+/// The state of a tile in the tile repository, see [`TileInfo`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TileInfoStatus {
+    /// The tile is still being processed.
+    Pending,
+    /// None of the requested source layers contain features.
+    Empty,
+    /// Geometry of the tile is stored in the buffer pool.
+    Uploaded,
+    /// The tile contains features, but none of them are stored in the buffer pool. Either no style
+    /// layer uses them, or the buffer pool evicted or rejected the tile.
+    NotUploaded,
+}
+
+/// Describes a tile of the tile repository, see [`HeadlessMap::dump_repository_state`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TileInfo {
+    pub coords: WorldTileCoords,
+    pub status: TileInfoStatus,
+    /// The source layers which contain features
+    pub layers: Vec<String>,
+    /// The requested source layers which are missing in the tile
+    pub missing_layers: Vec<String>,
+    pub vertices: usize,
+    pub indices: usize,
+    /// When the tile was stored in the repository
+    pub timestamp: SystemTime,
+}
+
 pub struct HeadlessMap {
     kernel: Rc<Kernel<HeadlessEnvironment>>,
     schedule: Schedule,
     map_context: MapContext,
+    tessellation_cache: RefCell<Option<TessellationCache<Vec<Box<HeadlessLayerTessellated>>>>>,
+    tessellation_store: Option<TessellationStore>,
+    /// The maximum number of tiles which are fetched at the same time
+    fetch_concurrency: usize,
+    overlay: Option<Overlay>,
+    /// The number of rendered pixels per logical pixel
+    pixel_ratio: f64,
+    repository_state: Vec<TileInfo>,
+    /// The clients of the sources which were added with [`HeadlessMap::add_source`]
+    source_clients: BTreeMap<String, Box<dyn FallbackTier>>,
+    /// Where the tiles which were fetched by this map came from
+    tile_provenance: RefCell<HashMap<WorldTileCoords, TileProvenance>>,
+    /// The features which were skipped when the tiles were last tessellated
+    tessellation_errors: RefCell<HashMap<WorldTileCoords, Vec<TessellationError>>>,
+    last_render_manifest: Option<RenderManifest>,
+    /// The tiles which were awaited with [`HeadlessMap::await_tiles`] and are not rendered yet
+    ready_tiles: RefCell<Vec<ReadyTile>>,
+    slow_tile_threshold: Option<Duration>,
 }
 
-impl HeadlessMap {
-    pub fn new(
-        style: Style,
-        mut renderer: Renderer,
-        kernel: Kernel<HeadlessEnvironment>,
-        plugins: Vec<Box<dyn Plugin<HeadlessEnvironment>>>,
-    ) -> Result<Self, MapError> {
+/// Assembles a [`HeadlessMap`] from a renderer and a kernel, see
+/// [`create_headless_renderer`](crate::headless::create_headless_renderer).
+///
+/// ```no_run
+/// # async fn example() -> Result<(), maplibre::map::MapError> {
+/// use maplibre::{
+///     coords::{LatLon, Zoom},
+///     headless::{create_headless_renderer, map::HeadlessMapBuilder},
+///     style::Style,
+/// };
+///
+/// let (kernel, renderer) = create_headless_renderer(512, None).await;
+/// let map = HeadlessMapBuilder::new(renderer, kernel)
+///     .with_style(Style::default())
+///     .with_camera(LatLon::new(48.137154, 11.576124), Zoom::new(12.0))
+///     .build()?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct HeadlessMapBuilder {
+    renderer: Renderer,
+    kernel: Kernel<HeadlessEnvironment>,
+    style: Style,
+    plugins: Vec<Box<dyn Plugin<HeadlessEnvironment>>>,
+    camera: Option<(LatLon, Zoom)>,
+    size: Option<WindowSize>,
+    msaa: Option<Msaa>,
+    tessellation_cache_capacity: Option<usize>,
+    tessellation_store: Option<TessellationStore>,
+    fetch_concurrency: usize,
+    overlay: Option<Overlay>,
+    pixel_ratio: f64,
+    slow_tile_threshold: Option<Duration>,
+}
+
+impl HeadlessMapBuilder {
+    pub fn new(renderer: Renderer, kernel: Kernel<HeadlessEnvironment>) -> Self {
+        Self {
+            renderer,
+            kernel,
+            style: Style::default(),
+            plugins: Vec::new(),
+            camera: None,
+            size: None,
+            msaa: None,
+            tessellation_cache_capacity: None,
+            tessellation_store: None,
+            fetch_concurrency: DEFAULT_FETCH_CONCURRENCY,
+            overlay: None,
+            pixel_ratio: 1.0,
+            slow_tile_threshold: None,
+        }
+    }
+
+    pub fn with_style(mut self, style: Style) -> Self {
+        self.style = style;
+        self
+    }
+
+    pub fn with_plugins(mut self, plugins: Vec<Box<dyn Plugin<HeadlessEnvironment>>>) -> Self {
+        self.plugins = plugins;
+        self
+    }
+
+    pub fn with_plugin(mut self, plugin: Box<dyn Plugin<HeadlessEnvironment>>) -> Self {
+        self.plugins.push(plugin);
+        self
+    }
+
+    /// Centers the camera at `center` with the given `zoom`. By default the camera looks at the
+    /// center of the tile `0/0/0`.
+    pub fn with_camera(mut self, center: LatLon, zoom: Zoom) -> Self {
+        self.camera = Some((center, zoom));
+        self
+    }
+
+    /// Renders into images of the given `size` instead of the size of the renderer's surface.
+    pub fn with_size(mut self, size: WindowSize) -> Self {
+        self.size = Some(size);
+        self
+    }
+
+    /// Renders `pixel_ratio` pixels per logical pixel, for example 300 / 96 for print output.
+    /// The sizes which are passed to the map are in logical pixels and the rendered images are
+    /// scaled by `pixel_ratio`. The view covers the same region and the same tiles are selected,
+    /// so strokes become proportionally thicker instead of the image being upscaled. The text of
+    /// the overlay is scaled as well.
+    pub fn with_pixel_ratio(mut self, pixel_ratio: f64) -> Self {
+        self.pixel_ratio = pixel_ratio;
+        self
+    }
+
+    /// Overrides the [`Msaa`] of the renderer's settings. The sample count is validated when
+    /// building.
+    pub fn with_msaa(mut self, msaa: Msaa) -> Self {
+        self.msaa = Some(msaa);
+        self
+    }
+
+    /// Keeps up to `capacity` tessellated tiles in memory, see [`TessellationCache`].
+    pub fn with_tessellation_cache(mut self, capacity: usize) -> Self {
+        self.tessellation_cache_capacity = Some(capacity);
+        self
+    }
+
+    /// Reads tessellated tiles from the `directory` before tessellating them, see
+    /// [`TessellationStore`]. The tiles are written by [`HeadlessMap::pregenerate`].
+    pub fn with_tessellation_store(mut self, directory: impl Into<PathBuf>) -> Self {
+        self.tessellation_store = Some(TessellationStore::new(directory));
+        self
+    }
+
+    /// Fetches up to `concurrency` tiles at the same time when several tiles are loaded at once,
+    /// see [`DEFAULT_FETCH_CONCURRENCY`]. A `concurrency` of 0 is treated as 1.
+    pub fn with_fetch_concurrency(mut self, concurrency: usize) -> Self {
+        self.fetch_concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Draws the `overlay` onto every frame which is returned by [`HeadlessMap::read_frame`].
+    pub fn with_overlay(mut self, overlay: Overlay) -> Self {
+        self.overlay = Some(overlay);
+        self
+    }
+
+    /// Logs a warning with the [`TileTimings`] of every tile whose fetching and tessellation
+    /// takes longer than `threshold`. Tiles are not timed by default.
+    pub fn with_slow_tile_threshold(mut self, threshold: Duration) -> Self {
+        self.slow_tile_threshold = Some(threshold);
+        self
+    }
+
+    /// Checks that the sample count can be used with the surface format of the renderer.
+    fn validate_msaa(renderer: &Renderer, msaa: Msaa) -> Result<(), MapError> {
+        // WebGPU currently only supports 1 or 4 samples
+        let supported = match msaa.samples {
+            1 => true,
+            4 => renderer
+                .adapter
+                .get_texture_format_features(renderer.state().surface().surface_format())
+                .flags
+                .contains(wgpu::TextureFormatFeatureFlags::MULTISAMPLE),
+            _ => false,
+        };
+
+        if supported {
+            Ok(())
+        } else {
+            Err(MapError::UnsupportedMsaaSamples(msaa.samples))
+        }
+    }
+
+    pub fn build(self) -> Result<HeadlessMap, MapError> {
+        let mut renderer = self.renderer;
+
+        if let Some(msaa) = self.msaa {
+            Self::validate_msaa(&renderer, msaa)?;
+            renderer.settings.msaa = msaa;
+        }
+
         let window_size = renderer.state().surface().size();
 
         let view_state = ViewState::new(
@@ -49,9 +467,9 @@ impl HeadlessMap {
 
         let mut world = World::default();
         let mut schedule = Schedule::default();
-        let kernel = Rc::new(kernel);
+        let kernel = Rc::new(self.kernel);
 
-        for plugin in &plugins {
+        for plugin in &self.plugins {
             plugin.build(
                 &mut schedule,
                 kernel.clone(),
@@ -60,100 +478,1526 @@ impl HeadlessMap {
             );
         }
 
-        Ok(Self {
+        let mut map = HeadlessMap {
             kernel,
             map_context: MapContext {
-                style,
+                style: self.style,
                 view_state,
                 world,
                 renderer,
             },
             schedule,
-        })
+            tessellation_cache: RefCell::new(
+                self.tessellation_cache_capacity.map(TessellationCache::new),
+            ),
+            tessellation_store: self.tessellation_store,
+            fetch_concurrency: self.fetch_concurrency,
+            overlay: self.overlay.map(|overlay| overlay.scaled(self.pixel_ratio)),
+            pixel_ratio: self.pixel_ratio,
+            repository_state: Vec::new(),
+            source_clients: BTreeMap::new(),
+            tile_provenance: RefCell::new(HashMap::new()),
+            tessellation_errors: RefCell::new(HashMap::new()),
+            last_render_manifest: None,
+            ready_tiles: RefCell::new(Vec::new()),
+            slow_tile_threshold: self.slow_tile_threshold,
+        };
+
+        map.resize(self.size.unwrap_or(window_size));
+
+        if let Some((center, zoom)) = self.camera {
+            map.move_camera(center, zoom);
+        }
+
+        Ok(map)
+    }
+}
+
+impl HeadlessMap {
+    /// Creates a map with the default camera. See [`HeadlessMapBuilder`] for more options.
+    pub fn new(
+        style: Style,
+        renderer: Renderer,
+        kernel: Kernel<HeadlessEnvironment>,
+        plugins: Vec<Box<dyn Plugin<HeadlessEnvironment>>>,
+    ) -> Result<Self, MapError> {
+        HeadlessMapBuilder::new(renderer, kernel)
+            .with_style(style)
+            .with_plugins(plugins)
+            .build()
+    }
+
+    pub fn render_tile(&mut self, layers: Vec<Box<HeadlessLayerTessellated>>) {
+        self.try_render_tile(layers);
+    }
+
+    /// Same as [`HeadlessMap::render_tile`], but reports which style layers were drawn.
+    pub fn try_render_tile(&mut self, layers: Vec<Box<HeadlessLayerTessellated>>) -> RenderReport {
+        self.render_tiles(vec![((0, 0, ZoomLevel::default()).into(), layers)])
+    }
+
+    pub fn style(&self) -> &Style {
+        &self.map_context.style
     }
 
-    pub fn render_tile(
+    /// Renders only the style layer `layer_id` of the tile over a transparent background and
+    /// returns the RGBA pixels. The overlay is not drawn.
+    pub fn render_layer(
         &mut self,
-        layers: Vec<Box<<DefaultVectorTransferables as VectorTransferables>::LayerTessellated>>,
-    ) {
+        layer_id: &str,
+        layers: Vec<Box<HeadlessLayerTessellated>>,
+    ) -> Result<Vec<u8>, HeadlessMapError> {
+        let style = &self.map_context.style;
+        let Some(style_layer) = style.layers.iter().find(|layer| layer.id == layer_id) else {
+            return Err(HeadlessMapError::UnknownLayer(layer_id.to_string()));
+        };
+
+        // The tessellation cache is kept, because the tessellation does not depend on the
+        // other layers
+        let isolated = Style {
+            layers: vec![style_layer.clone()],
+            ..style.clone()
+        };
+        let style = std::mem::replace(&mut self.map_context.style, isolated);
+
+        let clear_color = self
+            .map_context
+            .world
+            .resources
+            .get_mut::<ClearColor>()
+            .map(|clear_color| {
+                std::mem::replace(clear_color, ClearColor(wgpu::Color::TRANSPARENT))
+            });
+
+        self.render_tile(layers);
+
+        if let (Some(previous), Some(clear_color)) = (
+            clear_color,
+            self.map_context.world.resources.get_mut::<ClearColor>(),
+        ) {
+            *clear_color = previous;
+        }
+        self.map_context.style = style;
+
+        Ok(self
+            .read_raw_frame(false)
+            .expect("headless map always renders to a texture"))
+    }
+
+    /// Replaces the style of this map with `new` and returns the differences to the previous
+    /// style.
+    ///
+    /// Paint changes take effect with the next render, because the paint is applied when
+    /// tessellated layers are uploaded. Only the layers in [`StyleDiff::added`] and
+    /// [`StyleDiff::retessellate`] need to be processed again, see [`HeadlessMap::process_tile`].
+    ///
+    /// Cached tiles which contain source layers of added, removed or retessellated layers are
+    /// evicted from the tessellation cache. The other cached tiles are kept.
+    pub fn apply_style(&mut self, new: Style) -> StyleDiff {
+        let diff = self.map_context.style.diff(&new);
+        let source_layers = changed_source_layers(&self.map_context.style, &new, &diff);
+        self.map_context.style = new;
+        if let Some(cache) = self.tessellation_cache.get_mut() {
+            cache.retain(|layers| {
+                !layers
+                    .iter()
+                    .any(|layer| source_layers.contains(&layer.layer_data.name))
+            });
+        }
+        diff
+    }
+
+    /// Adds the `source` with the given `id` to the style. Its tiles are fetched with `client`
+    /// and merged into the tiles of the default source, see [`HeadlessMap::fetch_tile`].
+    /// Replaces a source with the same `id`.
+    ///
+    /// The source is not drawn until a layer which reads from it is added with
+    /// [`HeadlessMap::add_layer`].
+    pub fn add_source(
+        &mut self,
+        id: impl Into<String>,
+        source: Source,
+        client: impl HttpClient,
+    ) -> StyleDiff {
+        let id = id.into();
+        let mut style = self.map_context.style.clone();
+        style.sources.insert(id.clone(), source);
+        self.source_clients.insert(id, Box::new(client));
+
+        // Cached tiles were tessellated without the data of the new source
+        if let Some(cache) = self.tessellation_cache.get_mut() {
+            cache.clear();
+        }
+        self.apply_style(style)
+    }
+
+    /// Removes the source `id` together with the layers which read from it. The tessellated
+    /// layers of the source are released from the tiles which were awaited with
+    /// [`HeadlessMap::await_tiles`] and the tessellation cache is cleared.
+    pub fn remove_source(&mut self, id: &str) -> Result<StyleDiff, HeadlessMapError> {
+        let mut style = self.map_context.style.clone();
+        let released = remove_source(&mut style, id)?;
+        self.source_clients.remove(id);
+
+        self.release_tiles(&released);
+        Ok(self.apply_style(style))
+    }
+
+    /// Adds `layer` on top of the other layers of the style. The tiles have to be processed again
+    /// in order to draw it, see [`StyleDiff::added`].
+    pub fn add_layer(&mut self, layer: StyleLayer) -> Result<StyleDiff, HeadlessMapError> {
+        let mut style = self.map_context.style.clone();
+        add_layer(&mut style, layer)?;
+        Ok(self.apply_style(style))
+    }
+
+    /// Removes the layer `layer_id` from the style.
+    pub fn remove_layer(&mut self, layer_id: &str) -> Result<StyleDiff, HeadlessMapError> {
+        let mut style = self.map_context.style.clone();
+        remove_layer(&mut style, layer_id)?;
+        Ok(self.apply_style(style))
+    }
+
+    /// Drops the tessellated `source_layers` of the awaited tiles and clears the tessellation
+    /// cache, because cached tiles were tessellated from data of all sources.
+    fn release_tiles(&mut self, source_layers: &[String]) {
+        release_layers(self.ready_tiles.get_mut(), source_layers);
+        if let Some(cache) = self.tessellation_cache.get_mut() {
+            cache.clear();
+        }
+    }
+
+    /// Renders all `tiles` within a single frame. Each tile is placed at its coordinates.
+    pub fn render_tiles(
+        &mut self,
+        tiles: Vec<(WorldTileCoords, Vec<Box<HeadlessLayerTessellated>>)>,
+    ) -> RenderReport {
+        let mut report = self.create_report(&tiles);
+        let timestamp = SystemTime::now();
+        let manifest = self.create_manifest(&tiles, timestamp);
         let context = &mut self.map_context;
-        let tiles = &mut context.world.tiles;
 
-        tiles
-            .spawn_mut((0, 0, ZoomLevel::default()).into())
-            .expect("unable to spawn tile")
-            .insert(VectorLayersDataComponent {
-                done: true,
-                layers: layers
-                    .into_iter()
-                    .map(|layer| {
-                        VectorLayerData::Available(AvailableVectorLayerData {
-                            coords: layer.coords,
-                            source_layer: layer.layer_data.name,
-                            buffer: layer.buffer,
-                            feature_indices: layer.feature_indices,
+        let mut feature_tiles: HashMap<String, usize> = HashMap::new();
+        for layer in tiles.iter().flat_map(|(_, layers)| layers) {
+            if layer.buffer.usable_indices > 0 {
+                *feature_tiles
+                    .entry(layer.layer_data.name.clone())
+                    .or_default() += 1;
+            }
+        }
+
+        for (coords, layers) in tiles {
+            context
+                .world
+                .tiles
+                .spawn_mut(coords)
+                .expect("unable to spawn tile")
+                .insert(VectorLayersDataComponent {
+                    done: true,
+                    layers: layers
+                        .into_iter()
+                        .map(|layer| {
+                            VectorLayerData::Available(AvailableVectorLayerData {
+                                coords: layer.coords,
+                                source_layer: layer.layer_data.name,
+                                buffer: layer.buffer,
+                                feature_indices: layer.feature_indices,
+                            })
                         })
-                    })
-                    .collect::<Vec<_>>(),
-            });
+                        .collect::<Vec<_>>(),
+                });
+        }
 
         self.schedule.run(context);
 
+        self.repository_state = Self::repository_state(&context.world, timestamp);
+        self.last_render_manifest = Some(manifest);
+
         let resources = &mut context.world.resources;
         let tiles = &mut context.world.tiles;
 
         tiles.clear();
 
+        let errors = std::mem::take(&mut resources.get_or_init_mut::<UploadErrors>().0);
+        report.add_upload_errors(&context.style, errors, &feature_tiles);
+
         let pool = resources
             .query_mut::<&mut Eventually<VectorBufferPool>>() // FIXME tcs: we access internals of the vector plugin here
             .expect("VectorBufferPool not found")
             .expect_initialized_mut("VectorBufferPool not initialized");
 
         pool.clear();
+
+        report
+    }
+
+    /// Describes the tiles of the last rendered frame. The tiles are removed from the repository
+    /// after each frame, so this is the state right after the frame was drawn.
+    ///
+    /// This is meant for debugging why a tile is not visible, for example because it contained
+    /// no features or was not stored in the buffer pool.
+    pub fn dump_repository_state(&self) -> Vec<TileInfo> {
+        self.repository_state.clone()
+    }
+
+    fn repository_state(world: &World, timestamp: SystemTime) -> Vec<TileInfo> {
+        let buffer_pool = match world.resources.get::<Eventually<VectorBufferPool>>() {
+            Some(Eventually::Initialized(pool)) => Some(pool),
+            _ => None,
+        };
+
+        let mut infos = Vec::new();
+        for tile in world.tiles.tiles.values() {
+            let coords = tile.coords;
+            let Some(component) = world
+                .tiles
+                .query::<&VectorLayersDataComponent>(coords) else { continue; };
+
+            let mut info = TileInfo {
+                coords,
+                status: TileInfoStatus::Pending,
+                layers: Vec::new(),
+                missing_layers: Vec::new(),
+                vertices: 0,
+                indices: 0,
+                timestamp,
+            };
+
+            for layer in &component.layers {
+                match layer {
+                    VectorLayerData::Available(layer) => {
+                        info.layers.push(layer.source_layer.clone());
+                        info.vertices += layer.buffer.buffer.vertices.len();
+                        info.indices += layer.buffer.usable_indices as usize;
+                    }
+                    VectorLayerData::Missing(layer) => {
+                        info.missing_layers.push(layer.source_layer.clone())
+                    }
+                }
+            }
+
+            let uploaded = buffer_pool
+                .and_then(|pool| pool.index().get_layers(coords))
+                .map_or(false, |entries| !entries.is_empty());
+
+            info.status = if !component.done {
+                TileInfoStatus::Pending
+            } else if uploaded {
+                TileInfoStatus::Uploaded
+            } else if info.indices == 0 {
+                TileInfoStatus::Empty
+            } else {
+                TileInfoStatus::NotUploaded
+            };
+
+            infos.push(info);
+        }
+
+        infos
+    }
+
+    /// Describes the inputs of the last rendered frame, so that it can be proven which tiles,
+    /// style and camera produced the image. See [`RenderManifest::to_json`] for serializing it.
+    ///
+    /// The fetch time, expiry and `ETag` of a tile are known if it was fetched by the map, for
+    /// example with [`HeadlessMap::fetch_tile`]. The expiry and `ETag` are reported by the
+    /// [`HttpClient::fetch_stored`] of the source client.
+    pub fn last_render_manifest(&self) -> Option<&RenderManifest> {
+        self.last_render_manifest.as_ref()
+    }
+
+    fn create_manifest(
+        &self,
+        tiles: &[(WorldTileCoords, Vec<Box<HeadlessLayerTessellated>>)],
+        timestamp: SystemTime,
+    ) -> RenderManifest {
+        let view_state = &self.map_context.view_state;
+        let camera = view_state.camera();
+        let position = camera.position();
+        let center = WorldCoords::at_ground(position.x, position.y).to_lat_lon(view_state.zoom());
+        let size = self.map_context.renderer.surface().size();
+        let provenance = self.tile_provenance.borrow();
+
+        RenderManifest {
+            crate_version: env!("CARGO_PKG_VERSION").to_string(),
+            style_hash: style_hash(&self.map_context.style),
+            tiles: tiles
+                .iter()
+                .map(|(coords, _)| ManifestTile::new(*coords, provenance.get(coords)))
+                .collect(),
+            camera: ManifestCamera {
+                latitude: center.latitude,
+                longitude: center.longitude,
+                zoom: view_state.zoom().into(),
+                bearing: cgmath::Deg::from(camera.bearing()).0,
+                pitch: cgmath::Deg::from(camera.pitch()).0,
+            },
+            width: size.width(),
+            height: size.height(),
+            pixel_ratio: self.pixel_ratio,
+            rendered_at: timestamp
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .ok()
+                .map(|duration| duration.as_secs()),
+        }
+    }
+
+    /// Decides for each style layer whether it is drawn, like the vector upload and queue
+    /// systems do.
+    fn create_report(
+        &self,
+        tiles: &[(WorldTileCoords, Vec<Box<HeadlessLayerTessellated>>)],
+    ) -> RenderReport {
+        let zoom = self.map_context.view_state.zoom();
+        let errors = self.tessellation_errors.borrow();
+        let mut report = RenderReport {
+            tessellation_errors: tiles
+                .iter()
+                .filter_map(|(coords, _)| errors.get(coords))
+                .flatten()
+                .cloned()
+                .collect(),
+            ..RenderReport::default()
+        };
+
+        for style_layer in &self.map_context.style.layers {
+            let has_features = |source_layer: &String| {
+                tiles.iter().flat_map(|(_, layers)| layers).any(|layer| {
+                    &layer.layer_data.name == source_layer && layer.buffer.usable_indices > 0
+                })
+            };
+
+            let reason = if !style_layer.is_visible_at(zoom) {
+                Some(SkipReason::ZoomExcluded)
+            } else if style_layer
+                .paint
+                .as_ref()
+                .and_then(|paint| paint.get_color())
+                .is_none()
+            {
+                Some(SkipReason::Invisible)
+            } else if !style_layer
+                .source_layer
+                .as_ref()
+                .map_or(false, has_features)
+            {
+                Some(SkipReason::NoFeatures)
+            } else {
+                None
+            };
+
+            match reason {
+                Some(reason) => report.skipped.push((style_layer.id.clone(), reason)),
+                None => report.drawn_layers.push(style_layer.id.clone()),
+            }
+        }
+
+        report
+    }
+
+    /// Renders the map around `center` at `zoom` into an image of the given logical `size` and
+    /// returns its RGBA pixels, see [`HeadlessMapBuilder::with_pixel_ratio`]. The tiles which
+    /// cover the view are fetched concurrently, see [`HeadlessMapBuilder::with_fetch_concurrency`],
+    /// and are tessellated and rendered within a single frame. All source layers of the style are
+    /// requested. Tiles which do not exist in the source are left empty.
+    pub async fn render_thumbnail(
+        &mut self,
+        center: LatLon,
+        zoom: Zoom,
+        size: WindowSize,
+    ) -> Result<Vec<u8>, HeadlessMapError> {
+        self.resize(size);
+        self.move_camera(center, zoom);
+
+        let visible_tiles = self
+            .map_context
+            .view_state
+            .create_view_region()
+            .ok_or(HeadlessMapError::EmptyViewRegion)?
+            .iter()
+            .filter(|coords| coords.build_quad_key().is_some())
+            .collect::<Vec<_>>();
+
+        let source_layers = self.required_source_layers();
+        let source_layers = source_layers
+            .iter()
+            .map(|layer| layer.as_str())
+            .collect::<Vec<_>>();
+
+        let tiles = self.load_tiles(visible_tiles, &source_layers).await?;
+        self.render_tiles(tiles);
+
+        Ok(self
+            .read_frame(false)
+            .expect("headless map always renders to a texture"))
+    }
+
+    /// Loads the `tiles` with [`HeadlessMap::load_tile`] and fetches up to
+    /// [`HeadlessMapBuilder::with_fetch_concurrency`] of them at the same time. Tiles which do not
+    /// exist in the source are skipped and the first other error aborts. The loaded tiles are
+    /// returned in the order of `tiles`.
+    async fn load_tiles(
+        &self,
+        tiles: Vec<WorldTileCoords>,
+        source_layers: &[&str],
+    ) -> Result<Vec<(WorldTileCoords, Vec<Box<HeadlessLayerTessellated>>)>, HeadlessMapError> {
+        let mut loads = stream::iter(tiles)
+            .map(|coords| async move { (coords, self.load_tile(coords, source_layers).await) })
+            .buffered(self.fetch_concurrency);
+
+        let mut loaded = Vec::new();
+        while let Some((coords, layers)) = loads.next().await {
+            if let Some(layers) = layers? {
+                loaded.push((coords, layers));
+            }
+        }
+        Ok(loaded)
+    }
+
+    /// Fetches and tessellates `tiles` and keeps them until they are rendered with
+    /// [`HeadlessMap::render_ready_tiles`]. Resolves once every tile reached a terminal
+    /// [`TileReadiness`], so that the next frame is not rendered with missing tiles. Tiles which
+    /// are already ready are skipped and failed tiles are retried.
+    ///
+    /// All source layers of the style are requested. If a tile failed, the error of the first
+    /// failed tile is returned after all other tiles were processed.
+    pub async fn await_tiles(&self, tiles: &[WorldTileCoords]) -> Result<(), HeadlessMapError> {
+        let source_layers = self.required_source_layers();
+        let source_layers = source_layers
+            .iter()
+            .map(|layer| layer.as_str())
+            .collect::<Vec<_>>();
+
+        let mut first_error = None;
+        for coords in tiles.iter().copied() {
+            match self.tile_readiness(coords) {
+                Some(TileReadiness::Success | TileReadiness::Empty) => continue,
+                Some(TileReadiness::Failed) => self
+                    .ready_tiles
+                    .borrow_mut()
+                    .retain(|(ready, _, _)| *ready != coords),
+                None => {}
+            }
+
+            let result = self
+                .load_tile(coords, &source_layers)
+                .await
+                .map(|layers| match layers {
+                    Some(layers) => (TileReadiness::Success, layers),
+                    None => (TileReadiness::Empty, Vec::new()),
+                });
+
+            let (readiness, layers) = result.unwrap_or_else(|e| {
+                log::error!("tile {coords} is not ready: {e}");
+                first_error.get_or_insert(e);
+                (TileReadiness::Failed, Vec::new())
+            });
+            self.ready_tiles
+                .borrow_mut()
+                .push((coords, readiness, layers));
+        }
+
+        first_error.map_or(Ok(()), Err)
+    }
+
+    /// Fetches and tessellates the tile at `coords`. Returns `None` if the source does not contain
+    /// the tile. Logs the [`TileTimings`] if the tile is slower than the slow tile threshold.
+    ///
+    /// The tile is not fetched if its tessellation is cached and the tile is not expired.
+    async fn load_tile(
+        &self,
+        coords: WorldTileCoords,
+        source_layers: &[&str],
+    ) -> Result<Option<Vec<Box<HeadlessLayerTessellated>>>, HeadlessMapError> {
+        if let Some(key) = self.cached_key(coords, source_layers) {
+            let layers = self
+                .tessellation_cache
+                .borrow_mut()
+                .as_mut()
+                .and_then(|cache| cache.get(&key));
+            if layers.is_some() {
+                return Ok(layers);
+            }
+        }
+
+        let start = Instant::now();
+        let data = match self.fetch_tile(coords).await {
+            Ok(data) => data,
+            Err(e) if e.is_not_found() => return Ok(None),
+            Err(e) => return Err(HeadlessMapError::Fetch(coords, e)),
+        };
+        let fetch = start.elapsed();
+        let bytes = data.len();
+
+        let start = Instant::now();
+        let layers = self
+            .process_tile_at(coords, data, source_layers)
+            .map_err(|e| HeadlessMapError::Process(coords, e))?;
+
+        let timings = TileTimings {
+            coords,
+            bytes,
+            features: layers.iter().map(|layer| layer.feature_indices.len()).sum(),
+            fetch,
+            tessellate: start.elapsed(),
+        };
+        if timings.is_slow(self.slow_tile_threshold) {
+            log::warn!("slow tile: {timings}");
+        }
+
+        Ok(Some(layers))
+    }
+
+    /// Returns the status of a tile which was awaited with [`HeadlessMap::await_tiles`] and is
+    /// not rendered yet.
+    pub fn tile_readiness(&self, coords: WorldTileCoords) -> Option<TileReadiness> {
+        self.ready_tiles
+            .borrow()
+            .iter()
+            .find(|(ready, _, _)| *ready == coords)
+            .map(|(_, readiness, _)| *readiness)
+    }
+
+    /// Renders all tiles which were awaited with [`HeadlessMap::await_tiles`] within a single
+    /// frame and forgets them afterwards.
+    pub fn render_ready_tiles(&mut self) -> RenderReport {
+        let tiles = self
+            .ready_tiles
+            .take()
+            .into_iter()
+            .filter(|(_, readiness, _)| *readiness == TileReadiness::Success)
+            .map(|(coords, _, layers)| (coords, layers))
+            .collect();
+        self.render_tiles(tiles)
+    }
+
+    /// The key of the cached tessellation of the tile which was last fetched from `coords`, if it
+    /// is cached and not expired.
+    fn cached_key(
+        &self,
+        coords: WorldTileCoords,
+        source_layers: &[&str],
+    ) -> Option<TessellationCacheKey> {
+        valid_cache_key(
+            self.tessellation_cache.borrow().as_ref()?,
+            self.tile_provenance.borrow().get(&coords)?,
+            coords,
+            &self.map_context.style,
+            source_layers,
+            SystemTime::now(),
+        )
+    }
+
+    /// The source layers which are required by the style, sorted and without duplicates.
+    pub(crate) fn required_source_layers(&self) -> Vec<String> {
+        let mut source_layers = self
+            .map_context
+            .style
+            .required_source_layers()
+            .into_values()
+            .flatten()
+            .collect::<Vec<_>>();
+        source_layers.sort_unstable();
+        source_layers.dedup();
+        source_layers
+    }
+
+    /// Fetches the tiles in a ring of `radius` tiles around the current view and their parents
+    /// one zoom level out, without rendering them. The tiles are tessellated into the
+    /// tessellation cache, so that they are not fetched again when they are rendered while they
+    /// are valid, see [`HeadlessMapBuilder::with_tessellation_cache`]. Without tessellation cache
+    /// the tiles are only fetched, which warms the cache of the source client.
+    ///
+    /// Tiles beyond the highest `maxzoom` of the vector sources in the style are replaced by
+    /// their ancestor at that zoom level. Tiles which are cached or awaited with
+    /// [`HeadlessMap::await_tiles`] are skipped. Fetching errors are logged and do not abort the
+    /// prefetch. Returns the successfully fetched tiles.
+    pub async fn prefetch(&self, radius: u32) -> Vec<WorldTileCoords> {
+        let Some(view_region) = self
+            .map_context
+            .view_state
+            .create_padded_view_region(radius) else { return Vec::new(); };
+
+        let maxzoom = self
+            .map_context
+            .style
+            .sources
+            .values()
+            .filter_map(|source| match source {
+                Source::Vector(source) => source.maxzoom,
+                Source::Raster(_) => None,
+            })
+            .max()
+            .map(ZoomLevel::from);
+
+        let clamp_to_maxzoom = |mut coords: WorldTileCoords| -> Option<WorldTileCoords> {
+            if let Some(maxzoom) = maxzoom {
+                while coords.z > maxzoom {
+                    coords = coords.get_parent()?;
+                }
+            }
+            Some(coords)
+        };
+
+        let mut tiles = BTreeSet::new();
+        for coords in view_region.iter() {
+            if coords.build_quad_key().is_none() {
+                continue;
+            }
+
+            tiles.extend(clamp_to_maxzoom(coords));
+            tiles.extend(coords.get_parent().and_then(clamp_to_maxzoom));
+        }
+
+        let source_layers = self.required_source_layers();
+        let source_layers = source_layers
+            .iter()
+            .map(|layer| layer.as_str())
+            .collect::<Vec<_>>();
+        let tessellate = self.tessellation_cache.borrow().is_some();
+
+        let mut fetched = Vec::with_capacity(tiles.len());
+        for coords in tiles {
+            if self.tile_readiness(coords).is_some()
+                || self.cached_key(coords, &source_layers).is_some()
+            {
+                continue;
+            }
+
+            let result = if tessellate {
+                self.load_tile(coords, &source_layers)
+                    .await
+                    .map(|layers| layers.is_some())
+            } else {
+                match self.fetch_tile(coords).await {
+                    Ok(_) => Ok(true),
+                    Err(e) if e.is_not_found() => Ok(false),
+                    Err(e) => Err(HeadlessMapError::Fetch(coords, e)),
+                }
+            };
+
+            match result {
+                Ok(true) => fetched.push(coords),
+                Ok(false) => log::debug!("tile {coords} does not exist"),
+                Err(e) => log::warn!("prefetching tile {coords} failed: {e}"),
+            }
+        }
+
+        fetched
+    }
+
+    /// Fetches and tessellates every tile within `bounds` from `min_zoom` to `max_zoom`. The
+    /// `bounds` are `(west, south, east, north)` in WGS84, like the bounds of a
+    /// [`VectorSource`](crate::style::source::VectorSource).
+    ///
+    /// The tessellated tiles are written into the tessellation store, see
+    /// [`HeadlessMapBuilder::with_tessellation_store`], so that a later process with the same
+    /// style can render the region without tessellating it again. Tiles which are stored already
+    /// are not written again. The tiles are also kept by the tessellation cache, see
+    /// [`HeadlessMapBuilder::with_tessellation_cache`]. Fails with
+    /// [`HeadlessMapError::NoTessellationCache`] if neither is configured.
+    ///
+    /// Stored tiles are only read after the tile has been fetched, because they are identified
+    /// by the fetched data. To render the region without network access, the source client also
+    /// needs to store the fetched tiles, for example a `DiskCacheSource`.
+    ///
+    /// Up to [`HeadlessMapBuilder::with_fetch_concurrency`] tiles are fetched at the same time.
+    /// Tiles which the sources do not contain are skipped and the first other error aborts.
+    /// Returns the number of tiles which were tessellated or found in the caches.
+    pub async fn pregenerate(
+        &self,
+        bounds: (f64, f64, f64, f64),
+        min_zoom: ZoomLevel,
+        max_zoom: ZoomLevel,
+    ) -> Result<usize, HeadlessMapError> {
+        self.pregenerate_with_progress(bounds, min_zoom, max_zoom, |progress| {
+            log::info!("pregenerated {}/{} tiles", progress.done, progress.total)
+        })
+        .await
+    }
+
+    /// Same as [`HeadlessMap::pregenerate`], but calls `on_progress` after every tile.
+    pub async fn pregenerate_with_progress(
+        &self,
+        bounds: (f64, f64, f64, f64),
+        min_zoom: ZoomLevel,
+        max_zoom: ZoomLevel,
+        mut on_progress: impl FnMut(PregenerateProgress),
+    ) -> Result<usize, HeadlessMapError> {
+        if self.tessellation_cache.borrow().is_none() && self.tessellation_store.is_none() {
+            return Err(HeadlessMapError::NoTessellationCache);
+        }
+
+        let source_layers = self.required_source_layers();
+        let source_layers = source_layers
+            .iter()
+            .map(|layer| layer.as_str())
+            .collect::<Vec<_>>();
+
+        let tiles = (u8::from(min_zoom)..=u8::from(max_zoom))
+            .flat_map(|z| tiles_in_bounds(bounds, ZoomLevel::new(z)))
+            .collect::<Vec<_>>();
+        let total = tiles.len();
+
+        let source_layers = source_layers.as_slice();
+        let mut loads = stream::iter(tiles)
+            .map(|coords| async move { (coords, self.load_tile(coords, source_layers).await) })
+            .buffer_unordered(self.fetch_concurrency);
+
+        let mut processed = 0;
+        let mut done = 0;
+        while let Some((coords, layers)) = loads.next().await {
+            if let Some(layers) = layers? {
+                self.store_tessellation(coords, source_layers, &layers)?;
+                processed += 1;
+            }
+
+            done += 1;
+            on_progress(PregenerateProgress {
+                coords,
+                done,
+                total,
+            });
+        }
+
+        Ok(processed)
+    }
+
+    /// Writes the `layers` of the tile at `coords` into the tessellation store, unless they are
+    /// stored already. The key is the same as the key of the tessellation cache.
+    fn store_tessellation(
+        &self,
+        coords: WorldTileCoords,
+        source_layers: &[&str],
+        layers: &[Box<HeadlessLayerTessellated>],
+    ) -> Result<(), HeadlessMapError> {
+        let Some(store) = &self.tessellation_store else { return Ok(()); };
+        let Some(content_hash) = self
+            .tile_provenance
+            .borrow()
+            .get(&coords)
+            .and_then(|provenance| provenance.content_hash) else { return Ok(()); };
+
+        let key =
+            TessellationCacheKey::new(coords, &self.map_context.style, source_layers, content_hash);
+        if store.contains(&key) {
+            return Ok(());
+        }
+        store
+            .write(&key, layers)
+            .map_err(|e| HeadlessMapError::Store(coords, e))
+    }
+
+    /// Centers the camera at `center` and sets its `zoom`.
+    fn move_camera(&mut self, center: LatLon, zoom: Zoom) {
+        let view_state = &mut self.map_context.view_state;
+        view_state.update_zoom(zoom);
+        let position = WorldCoords::from_lat_lon(center, zoom);
+        let camera = view_state.camera_mut();
+        let height = camera.position().z;
+        camera.move_to(cgmath::Point3::new(position.x, position.y, height));
+    }
+
+    /// Rotates the map clockwise by `deg` degrees, so that the direction `deg` degrees east of
+    /// north is at the top of the rendered image.
+    pub fn set_bearing(&mut self, deg: f64) {
+        self.map_context
+            .view_state
+            .camera_mut()
+            .set_bearing(cgmath::Deg(deg));
+    }
+
+    /// Tilts the camera by `deg` degrees away from looking straight down. The pitch is clamped
+    /// to the range which is supported by the camera.
+    pub fn set_pitch(&mut self, deg: f64) {
+        self.map_context
+            .view_state
+            .camera_mut()
+            .set_pitch(cgmath::Deg(deg));
+    }
+
+    /// Moves the camera center by `dx_pixels` to the right and `dy_pixels` down in logical
+    /// pixels, so that the point which was at that offset from the center becomes the new
+    /// center. Bearing and pitch of the camera are taken into account. To follow a drag gesture,
+    /// pass the negated movement of the pointer.
+    pub fn pan_by(&mut self, dx_pixels: f64, dy_pixels: f64) {
+        let size = self.map_context.renderer.surface().size();
+        let center = cgmath::Vector2::new(size.width() as f64 / 2.0, size.height() as f64 / 2.0);
+        let (dx_pixels, dy_pixels) = (dx_pixels * self.pixel_ratio, dy_pixels * self.pixel_ratio);
+
+        let view_state = &mut self.map_context.view_state;
+        let inverted_view_proj = view_state.view_projection().invert();
+        let camera = view_state.camera();
+        let (Some(from), Some(to)) = (
+            camera.window_to_world_at_ground(&center, &inverted_view_proj, false),
+            camera.window_to_world_at_ground(
+                &(center + cgmath::Vector2::new(dx_pixels, dy_pixels)),
+                &inverted_view_proj,
+                false,
+            ),
+        ) else { return; };
+
+        let delta = to - from;
+        view_state
+            .camera_mut()
+            .move_relative(cgmath::Vector3::new(delta.x, delta.y, 0.0));
+    }
+
+    /// Recreates the offscreen render target if its size differs from the logical `size`
+    /// multiplied with the pixel ratio.
+    fn resize(&mut self, size: WindowSize) {
+        let size = size.scaled(self.pixel_ratio);
+        let renderer = &mut self.map_context.renderer;
+        if renderer.surface().size() == size {
+            return;
+        }
+
+        let window = HeadlessMapWindowConfig::new(size).create();
+        renderer.resources.surface =
+            Surface::from_image(&renderer.device, &window, &renderer.settings);
+        self.map_context
+            .view_state
+            .resize(window.size().width(), window.size().height());
+    }
+
+    /// Returns where the image which is rendered with the current camera and output size is
+    /// located in Web Mercator. Returns `None` if the camera is pitched, because the image can
+    /// then not be described by an affine transform.
+    pub fn georeference(&self) -> Option<GeoReference> {
+        GeoReference::from_view_state(
+            &self.map_context.view_state,
+            self.map_context.renderer.surface().size(),
+        )
+    }
+
+    /// Reads back the last rendered frame as tightly packed RGBA. The overlay of
+    /// [`HeadlessMapBuilder::with_overlay`] is drawn onto the frame.
+    /// See [`crate::render::resource::BufferedTextureHead::unpad_rows`] for the meaning of `flip_y`.
+    pub fn read_frame(&self, flip_y: bool) -> Option<Vec<u8>> {
+        let mut frame = self.read_raw_frame(flip_y)?;
+        if let Some(overlay) = &self.overlay {
+            let size = self.map_context.renderer.surface().size();
+            overlay.draw(
+                &mut frame,
+                size.width(),
+                size.height(),
+                self.meters_per_pixel(),
+                &self.attribution(),
+            );
+        }
+        Some(frame)
+    }
+
+    /// Same as [`HeadlessMap::read_frame`], but without the overlay.
+    fn read_raw_frame(&self, flip_y: bool) -> Option<Vec<u8>> {
+        let renderer = &self.map_context.renderer;
+        match renderer.state().surface().head() {
+            Head::Headed(_) => None,
+            Head::Headless(buffered_texture) => {
+                Some(buffered_texture.read_rgba(&renderer.device, flip_y))
+            }
+        }
+    }
+
+    /// The ground resolution of a rendered pixel in the center of the current view, or `None` if
+    /// the camera is pitched.
+    pub fn meters_per_pixel(&self) -> Option<f64> {
+        let reference = self.georeference()?;
+        let [_, min_y, _, max_y] = reference.bounds;
+        let latitude = ((min_y + max_y) / 2.0 / EARTH_RADIUS).sinh().atan();
+        Some(reference.pixel_size[0] * latitude.cos())
+    }
+
+    /// The attributions of all sources of the style without HTML tags.
+    pub fn attribution(&self) -> String {
+        attribution_text(self.map_context.style.sources.values().filter_map(
+            |source| match source {
+                Source::Vector(source) | Source::Raster(source) => source.attribution.as_deref(),
+            },
+        ))
+    }
+
+    /// Clips all layers to the `polygon`, which is given as `(longitude, latitude)` pairs. The
+    /// area outside the polygon stays transparent. Polygons with less than three points remove
+    /// the clip mask.
+    pub fn set_clip_mask(&mut self, polygon: Vec<(f64, f64)>) {
+        let clip_mask = self
+            .map_context
+            .world
+            .resources
+            .get_or_init_mut::<ClipMask>();
+        if let Err(e) = clip_mask.set_polygon(&polygon) {
+            log::error!("Failed to tessellate clip mask: {:?}", e);
+            clip_mask.clear();
+        }
+    }
+
+    /// Sets what is written to the color target when rendering vector layers. The mode takes
+    /// effect for tiles which are rendered after this call.
+    pub fn set_render_mode(&mut self, mode: VectorRenderMode) {
+        *self
+            .map_context
+            .world
+            .resources
+            .get_or_init_mut::<VectorRenderMode>() = mode;
+    }
+
+    /// The stages which are run for every rendered frame. Stages can be removed or reordered,
+    /// for example to skip a stage which is not needed for a custom render target.
+    pub fn schedule_mut(&mut self) -> &mut Schedule {
+        &mut self.schedule
+    }
+
+    /// The number of rendered pixels per logical pixel, see
+    /// [`HeadlessMapBuilder::with_pixel_ratio`].
+    pub fn pixel_ratio(&self) -> f64 {
+        self.pixel_ratio
+    }
+
+    /// Seeds the randomness of rendering, see [`RendererSettings::render_seed`]. Rendering is
+    /// not randomized yet, so this does not affect the output.
+    ///
+    /// [`RendererSettings::render_seed`]: crate::render::settings::RendererSettings::render_seed
+    pub fn set_render_seed(&mut self, seed: u64) {
+        self.map_context.renderer.settings.render_seed = seed;
+    }
+
+    /// Multiplies the color of every vector feature with `tint`, for example `[1.0, 0.8, 0.8, 1.0]`
+    /// for a red tint. The tint is ignored while rendering with
+    /// [`VectorRenderMode::FeatureIds`].
+    pub fn set_global_tint(&mut self, tint: [f32; 4]) {
+        self.map_context
+            .world
+            .resources
+            .get_or_init_mut::<GlobalUniforms>()
+            .tint = tint;
+    }
+
+    /// Sets the values which custom shaders can read as `uniforms.extra`. The default shaders
+    /// ignore them.
+    pub fn set_extra_uniforms(&mut self, extra: [f32; 4]) {
+        self.map_context
+            .world
+            .resources
+            .get_or_init_mut::<GlobalUniforms>()
+            .extra = extra;
     }
 
+    /// Binds `extra` as group 1 of the vector pipeline, which is recreated for its layout. The
+    /// bind group is created with the device of [`HeadlessMap::renderer`].
+    pub fn set_extra_bind_group(&mut self, extra: ExtraBindGroup) {
+        set_extra_bind_group(&mut self.map_context.world, extra);
+    }
+
+    /// The renderer of the map, for example to create buffers for an [`ExtraBindGroup`].
+    pub fn renderer(&self) -> &Renderer {
+        &self.map_context.renderer
+    }
+
+    /// Returns the id of the style layer and the index of the feature within this layer which
+    /// has been rendered at pixel `(x, y)` of the last frame.
+    ///
+    /// The frame needs to be rendered with [`VectorRenderMode::FeatureIds`] into a surface with
+    /// a linear color format. Pixels at the edges of features may be blended if multisampling is
+    /// enabled and can therefore not be picked reliably. Features which can not be encoded as
+    /// [`FeatureId`] are no hit.
+    pub fn pick(&self, x: u32, y: u32) -> Option<(String, u32)> {
+        let (frame, width, height) = self.read_feature_ids()?;
+        if x >= width || y >= height {
+            return None;
+        }
+
+        let offset = ((y * width + x) * 4) as usize;
+        let pixel: [u8; 4] = frame.get(offset..offset + 4)?.try_into().ok()?;
+
+        let id = FeatureId::decode(pixel)?;
+        let layer = self.map_context.style.layers.get(id.layer)?;
+        Some((layer.id.clone(), id.feature))
+    }
+
+    /// Returns the features which have been rendered within the rectangle of `width` x `height`
+    /// pixels at `(x, y)` of the last frame, in the order in which they are first encountered
+    /// row by row. Each feature is returned once. The parts of the rectangle outside of the
+    /// frame are ignored.
+    ///
+    /// The same requirements as for [`HeadlessMap::pick`] apply.
+    pub fn query_features_in_rect(
+        &self,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Vec<(String, u32)> {
+        let Some((frame, frame_width, frame_height)) = self.read_feature_ids() else { return Vec::new(); };
+
+        let columns = x.min(frame_width)..x.saturating_add(width).min(frame_width);
+        let rows = y.min(frame_height)..y.saturating_add(height).min(frame_height);
+
+        let mut seen = HashSet::new();
+        let mut features = Vec::new();
+        for row in rows {
+            let start = ((row * frame_width + columns.start) * 4) as usize;
+            let end = ((row * frame_width + columns.end) * 4) as usize;
+
+            for pixel in frame[start..end].chunks_exact(4) {
+                let id = pixel.try_into().ok().and_then(FeatureId::decode);
+                let Some(id) = id else { continue; };
+                if !seen.insert((id.layer, id.feature)) {
+                    continue;
+                }
+
+                if let Some(layer) = self.map_context.style.layers.get(id.layer) {
+                    features.push((layer.id.clone(), id.feature));
+                }
+            }
+        }
+
+        features
+    }
+
+    /// Reads back the last frame for picking, together with its width and height.
+    fn read_feature_ids(&self) -> Option<(Vec<u8>, u32, u32)> {
+        let renderer = &self.map_context.renderer;
+        if renderer.state().surface().surface_format().describe().srgb {
+            log::warn!("picking is not supported for sRGB surfaces");
+            return None;
+        }
+
+        let Head::Headless(buffered_texture) = renderer.state().surface().head() else { return None; };
+        let frame = buffered_texture.read_rgba(&renderer.device, false);
+        Some((frame, buffered_texture.width(), buffered_texture.height()))
+    }
+
+    /// Shuts the map down and releases its resources.
+    ///
+    /// The tile requests which the map has passed to the scheduler of the kernel are awaited
+    /// first, so that their responses are stored by the caching source clients. Afterwards, the
+    /// source client of the kernel and the clients of the added sources are flushed, see
+    /// [`HttpClient::flush`]. Finally, the submitted GPU work is waited for before the tile
+    /// buffers, caches and the renderer are released.
+    ///
+    /// Dropping the map releases its resources too, but neither awaits the requests nor flushes
+    /// the clients.
+    pub async fn shutdown(self) -> Result<(), HeadlessMapError> {
+        while self.kernel.apc().pending_calls() > 0 {
+            tokio::time::sleep(SHUTDOWN_POLL_INTERVAL).await;
+        }
+
+        let mut result = self
+            .kernel
+            .source_client()
+            .flush()
+            .await
+            .map_err(HeadlessMapError::Flush);
+        for client in self.source_clients.values() {
+            let flushed = client.flush().await.map_err(HeadlessMapError::Flush);
+            if result.is_ok() {
+                result = flushed;
+            }
+        }
+
+        drop(self);
+        result
+    }
+
+    /// Waits for the GPU and clears all tiles and caches. The remaining GPU resources are
+    /// released when the renderer is dropped.
+    fn release(&mut self) {
+        let context = &mut self.map_context;
+        context.renderer.device.poll(wgpu::Maintain::Wait);
+
+        context.world.tiles.clear();
+        if let Some(Eventually::Initialized(pool)) = context
+            .world
+            .resources
+            .query_mut::<&mut Eventually<VectorBufferPool>>()
+        {
+            pool.clear();
+        }
+
+        if let Some(cache) = self.tessellation_cache.get_mut() {
+            cache.clear();
+        }
+    }
+
+    /// Fetches the tile at `coords` from the source client of the kernel. The url of the tile is
+    /// formatted by the default vector source of the style, see [`SourceType::default_vector`].
+    ///
+    /// The tiles of the vector sources which were added with [`HeadlessMap::add_source`] are
+    /// fetched from their clients and appended. Vector tiles are protobuf messages, so the
+    /// concatenation contains the layers of all tiles. Gzip-compressed tiles are decompressed
+    /// before they are appended. The tile is only not found if none of the sources contains it.
     pub async fn fetch_tile(&self, coords: WorldTileCoords) -> Result<Box<[u8]>, SourceFetchError> {
+        Ok(self.fetch_stored_tile(coords).await?.data)
+    }
+
+    /// Fetches all `tiles` in order. Tiles which do not exist in the source are handled
+    /// according to `policy`, other errors abort the fetching.
+    pub async fn fetch_tiles(
+        &self,
+        tiles: impl IntoIterator<Item = WorldTileCoords>,
+        policy: MissingTilePolicy,
+    ) -> Result<Vec<(WorldTileCoords, TileStatus)>, HeadlessMapError> {
+        let mut fetched = Vec::new();
+        for coords in tiles {
+            match self.fetch_tile(coords).await {
+                Ok(data) => fetched.push((coords, TileStatus::Loaded(data))),
+                Err(e) if e.is_not_found() && policy != MissingTilePolicy::Error => {
+                    log::debug!("tile {coords} does not exist");
+                    if policy == MissingTilePolicy::Empty {
+                        fetched.push((coords, TileStatus::Empty));
+                    }
+                }
+                Err(e) => return Err(HeadlessMapError::Fetch(coords, e)),
+            }
+        }
+        Ok(fetched)
+    }
+
+    /// Same as [`HeadlessMap::fetch_tile`], but also returns until when the tile is valid, so
+    /// that stale tiles can be fetched again.
+    ///
+    /// If the tile is merged from several sources, it expires together with the first of its
+    /// parts. It only has an `ETag` if every part has one, which are then joined with commas.
+    pub async fn fetch_stored_tile(
+        &self,
+        coords: WorldTileCoords,
+    ) -> Result<StoredTile, SourceFetchError> {
         let source_client = self.kernel.source_client();
-        let data = source_client
-            .fetch(
-                &coords,
-                &SourceType::Tessellate(TessellateSource::default()),
-            )
-            .await?
-            .into_boxed_slice();
-        Ok(data)
+        let source = SourceType::default_vector(&self.map_context.style);
+        let fetch = source_client.fetch_stored(&coords, &source);
+        #[cfg(feature = "trace")]
+        let fetch = tracing::Instrument::instrument(fetch, tracing::info_span!("fetch", %coords));
+        let mut parts = match fetch.await {
+            Ok(tile) => vec![tile],
+            Err(e) if e.is_not_found() && !self.source_clients.is_empty() => Vec::new(),
+            Err(e) => return Err(e),
+        };
+
+        for (id, client) in &self.source_clients {
+            let Some(Source::Vector(source)) = self
+                .map_context
+                .style
+                .sources
+                .get(id) else { continue; };
+            let Some(url) = source.tile_url(&coords) else { continue; };
+
+            match client.fetch_stored(&url).await {
+                Ok(tile) => parts.push(tile),
+                Err(e) if e.is_not_found() => log::debug!("source {id} has no tile {coords}"),
+                Err(e) => return Err(e),
+            }
+        }
+
+        let tile = merge_tiles(parts)
+            .map_err(|e| SourceFetchError(Box::new(e)))?
+            .ok_or_else(|| SourceFetchError::not_found(&coords.to_string()))?;
+        self.tile_provenance.borrow_mut().insert(
+            coords,
+            TileProvenance {
+                fetched_at: Some(SystemTime::now()),
+                expires_at: tile.expires_at,
+                etag: tile.etag.clone(),
+                content_hash: Some(tile.content_hash()),
+            },
+        );
+        Ok(tile)
     }
 
+    /// Tessellates a tile for [`HeadlessMap::render_tile`], which renders a single tile at
+    /// `(0, 0, 0)`. The geometry is therefore placed at `(0, 0, 0)` regardless of where the tile
+    /// was fetched from. Use [`HeadlessMap::process_tile_at`] for tiles which are rendered together
+    /// with [`HeadlessMap::render_tiles`].
     pub async fn process_tile(
         &self,
         tile_data: Box<[u8]>,
         source_layers: &[&str],
-    ) -> Vec<Box<<DefaultVectorTransferables as VectorTransferables>::LayerTessellated>> {
-        let context = HeadlessContext::default();
-        let mut processor =
-            ProcessVectorContext::<DefaultVectorTransferables, HeadlessContext>::new(context);
-
+    ) -> Vec<Box<HeadlessLayerTessellated>> {
         let target_coords = WorldTileCoords::default(); // load to 0,0,0
-        process_vector_tile(
-            &tile_data,
-            VectorTileRequest {
-                coords: target_coords,
-                layers: source_layers
-                    .iter()
-                    .map(|layer| layer.to_string())
-                    .collect(),
-            },
-            &mut processor,
-        )
-        .expect("Failed to process!");
 
-        let messages = processor.take_context().messages.deref().take();
-        let layers = messages.into_iter()
-            .filter(|message| message.tag() == <DefaultVectorTransferables as VectorTransferables>::LayerTessellated::message_tag())
-            .map(|message| message.into_transferable::<<DefaultVectorTransferables as VectorTransferables>::LayerTessellated>())
-            .collect::<Vec<_>>();
+        // Not cached, because all tiles are loaded to the same coordinates
+        self.tessellate(target_coords, tile_data, source_layers)
+            .expect("Failed to process!")
+    }
+
+    /// Returns the lookup statistics of the tessellation cache, if it is enabled via
+    /// [`HeadlessMapBuilder::with_tessellation_cache`].
+    pub fn tessellation_cache_stats(&self) -> Option<TessellationCacheStats> {
+        self.tessellation_cache
+            .borrow()
+            .as_ref()
+            .map(|cache| cache.stats())
+    }
+
+    /// Returns how many tiles the last rendered frame drew and how many it skipped because they
+    /// were outside of the view.
+    pub fn frustum_culling_stats(&self) -> FrustumCullingStats {
+        self.map_context
+            .world
+            .resources
+            .get::<ViewFrustum>()
+            .map(|view_frustum| view_frustum.stats())
+            .unwrap_or_default()
+    }
+
+    /// Returns the number of draw calls of the last rendered frame.
+    pub fn draw_calls(&self) -> u32 {
+        self.map_context
+            .world
+            .resources
+            .get::<MainPassStats>()
+            .map_or(0, |stats| stats.draw_calls())
+    }
 
-        layers
+    /// Tessellates the tile which was fetched from `coords`, so that its geometry is placed at
+    /// `coords` when it is rendered with [`HeadlessMap::render_tiles`]. Returns a copy of the
+    /// cached tessellation if the tessellation cache is enabled and contains a tile with the same
+    /// data. Otherwise the tile is read from the tessellation store if it contains the tile, see
+    /// [`HeadlessMapBuilder::with_tessellation_store`].
+    pub fn process_tile_at(
+        &self,
+        coords: WorldTileCoords,
+        tile_data: Box<[u8]>,
+        source_layers: &[&str],
+    ) -> Result<Vec<Box<HeadlessLayerTessellated>>, ProcessVectorError> {
+        // Same as the content hash of the stored tile
+        let content_hash = fnv1a(&tile_data);
+        let key =
+            TessellationCacheKey::new(coords, &self.map_context.style, source_layers, content_hash);
+
+        if let Some(cache) = self.tessellation_cache.borrow_mut().as_mut() {
+            if let Some(layers) = cache.get(&key) {
+                return Ok(layers);
+            }
+        }
+
+        let stored = self.tessellation_store.as_ref().and_then(|store| {
+            store.read(&key).unwrap_or_else(|e| {
+                log::warn!("reading tile {coords} from the tessellation store failed: {e}");
+                None
+            })
+        });
+        let layers = match stored {
+            Some(layers) => layers,
+            None => self.tessellate(coords, tile_data, source_layers)?,
+        };
+
+        if let Some(cache) = self.tessellation_cache.borrow_mut().as_mut() {
+            cache.insert(key, layers.clone());
+        }
+
+        Ok(layers)
+    }
+
+    /// Returns the features of the tile at `coords` which were skipped because their
+    /// tessellation failed, when the tile was last tessellated. The errors are also reported
+    /// when the tile is rendered, see [`RenderReport::tessellation_errors`].
+    pub fn tessellation_errors(&self, coords: WorldTileCoords) -> Vec<TessellationError> {
+        self.tessellation_errors
+            .borrow()
+            .get(&coords)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Tessellates the tile at `coords` without using the tessellation cache.
+    pub(crate) fn tessellate(
+        &self,
+        coords: WorldTileCoords,
+        tile_data: Box<[u8]>,
+        source_layers: &[&str],
+    ) -> Result<Vec<Box<HeadlessLayerTessellated>>, ProcessVectorError> {
+        let (layers, errors) = tessellate_tile(coords, tile_data, source_layers)?;
+
+        let mut tessellation_errors = self.tessellation_errors.borrow_mut();
+        if errors.is_empty() {
+            tessellation_errors.remove(&coords);
+        } else {
+            tessellation_errors.insert(coords, errors);
+        }
+
+        Ok(layers)
+    }
+}
+
+/// The key of the cached tessellation of the tile at `coords`, if the data which was last fetched
+/// from there according to `provenance` is cached and not expired at `now`.
+fn valid_cache_key<V: Clone>(
+    cache: &TessellationCache<V>,
+    provenance: &TileProvenance,
+    coords: WorldTileCoords,
+    style: &Style,
+    source_layers: &[&str],
+    now: SystemTime,
+) -> Option<TessellationCacheKey> {
+    let content_hash = provenance.valid_content_hash(now)?;
+    let key = TessellationCacheKey::new(coords, style, source_layers, content_hash);
+    cache.contains_key(&key).then_some(key)
+}
+
+/// Returns the source layers of which the tessellation changes between the `old` and the `new`
+/// style, according to their `diff`. Paint changes are applied when uploading, so source layers
+/// of which only the paint changed are not included.
+fn changed_source_layers(old: &Style, new: &Style, diff: &StyleDiff) -> HashSet<String> {
+    let old_layers = old
+        .layers
+        .iter()
+        .filter(|layer| diff.removed.contains(&layer.id) || diff.retessellate.contains(&layer.id));
+    let new_layers = new
+        .layers
+        .iter()
+        .filter(|layer| diff.added.contains(&layer.id) || diff.retessellate.contains(&layer.id));
+
+    old_layers
+        .chain(new_layers)
+        .filter_map(|layer| layer.source_layer.clone())
+        .collect()
+}
+
+/// Removes the source `id` and the layers which read from it from `style`, see
+/// [`HeadlessMap::remove_source`]. Returns the source layers which are no longer required.
+fn remove_source(style: &mut Style, id: &str) -> Result<Vec<String>, HeadlessMapError> {
+    if style.sources.remove(id).is_none() {
+        return Err(HeadlessMapError::UnknownSource(id.to_string()));
+    }
+
+    let source_layers = style
+        .layers
+        .iter()
+        .filter(|layer| layer.source.as_deref() == Some(id))
+        .filter_map(|layer| layer.source_layer.clone())
+        .collect::<Vec<_>>();
+    style
+        .layers
+        .retain(|layer| layer.source.as_deref() != Some(id));
+
+    let still_required = style
+        .required_source_layers()
+        .into_values()
+        .flatten()
+        .collect::<HashSet<_>>();
+    Ok(source_layers
+        .into_iter()
+        .filter(|source_layer| !still_required.contains(source_layer))
+        .collect())
+}
+
+/// Adds `layer` on top of the layers of `style`, see [`HeadlessMap::add_layer`].
+fn add_layer(style: &mut Style, layer: StyleLayer) -> Result<(), HeadlessMapError> {
+    if let Some(source) = &layer.source {
+        if !style.sources.contains_key(source) {
+            return Err(HeadlessMapError::UnknownSource(source.clone()));
+        }
+    }
+    style.layers.retain(|existing| existing.id != layer.id);
+    style.layers.push(layer);
+    Ok(())
+}
+
+/// Removes the layer `layer_id` from `style`, see [`HeadlessMap::remove_layer`].
+fn remove_layer(style: &mut Style, layer_id: &str) -> Result<(), HeadlessMapError> {
+    let count = style.layers.len();
+    style.layers.retain(|layer| layer.id != layer_id);
+    if style.layers.len() == count {
+        return Err(HeadlessMapError::UnknownLayer(layer_id.to_string()));
+    }
+    Ok(())
+}
+
+/// Drops the tessellated `source_layers` of the `tiles`.
+fn release_layers(tiles: &mut [ReadyTile], source_layers: &[String]) {
+    for (_, _, layers) in tiles {
+        layers.retain(|layer| !source_layers.contains(&layer.layer_data.name));
+    }
+}
+
+/// The tiles at zoom level `z` which intersect `bounds`, see [`HeadlessMap::pregenerate`].
+fn tiles_in_bounds(
+    (west, south, east, north): (f64, f64, f64, f64),
+    z: ZoomLevel,
+) -> impl Iterator<Item = WorldTileCoords> {
+    // The highest latitude which can be represented in Web Mercator
+    const MAX_LATITUDE: f64 = 85.051129;
+
+    let zoom = Zoom::from(z);
+    let last = (1i32 << u8::from(z)) - 1;
+    let corner = |latitude: f64, longitude: f64| {
+        let lat_lon = LatLon::new(latitude.clamp(-MAX_LATITUDE, MAX_LATITUDE), longitude);
+        let coords = WorldCoords::from_lat_lon(lat_lon, zoom).into_world_tile(z, zoom);
+        (coords.x.clamp(0, last), coords.y.clamp(0, last))
+    };
+    let (min_x, min_y) = corner(north, west.max(-180.0));
+    let (max_x, max_y) = corner(south, east.min(180.0));
+
+    (min_x..=max_x).flat_map(move |x| (min_y..=max_y).map(move |y| WorldTileCoords { x, y, z }))
+}
+
+/// Concatenates the vector tiles `parts` into a single tile, see [`HeadlessMap::fetch_tile`].
+/// Returns `None` if there are no parts.
+fn merge_tiles(parts: Vec<StoredTile>) -> io::Result<Option<StoredTile>> {
+    if parts.is_empty() {
+        return Ok(None);
+    }
+
+    let mut data = Vec::new();
+    for part in &parts {
+        if gzip::is_gzip(&part.data) {
+            data.extend(gzip::decompress(&part.data)?);
+        } else {
+            data.extend_from_slice(&part.data);
+        }
+    }
+
+    let expires_at = parts.iter().filter_map(|part| part.expires_at).min();
+    let etags = parts
+        .iter()
+        .map(|part| part.etag.as_deref())
+        .collect::<Option<Vec<_>>>();
+
+    let tile = StoredTile::new(data.into_boxed_slice(), expires_at);
+    Ok(Some(match etags {
+        Some(etags) => tile.with_etag(etags.join(",")),
+        None => tile,
+    }))
+}
+
+/// Tessellates `source_layers` of the tile at `coords`. The layers are placed at `coords`.
+/// Returns the layers together with the features which were skipped.
+fn tessellate_tile(
+    coords: WorldTileCoords,
+    tile_data: Box<[u8]>,
+    source_layers: &[&str],
+) -> Result<(Vec<Box<HeadlessLayerTessellated>>, Vec<TessellationError>), ProcessVectorError> {
+    let context = HeadlessContext::default();
+    let mut processor =
+        ProcessVectorContext::<DefaultVectorTransferables, HeadlessContext>::new(context);
+
+    process_vector_tile(
+        &tile_data,
+        VectorTileRequest {
+            coords,
+            layers: source_layers
+                .iter()
+                .map(|layer| layer.to_string())
+                .collect(),
+        },
+        &mut processor,
+    )?;
+
+    let errors = processor.tessellation_errors().to_vec();
+    let messages = processor.take_context().messages.deref().take();
+    let layers = messages
+        .into_iter()
+        .filter(|message| message.tag() == HeadlessLayerTessellated::message_tag())
+        .map(|message| message.into_transferable::<HeadlessLayerTessellated>())
+        .collect::<Vec<_>>();
+
+    Ok((layers, errors))
+}
+
+impl Drop for HeadlessMap {
+    fn drop(&mut self) {
+        self.release();
     }
 }
 
@@ -168,3 +2012,390 @@ impl Context for HeadlessContext {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::Write,
+        time::{Duration, SystemTime},
+    };
+
+    use cgmath::Vector4;
+    use flate2::{write::GzEncoder, Compression};
+    use geozero::mvt::{tile, Message, Tile};
+
+    use super::{
+        add_layer, changed_source_layers, merge_tiles, release_layers, remove_layer, remove_source,
+        tessellate_tile, tiles_in_bounds, valid_cache_key, HeadlessMapError, RenderReport,
+        SkipReason, TileReadiness, TileTimings,
+    };
+    use crate::{
+        coords::{WorldTileCoords, Zoom, ZoomLevel},
+        headless::{
+            manifest::TileProvenance,
+            tessellation_cache::{TessellationCache, TessellationCacheKey},
+        },
+        io::{geojson_source::GeoJsonSource, tile_expiry::StoredTile},
+        style::{
+            layer::StyleLayer,
+            source::{Source, VectorSource},
+            Style,
+        },
+        vector::{BackingBufferType, BufferPoolError, LayerTessellated},
+    };
+
+    // language=json
+    const SQUARE: &str = r#"
+    {
+      "type": "FeatureCollection",
+      "features": [
+        {
+          "type": "Feature",
+          "properties": {},
+          "geometry": {
+            "type": "Polygon",
+            "coordinates": [[[-60, 10], [-30, 10], [-30, 40], [-60, 40], [-60, 10]]]
+          }
+        }
+      ]
+    }
+    "#;
+
+    fn vector_source() -> Source {
+        Source::Vector(VectorSource {
+            attribution: None,
+            bounds: None,
+            maxzoom: None,
+            minzoom: None,
+            scheme: None,
+            tiles: Some("https://example.com/{z}/{x}/{y}.pbf".to_string()),
+        })
+    }
+
+    fn style_layer(id: &str, source: &str, source_layer: &str) -> StyleLayer {
+        StyleLayer {
+            id: id.to_string(),
+            source: Some(source.to_string()),
+            source_layer: Some(source_layer.to_string()),
+            ..StyleLayer::default()
+        }
+    }
+
+    #[test]
+    fn test_adjacent_tiles_do_not_overlap() {
+        // One square in the west and one in the east of the equator
+        // language=json
+        let json = r#"
+        {
+          "type": "FeatureCollection",
+          "features": [
+            {
+              "type": "Feature",
+              "properties": {},
+              "geometry": {
+                "type": "Polygon",
+                "coordinates": [[[-60, 10], [-30, 10], [-30, 40], [-60, 40], [-60, 10]]]
+              }
+            },
+            {
+              "type": "Feature",
+              "properties": {},
+              "geometry": {
+                "type": "Polygon",
+                "coordinates": [[[30, 10], [60, 10], [60, 40], [30, 40], [30, 10]]]
+              }
+            }
+          ]
+        }
+        "#;
+        let source = GeoJsonSource::from_str(json, "squares").unwrap();
+
+        let world_x_range = |coords: WorldTileCoords| {
+            let data = source.tile(&coords).into_boxed_slice();
+            let (layers, _) = tessellate_tile(coords, data, &["squares"]).unwrap();
+            assert_eq!(layers.len(), 1);
+            let layer = &layers[0];
+            assert_eq!(layer.coords(), coords);
+
+            let transform = layer.coords().transform_for_zoom(Zoom::from(coords.z));
+            layer
+                .buffer
+                .buffer
+                .vertices
+                .iter()
+                .map(|vertex| {
+                    let [x, y] = vertex.position;
+                    (transform * Vector4::new(x as f64, y as f64, 0.0, 1.0)).x
+                })
+                .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), x| {
+                    (min.min(x), max.max(x))
+                })
+        };
+
+        let (_, west_max) = world_x_range(WorldTileCoords::from((0, 0, ZoomLevel::new(1))));
+        let (east_min, _) = world_x_range(WorldTileCoords::from((1, 0, ZoomLevel::new(1))));
+        assert!(west_max < east_min);
+    }
+
+    #[test]
+    fn test_tile_timings() {
+        let timings = TileTimings {
+            coords: WorldTileCoords::from((1, 2, ZoomLevel::new(3))),
+            bytes: 48213,
+            features: 913,
+            fetch: Duration::from_millis(700),
+            tessellate: Duration::from_millis(112),
+        };
+
+        assert!(timings.is_slow(Some(Duration::from_millis(500))));
+        assert!(!timings.is_slow(Some(Duration::from_secs(1))));
+        assert!(!timings.is_slow(None));
+        assert!(timings
+            .to_string()
+            .ends_with("bytes=48213 features=913 total_ms=812 fetch_ms=700 tessellate_ms=112"));
+    }
+
+    #[test]
+    fn test_tiles_in_bounds() {
+        let tiles = |bounds, z| tiles_in_bounds(bounds, ZoomLevel::new(z)).collect::<Vec<_>>();
+
+        assert_eq!(
+            tiles((-180.0, -90.0, 180.0, 90.0), 0),
+            vec![WorldTileCoords::from((0, 0, ZoomLevel::new(0)))]
+        );
+        assert_eq!(tiles((-180.0, -90.0, 180.0, 90.0), 2).len(), 16);
+        // Munich is in the north-east quarter of the world
+        assert_eq!(
+            tiles((11.4, 48.0, 11.7, 48.2), 1),
+            vec![WorldTileCoords::from((1, 0, ZoomLevel::new(1)))]
+        );
+        // Crossing the prime meridian and the equator
+        assert_eq!(
+            tiles((-1.0, -1.0, 1.0, 1.0), 1),
+            vec![
+                WorldTileCoords::from((0, 0, ZoomLevel::new(1))),
+                WorldTileCoords::from((0, 1, ZoomLevel::new(1))),
+                WorldTileCoords::from((1, 0, ZoomLevel::new(1))),
+                WorldTileCoords::from((1, 1, ZoomLevel::new(1))),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_add_and_remove_layers() {
+        let mut style = Style::default();
+        let count = style.layers.len();
+
+        let squares = style_layer("squares", "shapes", "squares");
+        assert!(matches!(
+            add_layer(&mut style, squares.clone()),
+            Err(HeadlessMapError::UnknownSource(source)) if source == "shapes"
+        ));
+
+        style.sources.insert("shapes".to_string(), vector_source());
+        add_layer(&mut style, squares.clone()).unwrap();
+        // Layers with the same id are replaced
+        add_layer(&mut style, squares).unwrap();
+        add_layer(&mut style, style_layer("lakes", "shapes", "water")).unwrap();
+        assert_eq!(style.layers.len(), count + 2);
+        assert_eq!(style.layers.last().unwrap().id, "lakes");
+
+        remove_layer(&mut style, "lakes").unwrap();
+        assert!(matches!(
+            remove_layer(&mut style, "lakes"),
+            Err(HeadlessMapError::UnknownLayer(layer)) if layer == "lakes"
+        ));
+        assert_eq!(style.layers.len(), count + 1);
+
+        // The water is still required by the default layers
+        add_layer(&mut style, style_layer("lakes", "shapes", "water")).unwrap();
+        assert_eq!(
+            remove_source(&mut style, "shapes").unwrap(),
+            vec!["squares".to_string()]
+        );
+        assert_eq!(style.layers.len(), count);
+        assert!(!style.sources.contains_key("shapes"));
+        assert!(matches!(
+            remove_source(&mut style, "shapes"),
+            Err(HeadlessMapError::UnknownSource(source)) if source == "shapes"
+        ));
+    }
+
+    #[test]
+    fn test_changed_source_layers() {
+        let mut old = Style::default();
+        old.sources.insert("shapes".to_string(), vector_source());
+
+        let mut new = old.clone();
+        add_layer(&mut new, style_layer("squares", "shapes", "squares")).unwrap();
+        remove_layer(&mut new, "building").unwrap();
+        for layer in &mut new.layers {
+            match layer.id.as_str() {
+                "water" => layer.minzoom = Some(4),
+                "park" => layer.index += 1,
+                _ => {}
+            }
+        }
+
+        let diff = old.diff(&new);
+        assert_eq!(diff.paint_changed, vec!["park".to_string()]);
+        let mut source_layers = changed_source_layers(&old, &new, &diff)
+            .into_iter()
+            .collect::<Vec<_>>();
+        source_layers.sort();
+        assert_eq!(source_layers, vec!["building", "squares", "water"]);
+    }
+
+    #[test]
+    fn test_upload_errors() {
+        let style = Style::default();
+        let mut report = RenderReport {
+            drawn_layers: vec!["water".to_string(), "park".to_string()],
+            ..RenderReport::default()
+        };
+        let too_large = |x| BufferPoolError::TileTooLarge {
+            coords: WorldTileCoords::from((x, 0, ZoomLevel::new(1))),
+            bytes: u64::MAX,
+            typ: BackingBufferType::Vertices,
+        };
+        let errors = vec![
+            ("water".to_string(), too_large(0)),
+            ("park".to_string(), too_large(0)),
+        ];
+        let feature_tiles = [("water".to_string(), 1), ("park".to_string(), 2)]
+            .into_iter()
+            .collect();
+
+        report.add_upload_errors(&style, errors.clone(), &feature_tiles);
+        // The park is still drawn on the other tile
+        assert_eq!(report.drawn_layers, vec!["park".to_string()]);
+        assert_eq!(report.skip_reason("water"), Some(SkipReason::TooLarge));
+        assert_eq!(report.upload_errors, errors);
+    }
+
+    #[test]
+    fn test_merge_tiles() {
+        let coords = WorldTileCoords::from((0, 0, ZoomLevel::new(0)));
+        let squares = GeoJsonSource::from_str(SQUARE, "squares")
+            .unwrap()
+            .tile(&coords);
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder
+            .write_all(
+                &GeoJsonSource::from_str(SQUARE, "lakes")
+                    .unwrap()
+                    .tile(&coords),
+            )
+            .unwrap();
+        let lakes = encoder.finish().unwrap();
+
+        let now = SystemTime::now();
+        let later = now + Duration::from_secs(60);
+        let parts = vec![
+            StoredTile::new(squares.into_boxed_slice(), Some(later)).with_etag("\"a\""),
+            StoredTile::new(lakes.into_boxed_slice(), Some(now)).with_etag("\"b\""),
+        ];
+
+        let merged = merge_tiles(parts.clone()).unwrap().unwrap();
+        assert_eq!(merged.expires_at, Some(now));
+        assert_eq!(merged.etag.as_deref(), Some("\"a\",\"b\""));
+
+        let (layers, _) = tessellate_tile(coords, merged.data, &["squares", "lakes"]).unwrap();
+        let mut names = layers
+            .iter()
+            .map(|layer| layer.layer_data.name.as_str())
+            .collect::<Vec<_>>();
+        names.sort_unstable();
+        assert_eq!(names, ["lakes", "squares"]);
+
+        // The released layers are dropped from the ready tiles
+        let mut tiles = vec![(coords, TileReadiness::Success, layers)];
+        release_layers(&mut tiles, &["lakes".to_string()]);
+        assert_eq!(tiles[0].2.len(), 1);
+        assert_eq!(tiles[0].2[0].layer_data.name, "squares");
+
+        let mut without_etag = parts;
+        without_etag[1].etag = None;
+        assert_eq!(merge_tiles(without_etag).unwrap().unwrap().etag, None);
+        assert_eq!(merge_tiles(Vec::new()).unwrap(), None);
+    }
+
+    #[test]
+    fn test_tessellation_errors() {
+        let square = |geometry: Vec<u32>| tile::Feature {
+            id: None,
+            tags: Vec::new(),
+            r#type: Some(tile::GeomType::Polygon as i32),
+            geometry,
+        };
+        let tile = Tile {
+            layers: vec![tile::Layer {
+                version: 2,
+                name: "squares".to_string(),
+                features: vec![
+                    // MoveTo(0, 0), LineTo(+1024, 0), (0, +1024), (-1024, 0), ClosePath
+                    square(vec![9, 0, 0, 26, 2048, 0, 0, 2048, 2047, 0, 15]),
+                    // The LineTo lacks the last of its three points
+                    square(vec![9, 0, 0, 26, 2048, 0, 0, 2048, 15]),
+                    square(vec![9, 0, 0, 26, 2048, 0, 0, 2048, 2047, 0, 15]),
+                ],
+                keys: Vec::new(),
+                values: Vec::new(),
+                extent: Some(4096),
+            }],
+        };
+
+        let coords = WorldTileCoords::from((0, 0, ZoomLevel::new(0)));
+        let data = tile.encode_to_vec().into_boxed_slice();
+        let (layers, errors) = tessellate_tile(coords, data, &["squares"]).unwrap();
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].layer, "squares");
+        assert_eq!(errors[0].feature_index, 1);
+
+        // The other features are still tessellated
+        assert_eq!(layers.len(), 1);
+        let feature_indices = &layers[0].feature_indices;
+        assert_eq!(feature_indices.len(), 3);
+        assert!(feature_indices[0] > 0 && feature_indices[2] > 0);
+        assert_eq!(feature_indices[1], 0);
+    }
+
+    #[test]
+    fn test_valid_cache_key() {
+        let coords = WorldTileCoords::from((0, 0, ZoomLevel::new(0)));
+        let now = SystemTime::now();
+        let expires_at = now + Duration::from_secs(60);
+        let data = GeoJsonSource::from_str(SQUARE, "squares")
+            .unwrap()
+            .tile(&coords);
+        let tile = StoredTile::new(data.into_boxed_slice(), Some(expires_at));
+        let provenance = TileProvenance {
+            fetched_at: Some(now),
+            expires_at: tile.expires_at,
+            etag: None,
+            content_hash: Some(tile.content_hash()),
+        };
+        let style = Style::default();
+        let mut cache = TessellationCache::new(4);
+
+        // The tile was fetched, but not tessellated
+        let key = |cache: &TessellationCache<_>, source_layers: &[&str], now| {
+            valid_cache_key(cache, &provenance, coords, &style, source_layers, now)
+        };
+        assert_eq!(key(&cache, &["squares"], now), None);
+
+        // Like after a prefetch, the tessellated tile is rendered without fetching it again
+        let (layers, _) = tessellate_tile(coords, tile.data.clone(), &["squares"]).unwrap();
+        let cached = TessellationCacheKey::new(coords, &style, &["squares"], tile.content_hash());
+        cache.insert(cached, layers);
+        assert_eq!(key(&cache, &["squares"], now), Some(cached));
+        assert!(cache.get(&cached).is_some());
+
+        // Until the tile expires
+        assert_eq!(key(&cache, &["squares"], expires_at), None);
+        assert_eq!(key(&cache, &["water"], now), None);
+        // Only the lookup of the tile is counted
+        assert_eq!((cache.stats().hits, cache.stats().misses), (1, 0));
+    }
+}
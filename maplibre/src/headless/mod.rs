@@ -23,8 +23,15 @@ use crate::{
 mod graph_node;
 mod system;
 
+pub mod benchmark;
 pub mod environment;
+pub mod georeference;
+pub mod manifest;
 pub mod map;
+pub mod overlay;
+pub mod snapshotter;
+pub mod tessellation_cache;
+pub mod tessellation_store;
 pub mod window;
 
 pub async fn create_headless_renderer(
@@ -67,11 +74,31 @@ mod draw_graph {
 
 pub struct HeadlessPlugin {
     write_to_disk: bool,
+    flip_y: bool,
+    write_world_file: bool,
 }
 
 impl HeadlessPlugin {
     pub fn new(write_to_disk: bool) -> Self {
-        Self { write_to_disk }
+        Self {
+            write_to_disk,
+            flip_y: false,
+            write_world_file: false,
+        }
+    }
+
+    /// Reverses the row order of the written PNGs. By default the first row is the top of the
+    /// map, like in the `image` crate.
+    pub fn with_flip_y(mut self, flip_y: bool) -> Self {
+        self.flip_y = flip_y;
+        self
+    }
+
+    /// Writes an ESRI world file (`.pgw`) alongside each PNG, which georeferences it in
+    /// Web Mercator.
+    pub fn with_world_file(mut self, write_world_file: bool) -> Self {
+        self.write_world_file = write_world_file;
+        self
     }
 }
 
@@ -95,7 +122,11 @@ impl Plugin<HeadlessEnvironment> for HeadlessPlugin {
 
         schedule.add_system_to_stage(
             RenderStageLabel::Cleanup,
-            SystemContainer::new(WriteSurfaceBufferSystem::new(self.write_to_disk)),
+            SystemContainer::new(WriteSurfaceBufferSystem::new(
+                self.write_to_disk,
+                self.flip_y,
+                self.write_world_file,
+            )),
         );
 
         // FIXME tcs: Is this good style?
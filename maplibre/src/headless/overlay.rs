@@ -0,0 +1,559 @@
+//! Draws a scale bar and the attribution of the sources onto rendered frames.
+//!
+//! The overlay is composited on the CPU after the frame has been read back. Text is drawn with
+//! a built-in 5x7 bitmap font, which only contains uppercase letters, digits and common
+//! punctuation. Lowercase letters are drawn as uppercase letters.
+
+/// The corner of the image in which an element of the overlay is placed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverlayCorner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+/// The units of the scale bar.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScaleUnits {
+    Metric,
+    Imperial,
+}
+
+impl Default for ScaleUnits {
+    fn default() -> Self {
+        ScaleUnits::Metric
+    }
+}
+
+const METERS_PER_FOOT: f64 = 0.3048;
+const FEET_PER_MILE: f64 = 5280.0;
+
+const GLYPH_WIDTH: u32 = 5;
+const GLYPH_HEIGHT: u32 = 7;
+/// The space around the overlay elements and between the image border and the elements
+const MARGIN: u32 = 4;
+
+const FOREGROUND: [u8; 4] = [0, 0, 0, 255];
+const BACKGROUND: [u8; 4] = [255, 255, 255, 204];
+
+/// Configures which elements are drawn by the overlay. By default, nothing is drawn.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Overlay {
+    scale_bar: Option<(OverlayCorner, ScaleUnits)>,
+    attribution: Option<OverlayCorner>,
+    /// The maximum width of the scale bar in pixels
+    max_scale_bar_width: u32,
+    /// The size of a font pixel in image pixels
+    text_scale: u32,
+}
+
+impl Default for Overlay {
+    fn default() -> Self {
+        Self {
+            scale_bar: None,
+            attribution: None,
+            max_scale_bar_width: 100,
+            text_scale: 1,
+        }
+    }
+}
+
+impl Overlay {
+    pub fn with_scale_bar(mut self, corner: OverlayCorner, units: ScaleUnits) -> Self {
+        self.scale_bar = Some((corner, units));
+        self
+    }
+
+    pub fn with_max_scale_bar_width(mut self, max_width: u32) -> Self {
+        self.max_scale_bar_width = max_width;
+        self
+    }
+
+    pub fn with_attribution(mut self, corner: OverlayCorner) -> Self {
+        self.attribution = Some(corner);
+        self
+    }
+
+    pub fn with_text_scale(mut self, text_scale: u32) -> Self {
+        self.text_scale = text_scale.max(1);
+        self
+    }
+
+    /// Scales the text and the maximum width of the scale bar for images which are rendered with
+    /// the given `pixel_ratio`. The text is scaled by whole font pixels.
+    pub fn scaled(&self, pixel_ratio: f64) -> Self {
+        Self {
+            max_scale_bar_width: (self.max_scale_bar_width as f64 * pixel_ratio).round() as u32,
+            text_scale: ((self.text_scale as f64 * pixel_ratio).round() as u32).max(1),
+            ..self.clone()
+        }
+    }
+
+    /// Draws the overlay onto the tightly packed RGBA `frame` of `width` x `height` pixels.
+    ///
+    /// `meters_per_pixel` is the ground resolution in the center of the frame. If it is `None`,
+    /// no scale bar is drawn.
+    pub fn draw(
+        &self,
+        frame: &mut [u8],
+        width: u32,
+        height: u32,
+        meters_per_pixel: Option<f64>,
+        attribution: &str,
+    ) {
+        let mut canvas = Canvas {
+            frame,
+            width,
+            height,
+        };
+
+        if let (Some((corner, units)), Some(meters_per_pixel)) = (self.scale_bar, meters_per_pixel)
+        {
+            if let Some((bar_width, label)) =
+                scale_bar(meters_per_pixel, self.max_scale_bar_width, units)
+            {
+                self.draw_scale_bar(&mut canvas, corner, bar_width, &label);
+            }
+        }
+
+        if let Some(corner) = self.attribution {
+            if !attribution.is_empty() {
+                self.draw_label(&mut canvas, corner, attribution);
+            }
+        }
+    }
+
+    fn text_size(&self, text: &str) -> (u32, u32) {
+        let glyphs = text.chars().count() as u32;
+        let width = (glyphs * (GLYPH_WIDTH + 1)).saturating_sub(1) * self.text_scale;
+        (width, GLYPH_HEIGHT * self.text_scale)
+    }
+
+    /// Returns the upper left corner of a box of `size` which is placed in `corner`.
+    fn place(canvas: &Canvas, corner: OverlayCorner, size: (u32, u32)) -> (i64, i64) {
+        let right = canvas.width as i64 - size.0 as i64 - MARGIN as i64;
+        let bottom = canvas.height as i64 - size.1 as i64 - MARGIN as i64;
+        match corner {
+            OverlayCorner::TopLeft => (MARGIN as i64, MARGIN as i64),
+            OverlayCorner::TopRight => (right, MARGIN as i64),
+            OverlayCorner::BottomLeft => (MARGIN as i64, bottom),
+            OverlayCorner::BottomRight => (right, bottom),
+        }
+    }
+
+    fn draw_label(&self, canvas: &mut Canvas, corner: OverlayCorner, text: &str) {
+        let (text_width, text_height) = self.text_size(text);
+        let size = (text_width + 2 * MARGIN, text_height + 2 * MARGIN);
+        let (x, y) = Self::place(canvas, corner, size);
+
+        canvas.fill_rect(x, y, size.0, size.1, BACKGROUND);
+        self.draw_text(canvas, x + MARGIN as i64, y + MARGIN as i64, text);
+    }
+
+    /// Draws the bar with end ticks and the label above it.
+    fn draw_scale_bar(
+        &self,
+        canvas: &mut Canvas,
+        corner: OverlayCorner,
+        bar_width: u32,
+        label: &str,
+    ) {
+        let (label_width, label_height) = self.text_size(label);
+        let tick_height = 4 * self.text_scale;
+        let line = self.text_scale;
+
+        let content_width = bar_width.max(label_width);
+        let content_height = label_height + MARGIN + tick_height;
+        let size = (content_width + 2 * MARGIN, content_height + 2 * MARGIN);
+        let (x, y) = Self::place(canvas, corner, size);
+
+        canvas.fill_rect(x, y, size.0, size.1, BACKGROUND);
+        self.draw_text(canvas, x + MARGIN as i64, y + MARGIN as i64, label);
+
+        let bar_x = x + MARGIN as i64;
+        let bar_bottom = y + (MARGIN + content_height) as i64;
+        canvas.fill_rect(bar_x, bar_bottom - line as i64, bar_width, line, FOREGROUND);
+        canvas.fill_rect(
+            bar_x,
+            bar_bottom - tick_height as i64,
+            line,
+            tick_height,
+            FOREGROUND,
+        );
+        canvas.fill_rect(
+            bar_x + bar_width as i64 - line as i64,
+            bar_bottom - tick_height as i64,
+            line,
+            tick_height,
+            FOREGROUND,
+        );
+    }
+
+    fn draw_text(&self, canvas: &mut Canvas, x: i64, y: i64, text: &str) {
+        let scale = self.text_scale as i64;
+        for (i, character) in text.chars().enumerate() {
+            let glyph_x = x + i as i64 * (GLYPH_WIDTH as i64 + 1) * scale;
+            for (row, bits) in glyph(character).iter().enumerate() {
+                for column in 0..GLYPH_WIDTH {
+                    if bits & (1 << (GLYPH_WIDTH - 1 - column)) != 0 {
+                        canvas.fill_rect(
+                            glyph_x + column as i64 * scale,
+                            y + row as i64 * scale,
+                            self.text_scale,
+                            self.text_scale,
+                            FOREGROUND,
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Chooses the longest bar of a round length (1, 2 or 5 times a power of ten) which fits into
+/// `max_width` pixels. Returns the width of the bar in pixels and its label.
+pub fn scale_bar(
+    meters_per_pixel: f64,
+    max_width: u32,
+    units: ScaleUnits,
+) -> Option<(u32, String)> {
+    if !meters_per_pixel.is_finite() || meters_per_pixel <= 0.0 || max_width == 0 {
+        return None;
+    }
+
+    let max_meters = meters_per_pixel * max_width as f64;
+    let (unit_meters, unit) = match units {
+        ScaleUnits::Metric if max_meters >= 1000.0 => (1000.0, "km"),
+        ScaleUnits::Metric => (1.0, "m"),
+        ScaleUnits::Imperial if max_meters >= FEET_PER_MILE * METERS_PER_FOOT => {
+            (FEET_PER_MILE * METERS_PER_FOOT, "mi")
+        }
+        ScaleUnits::Imperial => (METERS_PER_FOOT, "ft"),
+    };
+
+    let max_length = max_meters / unit_meters;
+    let magnitude = 10_f64.powf(max_length.log10().floor());
+    let length = [5.0, 2.0, 1.0]
+        .into_iter()
+        .map(|factor| factor * magnitude)
+        .find(|length| *length <= max_length)?;
+
+    let width = (length * unit_meters / meters_per_pixel).round() as u32;
+    Some((width, format!("{} {unit}", format_length(length))))
+}
+
+fn format_length(length: f64) -> String {
+    if length >= 1.0 {
+        format!("{}", length.round() as u64)
+    } else {
+        // Lengths below 1 are powers of ten times 1, 2 or 5, so they are exact with few digits
+        let formatted = format!("{length:.6}");
+        formatted.trim_end_matches('0').to_string()
+    }
+}
+
+/// Joins the attributions of the sources and removes HTML tags. Each distinct attribution is
+/// included once.
+pub fn attribution_text<'a>(attributions: impl IntoIterator<Item = &'a str>) -> String {
+    let mut distinct: Vec<String> = Vec::new();
+    for attribution in attributions {
+        let mut text = String::with_capacity(attribution.len());
+        let mut in_tag = false;
+        for character in attribution.chars() {
+            match character {
+                '<' => in_tag = true,
+                '>' => in_tag = false,
+                _ if !in_tag => text.push(character),
+                _ => {}
+            }
+        }
+
+        let text = text.replace("&copy;", "©").replace("&amp;", "&");
+        let text = text.trim().to_string();
+        if !text.is_empty() && !distinct.contains(&text) {
+            distinct.push(text);
+        }
+    }
+    distinct.join(" | ")
+}
+
+struct Canvas<'a> {
+    frame: &'a mut [u8],
+    width: u32,
+    height: u32,
+}
+
+impl Canvas<'_> {
+    /// Blends `color` over the pixels of the rectangle. Pixels outside the frame are skipped.
+    fn fill_rect(&mut self, x: i64, y: i64, width: u32, height: u32, color: [u8; 4]) {
+        let x_range = x.max(0)..(x + width as i64).min(self.width as i64);
+        let y_range = y.max(0)..(y + height as i64).min(self.height as i64);
+
+        for row in y_range {
+            for column in x_range.clone() {
+                let offset = ((row as u32 * self.width + column as u32) * 4) as usize;
+                let Some(pixel) = self.frame.get_mut(offset..offset + 4) else { continue; };
+                source_over(pixel, color);
+            }
+        }
+    }
+}
+
+/// Composites the `color` over the `pixel` with the source-over operator. Both are not
+/// premultiplied, so that transparent parts of the frame take the color of the overlay.
+fn source_over(pixel: &mut [u8], color: [u8; 4]) {
+    let source_alpha = color[3] as f32 / 255.0;
+    let target_alpha = pixel[3] as f32 / 255.0 * (1.0 - source_alpha);
+    let alpha = source_alpha + target_alpha;
+    if alpha == 0.0 {
+        return;
+    }
+
+    for (target, source) in pixel[..3].iter_mut().zip(&color[..3]) {
+        *target =
+            ((*source as f32 * source_alpha + *target as f32 * target_alpha) / alpha).round() as u8;
+    }
+    pixel[3] = (alpha * 255.0).round() as u8;
+}
+
+/// Returns the rows of the glyph for `character`. The most significant of the five bits is the
+/// leftmost pixel.
+fn glyph(character: char) -> [u8; GLYPH_HEIGHT as usize] {
+    match character.to_ascii_uppercase() {
+        ' ' => [
+            0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000,
+        ],
+        '0' => [
+            0b01110, 0b10001, 0b10011, 0b10101, 0b11001, 0b10001, 0b01110,
+        ],
+        '1' => [
+            0b00100, 0b01100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110,
+        ],
+        '2' => [
+            0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b01000, 0b11111,
+        ],
+        '3' => [
+            0b11111, 0b00010, 0b00100, 0b00010, 0b00001, 0b10001, 0b01110,
+        ],
+        '4' => [
+            0b00010, 0b00110, 0b01010, 0b10010, 0b11111, 0b00010, 0b00010,
+        ],
+        '5' => [
+            0b11111, 0b10000, 0b11110, 0b00001, 0b00001, 0b10001, 0b01110,
+        ],
+        '6' => [
+            0b00110, 0b01000, 0b10000, 0b11110, 0b10001, 0b10001, 0b01110,
+        ],
+        '7' => [
+            0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b01000, 0b01000,
+        ],
+        '8' => [
+            0b01110, 0b10001, 0b10001, 0b01110, 0b10001, 0b10001, 0b01110,
+        ],
+        '9' => [
+            0b01110, 0b10001, 0b10001, 0b01111, 0b00001, 0b00010, 0b01100,
+        ],
+        'A' => [
+            0b01110, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001,
+        ],
+        'B' => [
+            0b11110, 0b10001, 0b10001, 0b11110, 0b10001, 0b10001, 0b11110,
+        ],
+        'C' => [
+            0b01110, 0b10001, 0b10000, 0b10000, 0b10000, 0b10001, 0b01110,
+        ],
+        'D' => [
+            0b11100, 0b10010, 0b10001, 0b10001, 0b10001, 0b10010, 0b11100,
+        ],
+        'E' => [
+            0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b11111,
+        ],
+        'F' => [
+            0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b10000,
+        ],
+        'G' => [
+            0b01110, 0b10001, 0b10000, 0b10111, 0b10001, 0b10001, 0b01111,
+        ],
+        'H' => [
+            0b10001, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001,
+        ],
+        'I' => [
+            0b01110, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110,
+        ],
+        'J' => [
+            0b00111, 0b00010, 0b00010, 0b00010, 0b00010, 0b10010, 0b01100,
+        ],
+        'K' => [
+            0b10001, 0b10010, 0b10100, 0b11000, 0b10100, 0b10010, 0b10001,
+        ],
+        'L' => [
+            0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b11111,
+        ],
+        'M' => [
+            0b10001, 0b11011, 0b10101, 0b10101, 0b10001, 0b10001, 0b10001,
+        ],
+        'N' => [
+            0b10001, 0b10001, 0b11001, 0b10101, 0b10011, 0b10001, 0b10001,
+        ],
+        'O' => [
+            0b01110, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110,
+        ],
+        'P' => [
+            0b11110, 0b10001, 0b10001, 0b11110, 0b10000, 0b10000, 0b10000,
+        ],
+        'Q' => [
+            0b01110, 0b10001, 0b10001, 0b10001, 0b10101, 0b10010, 0b01101,
+        ],
+        'R' => [
+            0b11110, 0b10001, 0b10001, 0b11110, 0b10100, 0b10010, 0b10001,
+        ],
+        'S' => [
+            0b01111, 0b10000, 0b10000, 0b01110, 0b00001, 0b00001, 0b11110,
+        ],
+        'T' => [
+            0b11111, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100,
+        ],
+        'U' => [
+            0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110,
+        ],
+        'V' => [
+            0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01010, 0b00100,
+        ],
+        'W' => [
+            0b10001, 0b10001, 0b10001, 0b10101, 0b10101, 0b10101, 0b01010,
+        ],
+        'X' => [
+            0b10001, 0b10001, 0b01010, 0b00100, 0b01010, 0b10001, 0b10001,
+        ],
+        'Y' => [
+            0b10001, 0b10001, 0b10001, 0b01010, 0b00100, 0b00100, 0b00100,
+        ],
+        'Z' => [
+            0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0b11111,
+        ],
+        '.' => [
+            0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b01100, 0b01100,
+        ],
+        ',' => [
+            0b00000, 0b00000, 0b00000, 0b00000, 0b01100, 0b00100, 0b01000,
+        ],
+        '-' => [
+            0b00000, 0b00000, 0b00000, 0b11111, 0b00000, 0b00000, 0b00000,
+        ],
+        '+' => [
+            0b00000, 0b00100, 0b00100, 0b11111, 0b00100, 0b00100, 0b00000,
+        ],
+        '_' => [
+            0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b11111,
+        ],
+        '/' => [
+            0b00000, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0b00000,
+        ],
+        ':' => [
+            0b00000, 0b01100, 0b01100, 0b00000, 0b01100, 0b01100, 0b00000,
+        ],
+        '(' => [
+            0b00010, 0b00100, 0b01000, 0b01000, 0b01000, 0b00100, 0b00010,
+        ],
+        ')' => [
+            0b01000, 0b00100, 0b00010, 0b00010, 0b00010, 0b00100, 0b01000,
+        ],
+        '&' => [
+            0b01100, 0b10010, 0b10100, 0b01000, 0b10101, 0b10010, 0b01101,
+        ],
+        '\'' => [
+            0b01100, 0b00100, 0b01000, 0b00000, 0b00000, 0b00000, 0b00000,
+        ],
+        '|' => [
+            0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100,
+        ],
+        '©' => [
+            0b01110, 0b10001, 0b10111, 0b10101, 0b10111, 0b10001, 0b01110,
+        ],
+        _ => [
+            0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b00000, 0b00100,
+        ],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{attribution_text, scale_bar, Overlay, OverlayCorner, ScaleUnits};
+
+    #[test]
+    fn test_scale_bar() {
+        assert_eq!(
+            scale_bar(10.0, 100, ScaleUnits::Metric),
+            Some((100, "1 km".to_string()))
+        );
+        assert_eq!(
+            scale_bar(3.0, 100, ScaleUnits::Metric),
+            Some((67, "200 m".to_string()))
+        );
+        assert_eq!(
+            scale_bar(0.004, 100, ScaleUnits::Metric),
+            Some((50, "0.2 m".to_string()))
+        );
+        assert_eq!(
+            scale_bar(100.0, 100, ScaleUnits::Imperial),
+            Some((80, "5 mi".to_string()))
+        );
+        assert_eq!(scale_bar(0.0, 100, ScaleUnits::Metric), None);
+    }
+
+    #[test]
+    fn test_scaled() {
+        let overlay = Overlay::default().with_text_scale(2);
+        assert_eq!(
+            overlay.scaled(1.5),
+            Overlay::default()
+                .with_text_scale(3)
+                .with_max_scale_bar_width(150)
+        );
+        assert_eq!(overlay.scaled(0.1).text_scale, 1);
+    }
+
+    #[test]
+    fn test_draw() {
+        assert_eq!(
+            attribution_text([
+                "<a href=\"https://www.openstreetmap.org/copyright\">&copy; OpenStreetMap</a>",
+                "© OpenStreetMap",
+                "MapTiler",
+            ]),
+            "© OpenStreetMap | MapTiler"
+        );
+
+        let (width, height) = (200, 50);
+        let mut frame = vec![0; (width * height * 4) as usize];
+        Overlay::default()
+            .with_scale_bar(OverlayCorner::BottomLeft, ScaleUnits::Metric)
+            .with_attribution(OverlayCorner::TopRight)
+            .draw(&mut frame, width, height, Some(10.0), "© OpenStreetMap");
+
+        let pixel = |x: u32, y: u32| {
+            let offset = ((y * width + x) * 4) as usize;
+            [
+                frame[offset],
+                frame[offset + 1],
+                frame[offset + 2],
+                frame[offset + 3],
+            ]
+        };
+        // The background of the attribution keeps its color over transparent pixels
+        assert_eq!(pixel(width - 5, 5), [255, 255, 255, 204]);
+        // The bar is drawn at the bottom of the scale bar box
+        assert_eq!(pixel(50, height - 9), [0, 0, 0, 255]);
+        // The center of the image is untouched
+        assert_eq!(pixel(150, 30), [0, 0, 0, 0]);
+
+        // Over opaque pixels, the background is blended with the frame
+        let mut frame = [0, 0, 0, 255].repeat((width * height) as usize);
+        Overlay::default()
+            .with_attribution(OverlayCorner::TopRight)
+            .draw(&mut frame, width, height, Some(10.0), "© OpenStreetMap");
+        let offset = ((5 * width + width - 5) * 4) as usize;
+        assert_eq!(frame[offset..offset + 4], [204, 204, 204, 255]);
+    }
+}
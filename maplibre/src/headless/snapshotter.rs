@@ -0,0 +1,175 @@
+//! Renders an image of a location with a single call, like the map snapshotter of MapLibre
+//! Native. The renderer, the kernel and the plugins are set up by the [`Snapshotter`].
+
+use thiserror::Error;
+
+use crate::{
+    coords::{LatLon, Zoom},
+    headless::{
+        create_headless_renderer,
+        environment::HeadlessEnvironment,
+        map::{HeadlessMap, HeadlessMapBuilder, HeadlessMapError},
+        HeadlessPlugin,
+    },
+    map::MapError,
+    plugin::Plugin,
+    render::RenderPlugin,
+    style::Style,
+    vector::{DefaultVectorTransferables, VectorPlugin},
+    window::WindowSize,
+};
+
+#[derive(Error, Debug)]
+pub enum SnapshotError {
+    #[error("creating the map failed")]
+    Map(#[from] MapError),
+    #[error("rendering the snapshot failed")]
+    Render(#[from] HeadlessMapError),
+}
+
+/// An image with tightly packed RGBA pixels. The first row is the top of the image.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RgbaImage {
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<u8>,
+}
+
+impl RgbaImage {
+    pub fn to_png(&self) -> Result<Vec<u8>, png::EncodingError> {
+        let mut png = Vec::new();
+        let mut encoder = png::Encoder::new(&mut png, self.width, self.height);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.set_color(png::ColorType::Rgba);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&self.data)?;
+        writer.finish()?;
+        Ok(png)
+    }
+}
+
+/// Renders images of a style. The map is created by the first snapshot and reused by the
+/// following snapshots, so that fetched and tessellated tiles are shared between them.
+///
+/// ```no_run
+/// # async fn example() -> Result<(), maplibre::headless::snapshotter::SnapshotError> {
+/// use maplibre::{
+///     coords::{LatLon, Zoom},
+///     headless::snapshotter::Snapshotter,
+///     style::Style,
+///     window::WindowSize,
+/// };
+///
+/// let mut snapshotter = Snapshotter::new(Style::default(), WindowSize::new(800, 600).unwrap());
+/// let image = snapshotter
+///     .snapshot(LatLon::new(48.137154, 11.576124), Zoom::new(12.0), 0.0, 0.0)
+///     .await?;
+/// std::fs::write("munich.png", image.to_png().unwrap()).unwrap();
+/// # Ok(())
+/// # }
+/// ```
+pub struct Snapshotter {
+    style: Style,
+    size: WindowSize,
+    pixel_ratio: f64,
+    cache_path: Option<String>,
+    map: Option<HeadlessMap>,
+}
+
+impl Snapshotter {
+    /// Renders `style` into images of the given `size` in logical pixels.
+    pub fn new(style: Style, size: WindowSize) -> Self {
+        Self {
+            style,
+            size,
+            pixel_ratio: 1.0,
+            cache_path: None,
+            map: None,
+        }
+    }
+
+    /// See [`HeadlessMapBuilder::with_pixel_ratio`].
+    pub fn with_pixel_ratio(mut self, pixel_ratio: f64) -> Self {
+        self.pixel_ratio = pixel_ratio;
+        self
+    }
+
+    /// Caches the fetched tiles in the directory `cache_path`.
+    pub fn with_cache_path(mut self, cache_path: impl Into<String>) -> Self {
+        self.cache_path = Some(cache_path.into());
+        self
+    }
+
+    /// The map which renders the snapshots, or `None` before the first snapshot.
+    pub fn map_mut(&mut self) -> Option<&mut HeadlessMap> {
+        self.map.as_mut()
+    }
+
+    /// Renders the map centered at `center`. The `bearing` is clockwise from north and the
+    /// `pitch` is the tilt away from looking straight down, both in degrees.
+    pub async fn snapshot(
+        &mut self,
+        center: LatLon,
+        zoom: Zoom,
+        bearing: f64,
+        pitch: f64,
+    ) -> Result<RgbaImage, SnapshotError> {
+        let size = self.size;
+        let physical_size = size.scaled(self.pixel_ratio);
+
+        let map = self.map().await?;
+        map.set_bearing(bearing);
+        map.set_pitch(pitch);
+        let data = map.render_thumbnail(center, zoom, size).await?;
+
+        Ok(RgbaImage {
+            width: physical_size.width(),
+            height: physical_size.height(),
+            data,
+        })
+    }
+
+    async fn map(&mut self) -> Result<&mut HeadlessMap, MapError> {
+        if self.map.is_none() {
+            let (kernel, renderer) =
+                create_headless_renderer(self.size.width(), self.cache_path.clone()).await;
+            let plugins: Vec<Box<dyn Plugin<HeadlessEnvironment>>> = vec![
+                Box::new(RenderPlugin::default()),
+                Box::new(VectorPlugin::<DefaultVectorTransferables>::default()),
+                Box::new(HeadlessPlugin::new(false)),
+            ];
+
+            let map = HeadlessMapBuilder::new(renderer, kernel)
+                .with_style(self.style.clone())
+                .with_plugins(plugins)
+                .with_size(self.size)
+                .with_pixel_ratio(self.pixel_ratio)
+                .build()?;
+            self.map = Some(map);
+        }
+
+        Ok(self.map.as_mut().expect("map was created above"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RgbaImage;
+
+    #[test]
+    fn test_to_png() {
+        let image = RgbaImage {
+            width: 2,
+            height: 1,
+            data: vec![255, 0, 0, 255, 0, 0, 255, 128],
+        };
+
+        let png = image.to_png().unwrap();
+        let mut reader = png::Decoder::new(png.as_slice()).read_info().unwrap();
+        let mut data = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut data).unwrap();
+
+        assert_eq!((info.width, info.height), (2, 1));
+        assert_eq!(data, image.data);
+    }
+}
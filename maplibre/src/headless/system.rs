@@ -2,6 +2,7 @@ use std::{borrow::Cow, sync::Arc};
 
 use crate::{
     context::MapContext,
+    headless::georeference::GeoReference,
     render::{
         resource::{BufferedTextureHead, Head},
         Renderer,
@@ -10,17 +11,21 @@ use crate::{
 };
 
 /// Stage which writes the current contents of the GPU/CPU buffer in [`BufferedTextureHead`]
-/// to disk as PNG.
+/// to disk as PNG. Optionally, a world file is written alongside.
 pub struct WriteSurfaceBufferSystem {
     frame: u64,
     write_to_disk: bool,
+    flip_y: bool,
+    write_world_file: bool,
 }
 
 impl WriteSurfaceBufferSystem {
-    pub fn new(write_to_disk: bool) -> Self {
+    pub fn new(write_to_disk: bool, flip_y: bool, write_world_file: bool) -> Self {
         Self {
             frame: 0,
             write_to_disk,
+            flip_y,
+            write_world_file,
         }
     }
 }
@@ -33,6 +38,7 @@ impl System for WriteSurfaceBufferSystem {
     fn run(
         &mut self,
         MapContext {
+            view_state,
             renderer:
                 Renderer {
                     resources: state,
@@ -55,10 +61,25 @@ impl System for WriteSurfaceBufferSystem {
                 let padded_buffer = buffer_slice.get_mapped_range();
 
                 if self.write_to_disk {
-                    buffered_texture.write_png(
-                        &padded_buffer,
-                        format!("frame_{}.png", current_frame).as_str(),
-                    );
+                    let path = format!("frame_{}.png", current_frame);
+                    buffered_texture.write_png(&padded_buffer, path.as_str(), self.flip_y);
+
+                    if self.write_world_file {
+                        let size = surface.size();
+                        match GeoReference::from_view_state(view_state, size) {
+                            Some(reference) => {
+                                let reference = if self.flip_y {
+                                    reference.flip_y(size.height())
+                                } else {
+                                    reference
+                                };
+                                if let Err(e) = reference.write_world_file(&path) {
+                                    log::error!("Failed to write world file for {path}: {e}");
+                                }
+                            }
+                            None => log::warn!("Unable to georeference {path}"),
+                        }
+                    }
                 }
 
                 // With the current interface, we have to make sure all mapped views are
@@ -0,0 +1,238 @@
+//! Caches tessellated tiles so that rendering the same area repeatedly does not tessellate the
+//! same tiles again.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    hash::Hasher,
+};
+
+use crate::{coords::WorldTileCoords, style::Style, util::hash::Fnv1aHasher};
+
+/// Counts the lookups of a [`TessellationCache`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TessellationCacheStats {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+impl TessellationCacheStats {
+    /// The ratio of lookups which were served from the cache, or 0 if there were no lookups.
+    pub fn hit_rate(&self) -> f64 {
+        let lookups = self.hits + self.misses;
+        if lookups == 0 {
+            0.0
+        } else {
+            self.hits as f64 / lookups as f64
+        }
+    }
+}
+
+/// Identifies a tessellated tile. The hash covers the requested source layers and the style
+/// layers which read from them, see [`TessellationCacheKey::new`]. The hashes are stable, so
+/// that the key can name the files of a
+/// [`TessellationStore`](crate::headless::tessellation_store::TessellationStore).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TessellationCacheKey {
+    pub coords: WorldTileCoords,
+    pub style_layer_hash: u64,
+    /// The hash of the fetched data which was tessellated, see
+    /// [`StoredTile::content_hash`](crate::io::tile_expiry::StoredTile::content_hash)
+    pub content_hash: u64,
+}
+
+impl TessellationCacheKey {
+    /// A re-fetched tile with a new `content_hash` is tessellated again, while a tile with the
+    /// same data is served from the cache.
+    pub fn new(
+        coords: WorldTileCoords,
+        style: &Style,
+        source_layers: &[&str],
+        content_hash: u64,
+    ) -> Self {
+        let mut hasher = Fnv1aHasher::default();
+
+        let mut sorted_source_layers = source_layers.to_vec();
+        sorted_source_layers.sort_unstable();
+        hasher.write_usize(sorted_source_layers.len());
+        for source_layer in &sorted_source_layers {
+            write_str(&mut hasher, Some(source_layer));
+        }
+
+        for layer in style.layers.iter().filter(|layer| {
+            layer.source_layer.as_deref().map_or(false, |source_layer| {
+                sorted_source_layers.contains(&source_layer)
+            })
+        }) {
+            write_str(&mut hasher, Some(&layer.id));
+            write_str(&mut hasher, layer.source.as_deref());
+            write_str(&mut hasher, layer.source_layer.as_deref());
+            for zoom in [layer.minzoom, layer.maxzoom] {
+                hasher.write(&zoom.map_or([0, 0], |zoom| [1, zoom]));
+            }
+        }
+
+        Self {
+            coords,
+            style_layer_hash: hasher.finish(),
+            content_hash,
+        }
+    }
+}
+
+/// Writes `value` into `hasher`, so that `None` and the empty string are distinguished and that
+/// consecutive strings cannot be split differently.
+fn write_str(hasher: &mut Fnv1aHasher, value: Option<&str>) {
+    match value {
+        Some(value) => {
+            hasher.write_u8(1);
+            hasher.write_usize(value.len());
+            hasher.write(value.as_bytes());
+        }
+        None => hasher.write_u8(0),
+    }
+}
+
+/// A least recently used cache of tessellated tiles with a fixed capacity.
+pub struct TessellationCache<V> {
+    capacity: usize,
+    entries: HashMap<TessellationCacheKey, V>,
+    /// Keys from least to most recently used
+    order: VecDeque<TessellationCacheKey>,
+    stats: TessellationCacheStats,
+}
+
+impl<V: Clone> TessellationCache<V> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: HashMap::with_capacity(capacity),
+            order: VecDeque::with_capacity(capacity),
+            stats: TessellationCacheStats::default(),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn stats(&self) -> TessellationCacheStats {
+        self.stats
+    }
+
+    /// Whether a value is cached for `key`. Unlike [`TessellationCache::get`], this is not
+    /// counted as lookup.
+    pub fn contains_key(&self, key: &TessellationCacheKey) -> bool {
+        self.entries.contains_key(key)
+    }
+
+    /// Returns a copy of the cached value and marks it as most recently used.
+    pub fn get(&mut self, key: &TessellationCacheKey) -> Option<V> {
+        let Some(value) = self.entries.get(key) else {
+            self.stats.misses += 1;
+            return None;
+        };
+
+        self.stats.hits += 1;
+        self.order.retain(|other| other != key);
+        self.order.push_back(*key);
+        Some(value.clone())
+    }
+
+    /// Inserts the value and evicts the least recently used value if the cache is full.
+    pub fn insert(&mut self, key: TessellationCacheKey, value: V) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        if self.entries.insert(key, value).is_some() {
+            self.order.retain(|other| *other != key);
+        } else if self.entries.len() > self.capacity {
+            if let Some(evicted) = self.order.pop_front() {
+                self.entries.remove(&evicted);
+            }
+        }
+        self.order.push_back(key);
+    }
+
+    /// Removes the values for which `keep` returns `false`. The stats are kept.
+    pub fn retain(&mut self, mut keep: impl FnMut(&V) -> bool) {
+        self.entries.retain(|_, value| keep(value));
+        let entries = &self.entries;
+        self.order.retain(|key| entries.contains_key(key));
+    }
+
+    /// Removes all values. The stats are kept.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.order.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TessellationCache, TessellationCacheKey};
+    use crate::{
+        coords::{WorldTileCoords, ZoomLevel},
+        style::Style,
+    };
+
+    #[test]
+    fn test_eviction_and_stats() {
+        let style = Style::default();
+        let key = |x| {
+            TessellationCacheKey::new(
+                WorldTileCoords::from((x, 0, ZoomLevel::new(1))),
+                &style,
+                &["water"],
+                0,
+            )
+        };
+
+        let mut cache = TessellationCache::new(2);
+        cache.insert(key(0), 0);
+        cache.insert(key(1), 1);
+        assert_eq!(cache.get(&key(0)), Some(0));
+
+        // The least recently used tile is evicted
+        cache.insert(key(2), 2);
+        assert_eq!(cache.get(&key(1)), None);
+        assert_eq!(cache.get(&key(0)), Some(0));
+        assert_eq!(cache.len(), 2);
+
+        assert_eq!(cache.stats().hits, 2);
+        assert_eq!(cache.stats().misses, 1);
+        assert!((cache.stats().hit_rate() - 2.0 / 3.0).abs() < 1e-9);
+
+        // Other source layers result in other keys
+        let other = TessellationCacheKey::new(
+            WorldTileCoords::from((0, 0, ZoomLevel::new(1))),
+            &style,
+            &["transportation"],
+            0,
+        );
+        assert_ne!(key(0), other);
+
+        // Other data results in other keys
+        let refetched = TessellationCacheKey::new(
+            WorldTileCoords::from((0, 0, ZoomLevel::new(1))),
+            &style,
+            &["water"],
+            1,
+        );
+        assert_ne!(key(0), refetched);
+
+        cache.retain(|value| *value != 0);
+        assert_eq!(cache.len(), 1);
+        cache.insert(key(3), 3);
+        assert_eq!(cache.get(&key(2)), Some(2));
+        assert_eq!(cache.get(&key(3)), Some(3));
+    }
+}
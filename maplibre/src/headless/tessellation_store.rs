@@ -0,0 +1,287 @@
+//! Stores tessellated tiles as files in a directory, so that a region can be tessellated ahead
+//! of time by [`HeadlessMap::pregenerate`] and rendered by a later process without tessellating
+//! it again.
+//!
+//! [`HeadlessMap::pregenerate`]: crate::headless::map::HeadlessMap::pregenerate
+
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use geozero::mvt::{tile::Layer, Message};
+
+use crate::{
+    headless::tessellation_cache::TessellationCacheKey, render::ShaderVertex,
+    tessellation::OverAlignedVertexBuffer, vector::DefaultLayerTesselated,
+};
+
+/// Identifies the encoding of the stored files. Files with another version are not read.
+const FORMAT_VERSION: u32 = 1;
+
+/// Stores the tessellated layers of tiles in a directory. The files are named after the
+/// [`TessellationCacheKey`] of the tile, so a stored tile is only read if it was tessellated from
+/// the same data for the same style layers.
+#[derive(Debug, Clone)]
+pub struct TessellationStore {
+    directory: PathBuf,
+}
+
+impl TessellationStore {
+    /// The `directory` is created when the first tile is stored.
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        Self {
+            directory: directory.into(),
+        }
+    }
+
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    /// Returns the path of the file in which the tile with the `key` is stored.
+    pub fn path(&self, key: &TessellationCacheKey) -> PathBuf {
+        let coords = key.coords;
+        self.directory.join(format!(
+            "{}-{}-{}-{:016x}-{:016x}.tessellated",
+            coords.z, coords.x, coords.y, key.style_layer_hash, key.content_hash
+        ))
+    }
+
+    pub fn contains(&self, key: &TessellationCacheKey) -> bool {
+        self.path(key).exists()
+    }
+
+    /// Reads the layers of the tile with the `key`. Returns `None` if it is not stored.
+    pub fn read(
+        &self,
+        key: &TessellationCacheKey,
+    ) -> io::Result<Option<Vec<Box<DefaultLayerTesselated>>>> {
+        let data = match fs::read(self.path(key)) {
+            Ok(data) => data,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        decode_layers(key, &data).map(Some)
+    }
+
+    /// Stores the `layers` of the tile with the `key`. The file is written completely before it
+    /// is moved into place, so that concurrent readers never see a partial tile.
+    pub fn write(
+        &self,
+        key: &TessellationCacheKey,
+        layers: &[Box<DefaultLayerTesselated>],
+    ) -> io::Result<()> {
+        fs::create_dir_all(&self.directory)?;
+
+        let path = self.path(key);
+        let mut partial = path.as_os_str().to_owned();
+        partial.push(".partial");
+        fs::File::create(&partial)?.write_all(&encode_layers(layers))?;
+        fs::rename(partial, path)
+    }
+}
+
+fn write_u32(data: &mut Vec<u8>, value: u32) {
+    data.extend_from_slice(&value.to_le_bytes());
+}
+
+fn write_f32s(data: &mut Vec<u8>, values: &[f32]) {
+    for value in values {
+        data.extend_from_slice(&value.to_le_bytes());
+    }
+}
+
+/// Encodes the `layers` with little-endian integers and floats. Every list is prefixed with its
+/// length.
+fn encode_layers(layers: &[Box<DefaultLayerTesselated>]) -> Vec<u8> {
+    let mut data = Vec::new();
+    write_u32(&mut data, FORMAT_VERSION);
+    write_u32(&mut data, layers.len() as u32);
+
+    for layer in layers {
+        let layer_data = layer.layer_data.encode_to_vec();
+        write_u32(&mut data, layer_data.len() as u32);
+        data.extend_from_slice(&layer_data);
+
+        write_u32(&mut data, layer.feature_indices.len() as u32);
+        for count in &layer.feature_indices {
+            write_u32(&mut data, *count);
+        }
+
+        let buffer = &layer.buffer.buffer;
+        write_u32(&mut data, buffer.vertices.len() as u32);
+        for vertex in &buffer.vertices {
+            write_f32s(&mut data, &vertex.position);
+            write_f32s(&mut data, &vertex.normal);
+        }
+
+        write_u32(&mut data, buffer.indices.len() as u32);
+        for index in &buffer.indices {
+            write_u32(&mut data, *index);
+        }
+        write_u32(&mut data, layer.buffer.usable_indices);
+    }
+
+    data
+}
+
+/// Reads the values of an encoded tile.
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> io::Result<&'a [u8]> {
+        if len > self.data.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "the stored tile is truncated",
+            ));
+        }
+        let (taken, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(taken)
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn f32(&mut self) -> io::Result<f32> {
+        self.u32().map(f32::from_bits)
+    }
+
+    /// Reads the length of a list of values with `size` bytes, which needs to fit into the
+    /// remaining data.
+    fn len(&mut self, size: usize) -> io::Result<usize> {
+        let len = self.u32()? as usize;
+        if len.saturating_mul(size) > self.data.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "the stored tile is truncated",
+            ));
+        }
+        Ok(len)
+    }
+}
+
+fn decode_layers(
+    key: &TessellationCacheKey,
+    data: &[u8],
+) -> io::Result<Vec<Box<DefaultLayerTesselated>>> {
+    let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+
+    let mut reader = Reader { data };
+    if reader.u32()? != FORMAT_VERSION {
+        return Err(invalid("the stored tile has an unknown format"));
+    }
+
+    let layer_count = reader.len(1)?;
+    let mut layers = Vec::new();
+    for _ in 0..layer_count {
+        let layer_data_len = reader.len(1)?;
+        let layer_data = Layer::decode(reader.take(layer_data_len)?)
+            .map_err(|_| invalid("the stored layer is malformed"))?;
+
+        let feature_count = reader.len(4)?;
+        let feature_indices = (0..feature_count)
+            .map(|_| reader.u32())
+            .collect::<io::Result<Vec<_>>>()?;
+
+        let vertex_count = reader.len(16)?;
+        let vertices = (0..vertex_count)
+            .map(|_| {
+                Ok(ShaderVertex::new(
+                    [reader.f32()?, reader.f32()?],
+                    [reader.f32()?, reader.f32()?],
+                ))
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+
+        let index_count = reader.len(4)?;
+        let indices = (0..index_count)
+            .map(|_| reader.u32())
+            .collect::<io::Result<Vec<_>>>()?;
+        let usable_indices = reader.u32()?;
+        if usable_indices as usize > indices.len() {
+            return Err(invalid("the stored tile has too few indices"));
+        }
+
+        layers.push(Box::new(DefaultLayerTesselated {
+            coords: key.coords,
+            buffer: OverAlignedVertexBuffer::from_iters(vertices, indices, usable_indices),
+            feature_indices,
+            layer_data,
+        }));
+    }
+
+    Ok(layers)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use geozero::mvt::tile::Layer;
+
+    use super::TessellationStore;
+    use crate::{
+        coords::{WorldTileCoords, ZoomLevel},
+        headless::tessellation_cache::TessellationCacheKey,
+        render::ShaderVertex,
+        style::Style,
+        tessellation::OverAlignedVertexBuffer,
+        vector::DefaultLayerTesselated,
+    };
+
+    #[test]
+    fn test_round_trip() {
+        let directory = std::env::temp_dir().join(format!(
+            "maplibre-tessellation-store-{}",
+            std::process::id()
+        ));
+        let store = TessellationStore::new(&directory);
+
+        let coords = WorldTileCoords::from((1, 2, ZoomLevel::new(3)));
+        let key = TessellationCacheKey::new(coords, &Style::default(), &["water"], 7);
+        let vertices = [[0.0, 0.0], [1.5, 0.0], [0.0, -2.25]]
+            .map(|position| ShaderVertex::new(position, [0.5, 0.0]));
+        let layer = DefaultLayerTesselated {
+            coords,
+            // Padded to 4 indices, of which 3 are usable
+            buffer: OverAlignedVertexBuffer::from_iters(vertices, [0, 1, 2, 0], 3),
+            feature_indices: vec![3],
+            layer_data: Layer {
+                name: "water".to_string(),
+                extent: Some(4096),
+                ..Layer::default()
+            },
+        };
+
+        assert!(store.read(&key).unwrap().is_none());
+        store.write(&key, &[Box::new(layer.clone())]).unwrap();
+        assert!(store.contains(&key));
+
+        let read = store.read(&key).unwrap().unwrap();
+        assert_eq!(read.len(), 1);
+        assert_eq!(read[0].coords, coords);
+        assert_eq!(read[0].layer_data, layer.layer_data);
+        assert_eq!(read[0].feature_indices, layer.feature_indices);
+        assert_eq!(read[0].buffer.usable_indices, 3);
+        assert_eq!(read[0].fingerprint(), layer.fingerprint());
+
+        // Tiles of other data are stored separately
+        let refetched = TessellationCacheKey::new(coords, &Style::default(), &["water"], 8);
+        assert!(store.read(&refetched).unwrap().is_none());
+
+        // Truncated files are rejected
+        let data = fs::read(store.path(&key)).unwrap();
+        fs::write(store.path(&key), &data[..data.len() - 1]).unwrap();
+        assert!(store.read(&key).is_err());
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -6,8 +6,10 @@ use std::{
     marker::PhantomData,
     pin::Pin,
     sync::{
+        atomic::{AtomicUsize, Ordering},
         mpsc,
         mpsc::{Receiver, Sender},
+        Arc,
     },
     vec::IntoIter,
 };
@@ -198,6 +200,8 @@ pub struct SchedulerAsyncProcedureCall<K: OffscreenKernelEnvironment, S: Schedul
     channel: (Sender<Message>, Receiver<Message>),
     buffer: RefCell<Vec<Message>>,
     scheduler: S,
+    /// Counts the calls whose procedure has not finished yet
+    pending: Arc<AtomicUsize>,
     phantom_k: PhantomData<K>,
 }
 
@@ -207,9 +211,33 @@ impl<K: OffscreenKernelEnvironment, S: Scheduler> SchedulerAsyncProcedureCall<K,
             channel: mpsc::channel(),
             buffer: RefCell::new(Vec::new()),
             phantom_k: PhantomData::default(),
+            pending: Arc::new(AtomicUsize::new(0)),
             scheduler,
         }
     }
+
+    /// The number of calls whose procedure has neither finished nor been dropped by the
+    /// scheduler.
+    pub fn pending_calls(&self) -> usize {
+        self.pending.load(Ordering::Acquire)
+    }
+}
+
+/// Decrements the pending calls when it is dropped, which happens when the procedure finishes,
+/// panics or is never run because the scheduler drops it.
+struct PendingCall(Arc<AtomicUsize>);
+
+impl PendingCall {
+    fn new(pending: &Arc<AtomicUsize>) -> Self {
+        pending.fetch_add(1, Ordering::AcqRel);
+        Self(pending.clone())
+    }
+}
+
+impl Drop for PendingCall {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::AcqRel);
+    }
 }
 
 impl<K: OffscreenKernelEnvironment, S: Scheduler> AsyncProcedureCall<K>
@@ -255,9 +283,11 @@ impl<K: OffscreenKernelEnvironment, S: Scheduler> AsyncProcedureCall<K>
         procedure: AsyncProcedure<K, Self::Context>,
     ) -> Result<(), CallError> {
         let sender = self.channel.0.clone();
+        let pending = PendingCall::new(&self.pending);
 
         self.scheduler
             .schedule(move || async move {
+                let _pending = pending;
                 log::info!("Processing on thread: {:?}", std::thread::current().name());
 
                 procedure(input, SchedulerContext { sender }, K::create())
@@ -270,7 +300,12 @@ impl<K: OffscreenKernelEnvironment, S: Scheduler> AsyncProcedureCall<K>
 
 #[cfg(test)]
 pub mod tests {
-    use crate::io::apc::{Context, IntoMessage, SendError};
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use crate::io::apc::{Context, IntoMessage, PendingCall, SendError};
 
     pub struct DummyContext;
 
@@ -279,4 +314,24 @@ pub mod tests {
             Ok(())
         }
     }
+
+    #[test]
+    fn test_pending_call() {
+        let pending = Arc::new(AtomicUsize::new(0));
+
+        let first = PendingCall::new(&pending);
+        let second = PendingCall::new(&pending);
+        assert_eq!(pending.load(Ordering::Acquire), 2);
+
+        drop(first);
+        assert_eq!(pending.load(Ordering::Acquire), 1);
+
+        // A procedure which panics still finishes its call
+        let panicked = std::panic::catch_unwind(move || {
+            let _second = second;
+            panic!("procedure failed");
+        });
+        assert!(panicked.is_err());
+        assert_eq!(pending.load(Ordering::Acquire), 0);
+    }
 }
@@ -0,0 +1,348 @@
+//! Caches fetched tiles as files in a directory, for example to build an offline cache on a
+//! device.
+
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use async_trait::async_trait;
+use flate2::{write::GzEncoder, Compression};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    io::{
+        gzip,
+        source_client::{HttpClient, SourceFetchError},
+        tile_expiry::StoredTile,
+    },
+    util::hash::fnv1a,
+};
+
+/// How tiles are stored on disk by a [`DiskCacheSource`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheCompression {
+    /// The fetched bytes are stored unchanged.
+    None,
+    /// The fetched bytes are gzip-compressed into a file with the extension `tile.gz`. Vector
+    /// tiles usually shrink to about half of their size. Tiles which are already
+    /// gzip-compressed are stored unchanged.
+    Gzip,
+}
+
+impl Default for CacheCompression {
+    fn default() -> Self {
+        Self::None
+    }
+}
+
+/// The expiry and `ETag` of a cached tile, which are stored next to it. Times are seconds since
+/// the unix epoch.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CacheMetadata {
+    expires_at: Option<u64>,
+    etag: Option<String>,
+}
+
+/// Stores every tile which is fetched by the inner client in a directory and serves subsequent
+/// requests for the same URL from there. Cached tiles are kept until they expire, see
+/// [`HttpClient::fetch_stored`], or until they are deleted from the directory.
+///
+/// Files which were compressed by the cache are decompressed when they are read, regardless of
+/// the configured [`CacheCompression`], so the compression can be changed for an existing cache.
+/// Tiles which were already gzip-compressed when they were fetched are returned unchanged.
+#[derive(Clone)]
+pub struct DiskCacheSource<HC>
+where
+    HC: HttpClient,
+{
+    inner: HC,
+    directory: Arc<PathBuf>,
+    compression: CacheCompression,
+}
+
+impl<HC> DiskCacheSource<HC>
+where
+    HC: HttpClient,
+{
+    /// The `directory` is created when the first tile is stored.
+    pub fn new(inner: HC, directory: impl Into<PathBuf>) -> Self {
+        Self {
+            inner,
+            directory: Arc::new(directory.into()),
+            compression: CacheCompression::default(),
+        }
+    }
+
+    pub fn with_compression(mut self, compression: CacheCompression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    /// Returns the path of the file in which the tile at `url` is cached without compression.
+    /// The file name is a stable hash of the URL, so that the cache can be reused by later
+    /// releases. Tiles which are compressed by the cache are stored with the extension
+    /// `tile.gz` and their expiry with the extension `meta` instead.
+    pub fn path(&self, url: &str) -> PathBuf {
+        self.directory
+            .join(format!("{:016x}.tile", fnv1a(url.as_bytes())))
+    }
+
+    fn compressed_path(&self, url: &str) -> PathBuf {
+        self.path(url).with_extension("tile.gz")
+    }
+
+    fn metadata_path(&self, url: &str) -> PathBuf {
+        self.path(url).with_extension("meta")
+    }
+
+    /// Reads the cached tile of `url`, even if it is expired. Returns `None` if it is not cached.
+    pub fn read(&self, url: &str) -> io::Result<Option<Vec<u8>>> {
+        match read_file(&self.compressed_path(url))? {
+            Some(data) => gzip::decompress(&data).map(Some),
+            None => read_file(&self.path(url)),
+        }
+    }
+
+    /// Same as [`DiskCacheSource::read`], but also returns the expiry and `ETag` of the tile.
+    pub fn read_stored(&self, url: &str) -> io::Result<Option<StoredTile>> {
+        let Some(data) = self.read(url)? else { return Ok(None); };
+
+        let metadata = match read_file(&self.metadata_path(url))? {
+            Some(metadata) => serde_json::from_slice::<CacheMetadata>(&metadata)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?,
+            None => CacheMetadata::default(),
+        };
+
+        let expires_at = metadata
+            .expires_at
+            .map(|secs| UNIX_EPOCH + Duration::from_secs(secs));
+        let tile = StoredTile::new(data.into_boxed_slice(), expires_at);
+        Ok(Some(match metadata.etag {
+            Some(etag) => tile.with_etag(etag),
+            None => tile,
+        }))
+    }
+
+    /// Stores `data` as cached tile of `url`, which never expires.
+    pub fn write(&self, url: &str, data: &[u8]) -> io::Result<()> {
+        self.write_stored(url, &StoredTile::new(data.into(), None))
+    }
+
+    /// Stores the `tile` as cached tile of `url` together with its expiry and `ETag`. The files
+    /// are written completely before they are moved into place, so that concurrent readers never
+    /// see a partial tile.
+    pub fn write_stored(&self, url: &str, tile: &StoredTile) -> io::Result<()> {
+        fs::create_dir_all(self.directory.as_path())?;
+
+        let metadata = CacheMetadata {
+            expires_at: tile
+                .expires_at
+                .and_then(|expires_at| expires_at.duration_since(UNIX_EPOCH).ok())
+                .map(|duration| duration.as_secs()),
+            etag: tile.etag.clone(),
+        };
+        let metadata_path = self.metadata_path(url);
+        if metadata.expires_at.is_some() || metadata.etag.is_some() {
+            let json = serde_json::to_vec(&metadata).expect("metadata is serializable");
+            write_file(&metadata_path, |file| file.write_all(&json))?;
+        } else {
+            remove_file(&metadata_path)?;
+        }
+
+        if self.compression == CacheCompression::Gzip && !gzip::is_gzip(&tile.data) {
+            write_file(&self.compressed_path(url), |file| {
+                let mut encoder = GzEncoder::new(file, Compression::default());
+                encoder.write_all(&tile.data)?;
+                encoder.finish().map(|_| ())
+            })?;
+            remove_file(&self.path(url))
+        } else {
+            write_file(&self.path(url), |file| file.write_all(&tile.data))?;
+            remove_file(&self.compressed_path(url))
+        }
+    }
+}
+
+/// Reads the file at `path`. Returns `None` if it does not exist.
+fn read_file(path: &Path) -> io::Result<Option<Vec<u8>>> {
+    match fs::read(path) {
+        Ok(data) => Ok(Some(data)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Writes the file at `path` with `write` into a partial file which is then moved into place.
+fn write_file(path: &Path, write: impl FnOnce(fs::File) -> io::Result<()>) -> io::Result<()> {
+    let mut partial = path.as_os_str().to_owned();
+    partial.push(".partial");
+    write(fs::File::create(&partial)?)?;
+    fs::rename(partial, path)
+}
+
+/// Removes the file at `path` if it exists.
+fn remove_file(path: &Path) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+#[cfg_attr(not(feature = "thread-safe-futures"), async_trait(?Send))]
+#[cfg_attr(feature = "thread-safe-futures", async_trait)]
+impl<HC> HttpClient for DiskCacheSource<HC>
+where
+    HC: HttpClient,
+{
+    async fn fetch(&self, url: &str) -> Result<Vec<u8>, SourceFetchError> {
+        Ok(self.fetch_stored(url).await?.data.into_vec())
+    }
+
+    /// Serves the cached tile of `url` unless it is expired. Expired tiles are fetched again
+    /// and replaced.
+    async fn fetch_stored(&self, url: &str) -> Result<StoredTile, SourceFetchError> {
+        match self.read_stored(url) {
+            Ok(Some(tile)) if !tile.is_expired(SystemTime::now()) => return Ok(tile),
+            Ok(_) => {}
+            Err(e) => log::warn!("reading {url} from the disk cache failed: {e}"),
+        }
+
+        let tile = self.inner.fetch_stored(url).await?;
+        if let Err(e) = self.write_stored(url, &tile) {
+            log::warn!("writing {url} to the disk cache failed: {e}");
+        }
+        Ok(tile)
+    }
+
+    /// Tiles are written before they are returned, so this only flushes the inner client and
+    /// syncs the cache directory, which persists the names of the files moved into place.
+    async fn flush(&self) -> io::Result<()> {
+        self.inner.flush().await?;
+        sync_directory(&self.directory)
+    }
+}
+
+/// Syncs the entries of the `directory`, which were created or renamed, to disk. Directories can
+/// only be synced on unix, on other platforms this does nothing.
+fn sync_directory(directory: &Path) -> io::Result<()> {
+    if cfg!(unix) && directory.exists() {
+        fs::File::open(directory)?.sync_all()?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        io::Write,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::{Duration, SystemTime},
+    };
+
+    use flate2::{write::GzEncoder, Compression};
+
+    use super::{CacheCompression, DiskCacheSource};
+    use crate::io::{fn_source::FnSource, source_client::HttpClient, tile_expiry::StoredTile};
+
+    #[tokio::test]
+    async fn test_gzip_round_trip() {
+        let directory =
+            std::env::temp_dir().join(format!("maplibre-disk-cache-{}", std::process::id()));
+        // Compresses well, like the repeated keys and values of vector tiles
+        let tile = b"water water water water water water water water".repeat(64);
+
+        let expected = tile.clone();
+        let source = FnSource::new(move |_| {
+            let tile = expected.clone();
+            async move { Ok(tile) }
+        });
+        let cache =
+            DiskCacheSource::new(source, &directory).with_compression(CacheCompression::Gzip);
+
+        let url = "https://example.com/1/0/0.pbf";
+        assert_eq!(cache.read(url).unwrap(), None);
+        assert_eq!(cache.fetch(url).await.unwrap(), tile);
+
+        let compressed_path = cache.path(url).with_extension("tile.gz");
+        let stored = fs::read(&compressed_path).unwrap();
+        assert!(stored.len() < tile.len() / 2);
+        assert!(!cache.path(url).exists());
+        assert_eq!(cache.read(url).unwrap(), Some(tile.clone()));
+
+        // Uncompressed caches read compressed files as well
+        let uncompressed = cache.clone().with_compression(CacheCompression::None);
+        assert_eq!(uncompressed.read(url).unwrap(), Some(tile.clone()));
+        uncompressed.write(url, &tile).unwrap();
+        assert_eq!(fs::read(cache.path(url)).unwrap(), tile);
+        assert!(!compressed_path.exists());
+
+        // Tiles which were fetched compressed are returned unchanged
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&tile).unwrap();
+        let gzipped = encoder.finish().unwrap();
+        cache.write(url, &gzipped).unwrap();
+        assert_eq!(cache.read(url).unwrap(), Some(gzipped));
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_expired_tiles_are_fetched_again() {
+        let directory =
+            std::env::temp_dir().join(format!("maplibre-disk-cache-expiry-{}", std::process::id()));
+        let fetches = Arc::new(AtomicUsize::new(0));
+        let counter = fetches.clone();
+        let source = FnSource::new(move |_| {
+            counter.fetch_add(1, Ordering::Relaxed);
+            async move { Ok(b"fresh".to_vec()) }
+        });
+        let cache = DiskCacheSource::new(source, &directory);
+
+        let url = "https://example.com/1/0/0.pbf";
+        let now = SystemTime::now();
+        let valid = StoredTile::new(
+            b"cached".to_vec().into_boxed_slice(),
+            Some(now + Duration::from_secs(60)),
+        )
+        .with_etag("\"v1\"");
+        cache.write_stored(url, &valid).unwrap();
+
+        let tile = cache.fetch_stored(url).await.unwrap();
+        assert_eq!(tile.data, valid.data);
+        assert_eq!(tile.etag, valid.etag);
+        // The expiry is stored with a precision of seconds
+        assert!(tile.expires_at.unwrap() > now);
+        assert_eq!(fetches.load(Ordering::Relaxed), 0);
+
+        let expired = StoredTile::new(
+            b"cached".to_vec().into_boxed_slice(),
+            Some(now - Duration::from_secs(60)),
+        );
+        cache.write_stored(url, &expired).unwrap();
+        assert_eq!(&*cache.fetch_stored(url).await.unwrap().data, b"fresh");
+        assert_eq!(fetches.load(Ordering::Relaxed), 1);
+
+        // The fetched tile replaced the expired tile and never expires
+        assert_eq!(
+            cache.read_stored(url).unwrap(),
+            Some(StoredTile::new(b"fresh".to_vec().into_boxed_slice(), None))
+        );
+        assert_eq!(&*cache.fetch(url).await.unwrap(), b"fresh");
+        assert_eq!(fetches.load(Ordering::Relaxed), 1);
+        cache.flush().await.unwrap();
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
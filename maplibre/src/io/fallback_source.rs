@@ -0,0 +1,184 @@
+//! A source which tries multiple sources in order, for example a local archive for the base
+//! region and a remote server for everything else.
+
+use std::{io, sync::Arc};
+
+use async_trait::async_trait;
+
+use crate::io::{
+    source_client::{HttpClient, SourceFetchError},
+    tile_expiry::StoredTile,
+};
+
+/// One of the sources of a [`FallbackSource`]. Every [`HttpClient`] is a tier.
+#[cfg_attr(not(feature = "thread-safe-futures"), async_trait(?Send))]
+#[cfg_attr(feature = "thread-safe-futures", async_trait)]
+pub trait FallbackTier: Send + Sync {
+    async fn fetch_stored(&self, url: &str) -> Result<StoredTile, SourceFetchError>;
+
+    async fn flush(&self) -> io::Result<()>;
+}
+
+#[cfg_attr(not(feature = "thread-safe-futures"), async_trait(?Send))]
+#[cfg_attr(feature = "thread-safe-futures", async_trait)]
+impl<HC> FallbackTier for HC
+where
+    HC: HttpClient,
+{
+    async fn fetch_stored(&self, url: &str) -> Result<StoredTile, SourceFetchError> {
+        HttpClient::fetch_stored(self, url).await
+    }
+
+    async fn flush(&self) -> io::Result<()> {
+        HttpClient::flush(self).await
+    }
+}
+
+/// Decides whether a [`FallbackSource`] tries the next source after an error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FallbackPolicy {
+    /// Only tiles which are not found are fetched from the next source. Other errors, for
+    /// example a corrupt archive, are returned immediately.
+    NotFound,
+    /// The next source is tried after any error.
+    AnyError,
+}
+
+impl Default for FallbackPolicy {
+    fn default() -> Self {
+        Self::NotFound
+    }
+}
+
+/// Tries an ordered list of sources and returns the first tile which is fetched successfully.
+///
+/// If no source returns the tile, the error of the last source is returned.
+///
+/// # Example
+///
+/// ```
+/// use maplibre::io::{
+///     fallback_source::{FallbackSource, FallbackTier},
+///     fn_source::FnSource,
+///     source_client::SourceFetchError,
+/// };
+///
+/// let local = FnSource::new(|coords| async move {
+///     Err(SourceFetchError::not_found(&coords.to_string()))
+/// });
+/// let remote = FnSource::new(|_| async move { Ok(Vec::new()) });
+///
+/// let tiers: Vec<Box<dyn FallbackTier>> = vec![Box::new(local), Box::new(remote)];
+/// let source = FallbackSource::new(tiers);
+/// ```
+#[derive(Clone)]
+pub struct FallbackSource {
+    sources: Arc<[Box<dyn FallbackTier>]>,
+    policy: FallbackPolicy,
+}
+
+impl FallbackSource {
+    pub fn new(sources: Vec<Box<dyn FallbackTier>>) -> Self {
+        Self {
+            sources: sources.into(),
+            policy: FallbackPolicy::default(),
+        }
+    }
+
+    pub fn with_policy(mut self, policy: FallbackPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+}
+
+#[cfg_attr(not(feature = "thread-safe-futures"), async_trait(?Send))]
+#[cfg_attr(feature = "thread-safe-futures", async_trait)]
+impl HttpClient for FallbackSource {
+    async fn fetch(&self, url: &str) -> Result<Vec<u8>, SourceFetchError> {
+        let tile = HttpClient::fetch_stored(self, url).await?;
+        Ok(tile.data.into_vec())
+    }
+
+    async fn fetch_stored(&self, url: &str) -> Result<StoredTile, SourceFetchError> {
+        let Some((last, sources)) = self
+            .sources
+            .split_last() else { return Err(SourceFetchError::not_found(url)); };
+
+        for source in sources {
+            // The error is not kept across the next await, because it is not Send
+            match source.fetch_stored(url).await {
+                Ok(tile) => return Ok(tile),
+                Err(e) if e.is_not_found() || self.policy == FallbackPolicy::AnyError => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        last.fetch_stored(url).await
+    }
+
+    /// Flushes all sources, even if one of them fails. The first error is returned.
+    async fn flush(&self) -> io::Result<()> {
+        let mut result = Ok(());
+        for source in self.sources.iter() {
+            let flushed = source.flush().await;
+            if result.is_ok() {
+                result = flushed;
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FallbackPolicy, FallbackSource, FallbackTier};
+    use crate::{
+        coords::{WorldTileCoords, ZoomLevel},
+        io::{
+            fn_source::FnSource,
+            source_client::{HttpSourceClient, SourceFetchError},
+            source_type::{SourceType, TessellateSource},
+        },
+    };
+
+    #[tokio::test]
+    async fn test_fallback() {
+        // Contains the tiles up to zoom 1 and fails for zoom 3
+        let local = FnSource::new(|coords: WorldTileCoords| async move {
+            match u8::from(coords.z) {
+                0 | 1 => Ok(b"local".to_vec()),
+                3 => Err(SourceFetchError(Box::new(std::io::Error::new(
+                    std::io::ErrorKind::Other,
+                    "corrupt archive",
+                )))),
+                _ => Err(SourceFetchError::not_found(&coords.to_string())),
+            }
+        });
+        let remote = FnSource::new(|_| async move { Ok(b"remote".to_vec()) });
+        let empty = FnSource::new(|coords: WorldTileCoords| async move {
+            Err(SourceFetchError::not_found(&coords.to_string()))
+        });
+
+        let fetch = |source: FallbackSource, z: u8| async move {
+            HttpSourceClient::new(source)
+                .fetch(
+                    &WorldTileCoords::from((0, 0, ZoomLevel::new(z))),
+                    &SourceType::Tessellate(TessellateSource::default()),
+                )
+                .await
+        };
+
+        let tiers: Vec<Box<dyn FallbackTier>> = vec![Box::new(local), Box::new(remote)];
+        let source = FallbackSource::new(tiers);
+        assert_eq!(fetch(source.clone(), 1).await.unwrap(), b"local");
+        assert_eq!(fetch(source.clone(), 2).await.unwrap(), b"remote");
+        // Other errors are returned immediately by default
+        assert!(!fetch(source.clone(), 3).await.unwrap_err().is_not_found());
+        let source = source.with_policy(FallbackPolicy::AnyError);
+        assert_eq!(fetch(source, 3).await.unwrap(), b"remote");
+
+        let tiers: Vec<Box<dyn FallbackTier>> = vec![Box::new(empty)];
+        let source = FallbackSource::new(tiers);
+        assert!(fetch(source, 0).await.unwrap_err().is_not_found());
+    }
+}
@@ -0,0 +1,135 @@
+//! A source which produces tiles by calling a closure. This is useful for tests and for
+//! applications which synthesize tiles.
+
+use std::{future::Future, sync::Arc};
+
+use async_trait::async_trait;
+use thiserror::Error;
+
+use crate::{
+    coords::{TileCoords, WorldTileCoords, ZoomLevel, MAX_ZOOM},
+    io::source_client::{HttpClient, SourceFetchError},
+    style::source::TileAddressingScheme,
+};
+
+#[derive(Error, Debug)]
+pub enum FnSourceError {
+    #[error("unable to read tile coordinates from url `{0}`")]
+    InvalidUrl(String),
+}
+
+/// Calls a closure for each requested tile.
+///
+/// [`FnSource`] implements [`HttpClient`] so that it can be used within the
+/// [`Kernel`](crate::kernel::Kernel). The URLs which are built by
+/// [`SourceType`](crate::io::source_type::SourceType) end with `/{z}/{x}/{y}.{filetype}`.
+/// The coordinates are read from this suffix and passed to the closure.
+///
+/// # Example
+///
+/// ```
+/// use maplibre::io::fn_source::FnSource;
+///
+/// let source = FnSource::new(|coords| async move { Ok(format!("{coords}").into_bytes()) });
+/// ```
+pub struct FnSource<F> {
+    f: Arc<F>,
+}
+
+impl<F> Clone for FnSource<F> {
+    fn clone(&self) -> Self {
+        Self { f: self.f.clone() }
+    }
+}
+
+impl<F, Fut> FnSource<F>
+where
+    F: Fn(WorldTileCoords) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<Vec<u8>, SourceFetchError>> + Send + 'static,
+{
+    pub fn new(f: F) -> Self {
+        Self { f: Arc::new(f) }
+    }
+
+    /// Calls the closure for `coords`.
+    pub async fn fetch_tile(&self, coords: WorldTileCoords) -> Result<Vec<u8>, SourceFetchError> {
+        (self.f)(coords).await
+    }
+}
+
+/// Reads the coordinates from a URL of the form `.../{z}/{x}/{y}.{filetype}`. The query of the
+/// URL is ignored.
+pub(crate) fn parse_tile_url(url: &str) -> Option<WorldTileCoords> {
+    let path = url.split(['?', '#']).next()?;
+    let mut segments = path.rsplit('/');
+
+    let y = segments.next()?.split('.').next()?.parse::<u32>().ok()?;
+    let x = segments.next()?.parse::<u32>().ok()?;
+    let z = segments.next()?.parse::<u8>().ok()?;
+
+    if z as usize >= MAX_ZOOM {
+        return None;
+    }
+
+    TileCoords::from((x, y, ZoomLevel::from(z))).into_world_tile(TileAddressingScheme::XYZ)
+}
+
+#[cfg_attr(not(feature = "thread-safe-futures"), async_trait(?Send))]
+#[cfg_attr(feature = "thread-safe-futures", async_trait)]
+impl<F, Fut> HttpClient for FnSource<F>
+where
+    F: Fn(WorldTileCoords) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<Vec<u8>, SourceFetchError>> + Send + 'static,
+{
+    async fn fetch(&self, url: &str) -> Result<Vec<u8>, SourceFetchError> {
+        let coords = parse_tile_url(url).ok_or_else(|| {
+            SourceFetchError(Box::new(FnSourceError::InvalidUrl(url.to_string())))
+        })?;
+        self.fetch_tile(coords).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_tile_url, FnSource};
+    use crate::{
+        coords::{WorldTileCoords, ZoomLevel},
+        io::{
+            source_client::{HttpClient, HttpSourceClient},
+            source_type::{RasterSource, SourceType, TessellateSource},
+        },
+    };
+
+    #[test]
+    fn test_parse_tile_url() {
+        let coords = WorldTileCoords::from((3, 5, ZoomLevel::new(4)));
+
+        let vector = SourceType::Tessellate(TessellateSource::default());
+        assert_eq!(parse_tile_url(&vector.format(&coords)), Some(coords));
+        let raster = SourceType::Raster(RasterSource::default());
+        assert_eq!(parse_tile_url(&raster.format(&coords)), Some(coords));
+
+        assert_eq!(parse_tile_url("https://example.com/tiles.json"), None);
+        assert_eq!(parse_tile_url("https://example.com/1/5/5.pbf"), None);
+    }
+
+    #[tokio::test]
+    async fn test_fetch() {
+        let source = FnSource::new(|coords: WorldTileCoords| async move {
+            Ok(vec![u8::from(coords.z), coords.x as u8, coords.y as u8])
+        });
+
+        let client = HttpSourceClient::new(source.clone());
+        let coords = WorldTileCoords::from((1, 2, ZoomLevel::new(3)));
+        let data = client
+            .fetch(
+                &coords,
+                &SourceType::Tessellate(TessellateSource::default()),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(data, vec![3, 1, 2]);
+        assert!(source.fetch("not a tile").await.is_err());
+    }
+}
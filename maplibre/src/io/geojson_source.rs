@@ -0,0 +1,847 @@
+//! A source which cuts a GeoJSON document into vector tiles on the fly, similar to
+//! [geojson-vt](https://github.com/mapbox/geojson-vt).
+//!
+//! The features are projected to Web Mercator once, when the source is created. For each
+//! requested tile, the features which intersect the tile are clipped to it and encoded as a
+//! Mapbox vector tile with a single layer. Therefore, the tiles can be processed by
+//! [`process_vector_tile`](crate::vector::process_vector::process_vector_tile) like any other
+//! vector tile.
+
+use std::{collections::HashMap, f64::consts::PI};
+
+use geozero::mvt::{tile, Message, Tile};
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::{
+    coords::{WorldTileCoords, EXTENT, EXTENT_UINT},
+    io::source_client::SourceFetchError,
+};
+
+/// The highest latitude which can be represented in Web Mercator.
+const MAX_LATITUDE: f64 = 85.051129;
+
+/// Features are clipped to the tile extended by this many units on each side, so that lines
+/// and polygon outlines continue seamlessly across tile borders.
+const BUFFER: f64 = 64.0;
+
+#[derive(Error, Debug)]
+pub enum GeoJsonError {
+    #[error("the GeoJSON document is invalid")]
+    Parse(#[from] serde_json::Error),
+    #[error("unsupported GeoJSON: {0}")]
+    Unsupported(String),
+}
+
+/// A point in Web Mercator, normalized to `0..1`. The origin is in the upper-left corner.
+type Point = [f64; 2];
+
+#[derive(Debug, Clone)]
+enum Geometry {
+    Points(Vec<Point>),
+    Lines(Vec<Vec<Point>>),
+    /// Polygons, each consisting of an exterior ring followed by its holes
+    Polygons(Vec<Vec<Vec<Point>>>),
+}
+
+#[derive(Debug, Clone)]
+struct Feature {
+    id: Option<u64>,
+    properties: Vec<(String, tile::Value)>,
+    geometry: Geometry,
+    /// The bounding box as `[min_x, min_y, max_x, max_y]`
+    bounds: [f64; 4],
+}
+
+/// Serves the features of a GeoJSON document as vector tiles with a single layer.
+///
+/// The tiles can be fetched through a [`FnSource`](crate::io::fn_source::FnSource):
+///
+/// ```
+/// use std::sync::Arc;
+///
+/// use maplibre::io::{fn_source::FnSource, geojson_source::GeoJsonSource};
+///
+/// let geojson = Arc::new(
+///     GeoJsonSource::from_str(
+///         r#"{"type": "LineString", "coordinates": [[11.5, 48.1], [11.6, 48.2]]}"#,
+///         "route",
+///     )
+///     .unwrap(),
+/// );
+/// let source = FnSource::new(move |coords| {
+///     let tile = geojson.tile(&coords);
+///     async move { Ok(tile) }
+/// });
+/// ```
+#[derive(Debug, Clone)]
+pub struct GeoJsonSource {
+    layer_name: String,
+    features: Vec<Feature>,
+    simplify_tolerance: f64,
+}
+
+impl GeoJsonSource {
+    /// Reads a `FeatureCollection`, a `Feature` or a bare geometry. The features are served
+    /// within the source layer `layer_name`.
+    pub fn from_str(json: &str, layer_name: &str) -> Result<Self, GeoJsonError> {
+        Self::from_value(&serde_json::from_str(json)?, layer_name)
+    }
+
+    pub fn from_slice(json: &[u8], layer_name: &str) -> Result<Self, GeoJsonError> {
+        Self::from_value(&serde_json::from_slice(json)?, layer_name)
+    }
+
+    pub fn from_value(json: &Value, layer_name: &str) -> Result<Self, GeoJsonError> {
+        let mut features = Vec::new();
+        read_object(json, &mut features)?;
+        Ok(Self {
+            layer_name: layer_name.to_string(),
+            features,
+            simplify_tolerance: 0.0,
+        })
+    }
+
+    /// Simplifies lines and polygon rings with the Douglas-Peucker algorithm and the given
+    /// `tolerance` in tile units, i.e. a tile is [`EXTENT`] units wide. Points which are closer
+    /// than `tolerance` to the simplified line are dropped, so densely sampled curves are encoded
+    /// with as few segments as are visible at the zoom of the tile. Points are never added, so
+    /// this does not refine coarsely sampled curves. By default the tolerance is `0` and every
+    /// point is kept.
+    ///
+    /// Rings which would have less than three distinct points after the simplification are kept
+    /// as they are, so that small polygons do not vanish.
+    pub fn with_simplify_tolerance(mut self, tolerance: f64) -> Self {
+        self.simplify_tolerance = tolerance;
+        self
+    }
+
+    pub fn layer_name(&self) -> &str {
+        &self.layer_name
+    }
+
+    pub fn simplify_tolerance(&self) -> f64 {
+        self.simplify_tolerance
+    }
+
+    /// Encodes the features which intersect the tile at `coords` as vector tile. If no feature
+    /// intersects the tile, the tile does not contain any layer.
+    pub fn tile(&self, coords: &WorldTileCoords) -> Vec<u8> {
+        let scale = (1u64 << u8::from(coords.z)) as f64;
+        let to_tile = |point: &Point| -> Point {
+            [
+                (point[0] * scale - coords.x as f64) * EXTENT,
+                (point[1] * scale - coords.y as f64) * EXTENT,
+            ]
+        };
+
+        let buffer = BUFFER / EXTENT / scale;
+        let tile_bounds = [
+            coords.x as f64 / scale - buffer,
+            coords.y as f64 / scale - buffer,
+            (coords.x as f64 + 1.0) / scale + buffer,
+            (coords.y as f64 + 1.0) / scale + buffer,
+        ];
+
+        let mut layer = LayerEncoder::default();
+        for feature in &self.features {
+            if feature.bounds[0] > tile_bounds[2]
+                || feature.bounds[2] < tile_bounds[0]
+                || feature.bounds[1] > tile_bounds[3]
+                || feature.bounds[3] < tile_bounds[1]
+            {
+                continue;
+            }
+
+            let (geom_type, geometry) = match &feature.geometry {
+                Geometry::Points(points) => {
+                    let points = points
+                        .iter()
+                        .map(to_tile)
+                        .filter(|point| in_clip_rect(point))
+                        .collect::<Vec<_>>();
+                    (tile::GeomType::Point, encode_points(&points))
+                }
+                Geometry::Lines(lines) => {
+                    let lines = lines
+                        .iter()
+                        .flat_map(|line| clip_line(&self.simplify_line(line.iter().map(to_tile))))
+                        .collect::<Vec<_>>();
+                    (tile::GeomType::Linestring, encode_lines(&lines))
+                }
+                Geometry::Polygons(polygons) => {
+                    let polygons = polygons
+                        .iter()
+                        .map(|rings| {
+                            rings
+                                .iter()
+                                .map(|ring| {
+                                    clip_ring(&self.simplify_ring(ring.iter().map(to_tile)))
+                                })
+                                .collect::<Vec<_>>()
+                        })
+                        .collect::<Vec<_>>();
+                    (tile::GeomType::Polygon, encode_polygons(&polygons))
+                }
+            };
+
+            if geometry.is_empty() {
+                continue;
+            }
+
+            layer.add_feature(feature, geom_type, geometry);
+        }
+
+        let layers = if layer.features.is_empty() {
+            Vec::new()
+        } else {
+            vec![layer.finish(&self.layer_name)]
+        };
+
+        Tile { layers }.encode_to_vec()
+    }
+
+    fn simplify_line(&self, points: impl Iterator<Item = Point>) -> Vec<Point> {
+        let points = points.collect::<Vec<_>>();
+        if self.simplify_tolerance > 0.0 {
+            simplify(&points, self.simplify_tolerance)
+        } else {
+            points
+        }
+    }
+
+    /// Same as [`GeoJsonSource::simplify_line`], but keeps the `ring` as it is if it would be
+    /// degenerate after the simplification.
+    fn simplify_ring(&self, ring: impl Iterator<Item = Point>) -> Vec<Point> {
+        let ring = ring.collect::<Vec<_>>();
+        let simplified = self.simplify_line(ring.iter().copied());
+
+        let is_closed = simplified.len() > 1 && simplified.first() == simplified.last();
+        if simplified.len() - usize::from(is_closed) < 3 {
+            ring
+        } else {
+            simplified
+        }
+    }
+
+    /// Same as [`GeoJsonSource::tile`], but with an interface which is compatible with the
+    /// other sources.
+    pub async fn fetch(&self, coords: &WorldTileCoords) -> Result<Vec<u8>, SourceFetchError> {
+        Ok(self.tile(coords))
+    }
+}
+
+fn read_object(json: &Value, features: &mut Vec<Feature>) -> Result<(), GeoJsonError> {
+    match json.get("type").and_then(Value::as_str) {
+        Some("FeatureCollection") => {
+            let collection = json
+                .get("features")
+                .and_then(Value::as_array)
+                .ok_or_else(|| {
+                    GeoJsonError::Unsupported("FeatureCollection without features".to_string())
+                })?;
+            for feature in collection {
+                read_object(feature, features)?;
+            }
+        }
+        Some("Feature") => {
+            let id = json.get("id").and_then(Value::as_u64);
+            let properties: Vec<(String, tile::Value)> = json
+                .get("properties")
+                .and_then(Value::as_object)
+                .map(|properties| {
+                    properties
+                        .iter()
+                        .filter_map(|(key, value)| Some((key.clone(), read_property(value)?)))
+                        .collect()
+                })
+                .unwrap_or_default();
+
+            match json.get("geometry") {
+                Some(Value::Null) | None => {}
+                Some(geometry) => read_geometry(geometry, id, &properties, features)?,
+            }
+        }
+        _ => read_geometry(json, None, &[], features)?,
+    }
+
+    Ok(())
+}
+
+fn read_property(value: &Value) -> Option<tile::Value> {
+    match value {
+        Value::String(string) => Some(tile::Value {
+            string_value: Some(string.clone()),
+            ..Default::default()
+        }),
+        Value::Bool(value) => Some(tile::Value {
+            bool_value: Some(*value),
+            ..Default::default()
+        }),
+        Value::Number(number) => Some(if let Some(int) = number.as_i64() {
+            tile::Value {
+                sint_value: Some(int),
+                ..Default::default()
+            }
+        } else {
+            tile::Value {
+                double_value: number.as_f64(),
+                ..Default::default()
+            }
+        }),
+        // Vector tiles can not store nested values
+        Value::Null | Value::Array(_) | Value::Object(_) => None,
+    }
+}
+
+fn read_geometry(
+    json: &Value,
+    id: Option<u64>,
+    properties: &[(String, tile::Value)],
+    features: &mut Vec<Feature>,
+) -> Result<(), GeoJsonError> {
+    let kind = json.get("type").and_then(Value::as_str).unwrap_or_default();
+
+    if kind == "GeometryCollection" {
+        let geometries = json
+            .get("geometries")
+            .and_then(Value::as_array)
+            .ok_or_else(|| {
+                GeoJsonError::Unsupported("GeometryCollection without geometries".to_string())
+            })?;
+        for geometry in geometries {
+            read_geometry(geometry, id, properties, features)?;
+        }
+        return Ok(());
+    }
+
+    let coordinates = json.get("coordinates").ok_or_else(|| {
+        GeoJsonError::Unsupported(format!("geometry `{kind}` without coordinates"))
+    })?;
+
+    let geometry = match kind {
+        "Point" => Geometry::Points(vec![read_point(coordinates)?]),
+        "MultiPoint" => Geometry::Points(read_points(coordinates)?),
+        "LineString" => Geometry::Lines(vec![read_points(coordinates)?]),
+        "MultiLineString" => Geometry::Lines(read_array(coordinates, read_points)?),
+        "Polygon" => Geometry::Polygons(vec![read_array(coordinates, read_points)?]),
+        "MultiPolygon" => Geometry::Polygons(read_array(coordinates, |polygon| {
+            read_array(polygon, read_points)
+        })?),
+        _ => return Err(GeoJsonError::Unsupported(format!("geometry type `{kind}`"))),
+    };
+
+    let mut bounds = [
+        f64::INFINITY,
+        f64::INFINITY,
+        f64::NEG_INFINITY,
+        f64::NEG_INFINITY,
+    ];
+    let mut extend = |point: &Point| {
+        bounds[0] = bounds[0].min(point[0]);
+        bounds[1] = bounds[1].min(point[1]);
+        bounds[2] = bounds[2].max(point[0]);
+        bounds[3] = bounds[3].max(point[1]);
+    };
+    match &geometry {
+        Geometry::Points(points) => points.iter().for_each(&mut extend),
+        Geometry::Lines(lines) => lines.iter().flatten().for_each(&mut extend),
+        Geometry::Polygons(polygons) => polygons.iter().flatten().flatten().for_each(&mut extend),
+    }
+
+    features.push(Feature {
+        id,
+        properties: properties.to_vec(),
+        geometry,
+        bounds,
+    });
+
+    Ok(())
+}
+
+fn read_array<T>(
+    json: &Value,
+    read: impl Fn(&Value) -> Result<T, GeoJsonError>,
+) -> Result<Vec<T>, GeoJsonError> {
+    json.as_array()
+        .ok_or_else(|| GeoJsonError::Unsupported(format!("expected an array, found `{json}`")))?
+        .iter()
+        .map(read)
+        .collect()
+}
+
+fn read_points(json: &Value) -> Result<Vec<Point>, GeoJsonError> {
+    read_array(json, read_point)
+}
+
+/// Projects a `[longitude, latitude]` position to Web Mercator.
+fn read_point(json: &Value) -> Result<Point, GeoJsonError> {
+    let position = json.as_array().map(|position| {
+        (
+            position.get(0).and_then(Value::as_f64),
+            position.get(1).and_then(Value::as_f64),
+        )
+    });
+    let Some((Some(longitude), Some(latitude))) = position else {
+        return Err(GeoJsonError::Unsupported(format!(
+            "invalid position `{json}`"
+        )));
+    };
+
+    let latitude = latitude.clamp(-MAX_LATITUDE, MAX_LATITUDE).to_radians();
+    Ok([
+        longitude / 360.0 + 0.5,
+        0.5 - (latitude.tan() + 1.0 / latitude.cos()).ln() / (2.0 * PI),
+    ])
+}
+
+fn in_clip_rect(point: &Point) -> bool {
+    (-BUFFER..=EXTENT + BUFFER).contains(&point[0])
+        && (-BUFFER..=EXTENT + BUFFER).contains(&point[1])
+}
+
+/// Clips a line to the buffered tile. The line is split where it leaves the tile.
+fn clip_line(line: &[Point]) -> Vec<Vec<Point>> {
+    let (min, max) = (-BUFFER, EXTENT + BUFFER);
+    let mut parts = Vec::new();
+    let mut current: Vec<Point> = Vec::new();
+
+    for segment in line.windows(2) {
+        let (a, b) = (segment[0], segment[1]);
+        let delta = [b[0] - a[0], b[1] - a[1]];
+
+        // Liang-Barsky
+        let mut t0: f64 = 0.0;
+        let mut t1: f64 = 1.0;
+        let mut visible = true;
+        for (p, q) in [
+            (-delta[0], a[0] - min),
+            (delta[0], max - a[0]),
+            (-delta[1], a[1] - min),
+            (delta[1], max - a[1]),
+        ] {
+            if p == 0.0 {
+                if q < 0.0 {
+                    visible = false;
+                }
+            } else if p < 0.0 {
+                t0 = t0.max(q / p);
+            } else {
+                t1 = t1.min(q / p);
+            }
+        }
+
+        if !visible || t0 > t1 {
+            if current.len() > 1 {
+                parts.push(std::mem::take(&mut current));
+            }
+            current.clear();
+            continue;
+        }
+
+        let start = [a[0] + t0 * delta[0], a[1] + t0 * delta[1]];
+        let end = [a[0] + t1 * delta[0], a[1] + t1 * delta[1]];
+
+        if current.last() != Some(&start) {
+            if current.len() > 1 {
+                parts.push(std::mem::take(&mut current));
+            }
+            current.clear();
+            current.push(start);
+        }
+        current.push(end);
+
+        if t1 < 1.0 {
+            parts.push(std::mem::take(&mut current));
+        }
+    }
+
+    if current.len() > 1 {
+        parts.push(current);
+    }
+
+    parts
+}
+
+/// Clips a ring to the buffered tile with the Sutherland-Hodgman algorithm.
+fn clip_ring(ring: &[Point]) -> Vec<Point> {
+    let (min, max) = (-BUFFER, EXTENT + BUFFER);
+    let edges: [(usize, f64, bool); 4] = [
+        (0, min, true),
+        (0, max, false),
+        (1, min, true),
+        (1, max, false),
+    ];
+
+    let mut output = ring.to_vec();
+    for (axis, bound, is_min) in edges {
+        let input = std::mem::take(&mut output);
+        let inside = |point: &Point| {
+            if is_min {
+                point[axis] >= bound
+            } else {
+                point[axis] <= bound
+            }
+        };
+        let intersect = |a: &Point, b: &Point| {
+            let t = (bound - a[axis]) / (b[axis] - a[axis]);
+            [a[0] + t * (b[0] - a[0]), a[1] + t * (b[1] - a[1])]
+        };
+
+        for (i, current) in input.iter().enumerate() {
+            let previous = &input[(i + input.len() - 1) % input.len()];
+            match (inside(previous), inside(current)) {
+                (true, true) => output.push(*current),
+                (true, false) => output.push(intersect(previous, current)),
+                (false, true) => {
+                    output.push(intersect(previous, current));
+                    output.push(*current);
+                }
+                (false, false) => {}
+            }
+        }
+    }
+
+    output
+}
+
+/// Removes the points which are closer than `tolerance` to the simplified line with the
+/// Douglas-Peucker algorithm. The first and the last point are always kept.
+fn simplify(points: &[Point], tolerance: f64) -> Vec<Point> {
+    if points.len() < 3 {
+        return points.to_vec();
+    }
+
+    let mut keep = vec![false; points.len()];
+    keep[0] = true;
+    keep[points.len() - 1] = true;
+
+    let mut ranges = vec![(0, points.len() - 1)];
+    while let Some((first, last)) = ranges.pop() {
+        let (a, b) = (points[first], points[last]);
+        let farthest = (first + 1..last)
+            .map(|i| (i, segment_distance(&points[i], &a, &b)))
+            .max_by(|(_, a), (_, b)| a.total_cmp(b));
+
+        if let Some((i, distance)) = farthest {
+            if distance > tolerance {
+                keep[i] = true;
+                ranges.push((first, i));
+                ranges.push((i, last));
+            }
+        }
+    }
+
+    points
+        .iter()
+        .zip(keep)
+        .filter_map(|(point, keep)| keep.then_some(*point))
+        .collect()
+}
+
+/// The distance between `point` and the segment from `a` to `b`.
+fn segment_distance(point: &Point, a: &Point, b: &Point) -> f64 {
+    let delta = [b[0] - a[0], b[1] - a[1]];
+    let length = delta[0] * delta[0] + delta[1] * delta[1];
+    let t = if length == 0.0 {
+        0.0
+    } else {
+        (((point[0] - a[0]) * delta[0] + (point[1] - a[1]) * delta[1]) / length).clamp(0.0, 1.0)
+    };
+    let closest = [a[0] + t * delta[0], a[1] + t * delta[1]];
+    ((point[0] - closest[0]).powi(2) + (point[1] - closest[1]).powi(2)).sqrt()
+}
+
+/// Rounds the points to integer tile coordinates and removes consecutive duplicates.
+fn round(points: &[Point]) -> Vec<[i32; 2]> {
+    let mut rounded: Vec<[i32; 2]> = Vec::with_capacity(points.len());
+    for point in points {
+        let point = [point[0].round() as i32, point[1].round() as i32];
+        if rounded.last() != Some(&point) {
+            rounded.push(point);
+        }
+    }
+    rounded
+}
+
+/// Builds the geometry commands of a vector tile feature.
+#[derive(Default)]
+struct CommandEncoder {
+    commands: Vec<u32>,
+    cursor: [i32; 2],
+}
+
+impl CommandEncoder {
+    const MOVE_TO: u32 = 1;
+    const LINE_TO: u32 = 2;
+    const CLOSE_PATH: u32 = 7;
+
+    fn command(&mut self, id: u32, count: usize) {
+        self.commands.push((id & 0x7) | ((count as u32) << 3));
+    }
+
+    fn point(&mut self, point: [i32; 2]) {
+        let zigzag = |value: i32| ((value << 1) ^ (value >> 31)) as u32;
+        self.commands.push(zigzag(point[0] - self.cursor[0]));
+        self.commands.push(zigzag(point[1] - self.cursor[1]));
+        self.cursor = point;
+    }
+
+    fn path(&mut self, points: &[[i32; 2]], close: bool) {
+        self.command(Self::MOVE_TO, 1);
+        self.point(points[0]);
+        self.command(Self::LINE_TO, points.len() - 1);
+        for point in &points[1..] {
+            self.point(*point);
+        }
+        if close {
+            self.command(Self::CLOSE_PATH, 1);
+        }
+    }
+}
+
+fn encode_points(points: &[Point]) -> Vec<u32> {
+    let points = points
+        .iter()
+        .map(|point| [point[0].round() as i32, point[1].round() as i32])
+        .collect::<Vec<_>>();
+    if points.is_empty() {
+        return Vec::new();
+    }
+
+    let mut encoder = CommandEncoder::default();
+    encoder.command(CommandEncoder::MOVE_TO, points.len());
+    for point in points {
+        encoder.point(point);
+    }
+    encoder.commands
+}
+
+fn encode_lines(lines: &[Vec<Point>]) -> Vec<u32> {
+    let mut encoder = CommandEncoder::default();
+    for line in lines {
+        let line = round(line);
+        if line.len() > 1 {
+            encoder.path(&line, false);
+        }
+    }
+    encoder.commands
+}
+
+/// The surveyor's formula. Rings which appear clockwise in tile coordinates have a positive
+/// area.
+fn signed_area(ring: &[[i32; 2]]) -> i64 {
+    (0..ring.len())
+        .map(|i| {
+            let (a, b) = (ring[i], ring[(i + 1) % ring.len()]);
+            a[0] as i64 * b[1] as i64 - b[0] as i64 * a[1] as i64
+        })
+        .sum()
+}
+
+/// Encodes polygons with clockwise exterior rings and counterclockwise holes, as required by
+/// the vector tile specification.
+fn encode_polygons(polygons: &[Vec<Vec<Point>>]) -> Vec<u32> {
+    let mut encoder = CommandEncoder::default();
+    for rings in polygons {
+        for (i, ring) in rings.iter().enumerate() {
+            let mut ring = round(ring);
+            if ring.len() > 1 && ring.first() == ring.last() {
+                ring.pop();
+            }
+
+            let area = signed_area(&ring);
+            if ring.len() < 3 || area == 0 {
+                // A polygon without exterior is dropped together with its holes
+                if i == 0 {
+                    break;
+                }
+                continue;
+            }
+
+            let is_exterior = i == 0;
+            if (area > 0) != is_exterior {
+                ring.reverse();
+            }
+            encoder.path(&ring, true);
+        }
+    }
+    encoder.commands
+}
+
+/// Collects the features of a layer and deduplicates their property keys and values.
+#[derive(Default)]
+struct LayerEncoder {
+    features: Vec<tile::Feature>,
+    keys: Vec<String>,
+    values: Vec<tile::Value>,
+    key_indices: HashMap<String, u32>,
+}
+
+impl LayerEncoder {
+    fn add_feature(&mut self, feature: &Feature, geom_type: tile::GeomType, geometry: Vec<u32>) {
+        let mut tags = Vec::with_capacity(feature.properties.len() * 2);
+        for (key, value) in &feature.properties {
+            let key_index = *self.key_indices.entry(key.clone()).or_insert_with(|| {
+                self.keys.push(key.clone());
+                self.keys.len() as u32 - 1
+            });
+            let value_index = match self.values.iter().position(|other| other == value) {
+                Some(index) => index as u32,
+                None => {
+                    self.values.push(value.clone());
+                    self.values.len() as u32 - 1
+                }
+            };
+            tags.push(key_index);
+            tags.push(value_index);
+        }
+
+        self.features.push(tile::Feature {
+            id: feature.id,
+            tags,
+            r#type: Some(geom_type as i32),
+            geometry,
+        });
+    }
+
+    fn finish(self, name: &str) -> tile::Layer {
+        tile::Layer {
+            version: 2,
+            name: name.to_string(),
+            features: self.features,
+            keys: self.keys,
+            values: self.values,
+            extent: Some(EXTENT_UINT),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use geozero::mvt::{Message, Tile};
+
+    use super::{clip_line, round, simplify, GeoJsonSource};
+    use crate::coords::{WorldTileCoords, ZoomLevel};
+
+    #[test]
+    fn test_tiles() {
+        // language=json
+        let json = r#"
+        {
+          "type": "FeatureCollection",
+          "features": [
+            {
+              "type": "Feature",
+              "id": 7,
+              "properties": {"name": "square", "rank": 1},
+              "geometry": {
+                "type": "Polygon",
+                "coordinates": [[[10, 10], [20, 10], [20, 20], [10, 20], [10, 10]]]
+              }
+            },
+            {
+              "type": "Feature",
+              "properties": {"name": "route"},
+              "geometry": {"type": "LineString", "coordinates": [[-170, 0], [170, 0]]}
+            }
+          ]
+        }
+        "#;
+
+        let source = GeoJsonSource::from_str(json, "overlay").unwrap();
+
+        let root = Tile::decode(source.tile(&WorldTileCoords::default()).as_slice()).unwrap();
+        assert_eq!(root.layers.len(), 1);
+        let layer = &root.layers[0];
+        assert_eq!(layer.name, "overlay");
+        assert_eq!(layer.features.len(), 2);
+        assert_eq!(layer.features[0].id, Some(7));
+        assert_eq!(layer.keys, vec!["name".to_string(), "rank".to_string()]);
+
+        // Only the line crosses the tile in the south-west
+        let south_west = WorldTileCoords::from((0, 1, ZoomLevel::new(1)));
+        let tile = Tile::decode(source.tile(&south_west).as_slice()).unwrap();
+        assert_eq!(tile.layers[0].features.len(), 1);
+
+        // No feature is in the tile, so the layer is missing
+        let empty = WorldTileCoords::from((0, 0, ZoomLevel::new(4)));
+        let tile = Tile::decode(source.tile(&empty).as_slice()).unwrap();
+        assert!(tile.layers.is_empty());
+
+        assert!(GeoJsonSource::from_str(r#"{"type": "Circle"}"#, "overlay").is_err());
+    }
+
+    #[test]
+    fn test_clip_line() {
+        let parts = clip_line(&[
+            [-1000.0, 100.0],
+            [100.0, 100.0],
+            [100.0, 10000.0],
+            [200.0, 10000.0],
+            [200.0, 200.0],
+        ]);
+
+        // The line leaves the tile at the buffered bounds
+        assert_eq!(
+            parts.iter().map(|part| round(part)).collect::<Vec<_>>(),
+            vec![
+                vec![[-64, 100], [100, 100], [100, 4160]],
+                vec![[200, 4160], [200, 200]],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_simplify_tolerance() {
+        // A half circle which is sampled every 0.1 degrees
+        let arc = (0..=1800)
+            .map(|i| {
+                let angle = (i as f64 / 10.0).to_radians();
+                format!("[{}, {}]", 10.0 * angle.cos(), 10.0 * angle.sin())
+            })
+            .collect::<Vec<_>>()
+            .join(",");
+        let json = format!(r#"{{"type": "LineString", "coordinates": [{arc}]}}"#);
+
+        let geometry_len = |source: &GeoJsonSource| {
+            let tile = Tile::decode(source.tile(&WorldTileCoords::default()).as_slice()).unwrap();
+            tile.layers[0].features[0].geometry.len()
+        };
+
+        let source = GeoJsonSource::from_str(&json, "overlay").unwrap();
+        let exact = geometry_len(&source);
+        let simplified = geometry_len(&source.clone().with_simplify_tolerance(1.0));
+        let coarse = geometry_len(&source.with_simplify_tolerance(8.0));
+        assert!(simplified < exact / 4);
+        assert!(coarse < simplified);
+
+        // Points on a straight line are dropped, the corner is kept
+        assert_eq!(
+            simplify(&[[0.0, 0.0], [1.0, 0.1], [2.0, 0.0], [2.0, 2.0]], 0.5),
+            vec![[0.0, 0.0], [2.0, 0.0], [2.0, 2.0]]
+        );
+    }
+
+    #[test]
+    fn test_degenerate_rings() {
+        // A square which is smaller than the tolerance
+        // language=json
+        let json = r#"
+        {
+          "type": "Polygon",
+          "coordinates": [[[0, 0], [1, 0], [1, 1], [0, 1], [0, 0]]]
+        }
+        "#;
+        let geometry = |source: &GeoJsonSource| {
+            let tile = Tile::decode(source.tile(&WorldTileCoords::default()).as_slice()).unwrap();
+            tile.layers[0].features[0].geometry.clone()
+        };
+
+        let source = GeoJsonSource::from_str(json, "overlay").unwrap();
+        assert_eq!(
+            geometry(&source.clone().with_simplify_tolerance(100.0)),
+            geometry(&source)
+        );
+    }
+}
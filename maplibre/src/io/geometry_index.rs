@@ -185,6 +185,7 @@ pub struct IndexProcessor {
     geo_writer: GeoWriter,
     geometries: Vec<IndexedGeometry<f64>>,
     properties: Option<HashMap<String, String>>,
+    scale: f64,
 }
 
 impl IndexProcessor {
@@ -193,9 +194,16 @@ impl IndexProcessor {
             geo_writer: GeoWriter::new(),
             geometries: Vec::new(),
             properties: None,
+            scale: 1.0,
         }
     }
 
+    /// Multiplies the coordinates of the following geometries with `scale`, for example to
+    /// index layers whose extent differs from [`EXTENT`].
+    pub fn set_scale(&mut self, scale: f64) {
+        self.scale = scale;
+    }
+
     pub fn build_tree(self) -> RTree<IndexedGeometry<f64>> {
         RTree::bulk_load(self.geometries)
     }
@@ -213,7 +221,7 @@ impl Default for IndexProcessor {
 
 impl GeomProcessor for IndexProcessor {
     fn xy(&mut self, x: f64, y: f64, idx: usize) -> Result<(), GeozeroError> {
-        self.geo_writer.xy(x, y, idx)
+        self.geo_writer.xy(x * self.scale, y * self.scale, idx)
     }
     fn point_begin(&mut self, idx: usize) -> Result<(), GeozeroError> {
         self.geo_writer.point_begin(idx)
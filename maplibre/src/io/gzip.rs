@@ -0,0 +1,20 @@
+//! Detects and decompresses gzip-compressed tiles.
+
+use std::io::{self, Read};
+
+use flate2::read::GzDecoder;
+
+/// The magic bytes at the start of every gzip stream.
+pub const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// Whether `data` starts like a gzip stream.
+pub fn is_gzip(data: &[u8]) -> bool {
+    data.starts_with(&GZIP_MAGIC)
+}
+
+/// Decompresses the gzip stream in `data`.
+pub fn decompress(data: &[u8]) -> io::Result<Vec<u8>> {
+    let mut decompressed = Vec::new();
+    GzDecoder::new(data).read_to_end(&mut decompressed)?;
+    Ok(decompressed)
+}
@@ -0,0 +1,421 @@
+//! Reads tiles from [MBTiles](https://github.com/mapbox/mbtiles-spec) archives.
+//!
+//! The `metadata` table of an archive is read when it is opened. The declared `format` decides
+//! whether tile data is gunzipped and whether it belongs to the vector or raster pipeline, see
+//! [`MbtilesSource`].
+
+use std::{
+    collections::HashMap,
+    path::Path,
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
+
+use async_trait::async_trait;
+use rusqlite::{params, Connection, OptionalExtension};
+use thiserror::Error;
+
+use crate::{
+    coords::{WorldTileCoords, ZoomLevel},
+    io::{
+        fn_source::parse_tile_url,
+        gzip,
+        source_client::{HttpClient, SourceFetchError},
+    },
+    style::source::{Source, TileAddressingScheme},
+};
+
+#[derive(Error, Debug)]
+pub enum MbtilesError {
+    #[error("reading from the sqlite database failed")]
+    Sqlite(#[from] rusqlite::Error),
+    #[error("required metadata `{0}` is missing")]
+    MissingMetadata(&'static str),
+    #[error("metadata `{key}` has the invalid value `{value}`")]
+    InvalidMetadata { key: &'static str, value: String },
+    #[error("decompressing tile data failed")]
+    Decompression(#[from] std::io::Error),
+    #[error("tile {0} does not exist in the archive")]
+    TileNotFound(WorldTileCoords),
+    #[error("style source is of type {expected} but the archive contains {found:?} tiles")]
+    SourceMismatch {
+        expected: &'static str,
+        found: TileFormat,
+    },
+}
+
+/// The format of the tiles stored in an archive, as declared by the `format` metadata.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TileFormat {
+    /// Mapbox vector tiles
+    Pbf,
+    Png,
+    Jpg,
+    Webp,
+}
+
+impl TileFormat {
+    pub fn is_vector(&self) -> bool {
+        matches!(self, TileFormat::Pbf)
+    }
+
+    pub fn is_raster(&self) -> bool {
+        !self.is_vector()
+    }
+}
+
+impl FromStr for TileFormat {
+    type Err = MbtilesError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pbf" | "mvt" => Ok(TileFormat::Pbf),
+            "png" => Ok(TileFormat::Png),
+            "jpg" | "jpeg" => Ok(TileFormat::Jpg),
+            "webp" => Ok(TileFormat::Webp),
+            _ => Err(MbtilesError::InvalidMetadata {
+                key: "format",
+                value: s.to_string(),
+            }),
+        }
+    }
+}
+
+/// The compression which is applied to the tile data within the archive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TileCompression {
+    None,
+    Gzip,
+}
+
+/// The subset of the `metadata` table which is relevant for reading tiles.
+#[derive(Debug, Clone)]
+pub struct MbtilesMetadata {
+    pub format: TileFormat,
+    pub compression: TileCompression,
+    pub minzoom: Option<ZoomLevel>,
+    pub maxzoom: Option<ZoomLevel>,
+    /// The bounds in which tiles are available as `(left, bottom, right, top)` in WGS84.
+    pub bounds: Option<(f64, f64, f64, f64)>,
+}
+
+impl MbtilesMetadata {
+    fn from_entries(entries: &HashMap<String, String>) -> Result<Self, MbtilesError> {
+        let format: TileFormat = entries
+            .get("format")
+            .ok_or(MbtilesError::MissingMetadata("format"))?
+            .parse()?;
+
+        let compression = match entries.get("compression").map(|value| value.as_str()) {
+            Some("gzip") => TileCompression::Gzip,
+            Some("none") => TileCompression::None,
+            Some(value) => {
+                return Err(MbtilesError::InvalidMetadata {
+                    key: "compression",
+                    value: value.to_string(),
+                })
+            }
+            // Vector tiles within MBTiles are gzipped by convention
+            None if format.is_vector() => TileCompression::Gzip,
+            None => TileCompression::None,
+        };
+
+        let parse_zoom = |key: &'static str| -> Result<Option<ZoomLevel>, MbtilesError> {
+            entries
+                .get(key)
+                .map(|value| {
+                    value
+                        .trim()
+                        .parse::<u8>()
+                        .map(ZoomLevel::from)
+                        .map_err(|_| MbtilesError::InvalidMetadata {
+                            key,
+                            value: value.clone(),
+                        })
+                })
+                .transpose()
+        };
+
+        let bounds = entries
+            .get("bounds")
+            .map(|value| {
+                let parts = value
+                    .split(',')
+                    .map(|part| part.trim().parse::<f64>())
+                    .collect::<Result<Vec<_>, _>>();
+
+                match parts.as_deref() {
+                    Ok([left, bottom, right, top]) => Ok((*left, *bottom, *right, *top)),
+                    _ => Err(MbtilesError::InvalidMetadata {
+                        key: "bounds",
+                        value: value.clone(),
+                    }),
+                }
+            })
+            .transpose()?;
+
+        Ok(Self {
+            format,
+            compression,
+            minzoom: parse_zoom("minzoom")?,
+            maxzoom: parse_zoom("maxzoom")?,
+            bounds,
+        })
+    }
+}
+
+/// A source which reads tiles from a local MBTiles archive.
+///
+/// The source is an [`HttpClient`], so that it can be used within the
+/// [`Kernel`](crate::kernel::Kernel) or be added to a map. The coordinates are read from the URL
+/// like [`FnSource`](crate::io::fn_source::FnSource) does. The file type of the URL selects the
+/// pipeline: `pbf` and `mvt` URLs are requested by the vector pipeline and all other file types
+/// by the raster pipeline. Tiles of the pipeline which does not match the declared `format` of
+/// the archive are not found.
+#[derive(Clone)]
+pub struct MbtilesSource {
+    connection: Arc<Mutex<Connection>>,
+    metadata: MbtilesMetadata,
+}
+
+impl MbtilesSource {
+    /// Opens the archive at `path` and reads its metadata.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, MbtilesError> {
+        Self::from_connection(Connection::open(path)?)
+    }
+
+    /// Reads the metadata from an already opened `connection`.
+    pub fn from_connection(connection: Connection) -> Result<Self, MbtilesError> {
+        let entries = {
+            // language=SQL
+            let mut statement = connection.prepare("SELECT name, value FROM metadata;")?;
+            let rows = statement.query_map([], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+            })?;
+            rows.collect::<Result<HashMap<_, _>, _>>()?
+        };
+
+        let metadata = MbtilesMetadata::from_entries(&entries)?;
+
+        Ok(Self {
+            connection: Arc::new(Mutex::new(connection)),
+            metadata,
+        })
+    }
+
+    pub fn metadata(&self) -> &MbtilesMetadata {
+        &self.metadata
+    }
+
+    pub fn format(&self) -> TileFormat {
+        self.metadata.format
+    }
+
+    pub fn minzoom(&self) -> Option<ZoomLevel> {
+        self.metadata.minzoom
+    }
+
+    pub fn maxzoom(&self) -> Option<ZoomLevel> {
+        self.metadata.maxzoom
+    }
+
+    pub fn bounds(&self) -> Option<(f64, f64, f64, f64)> {
+        self.metadata.bounds
+    }
+
+    /// Checks whether the tiles in this archive can be used for the given style `source`.
+    pub fn validate_source(&self, source: &Source) -> Result<(), MbtilesError> {
+        let format = self.format();
+        match source {
+            Source::Vector(_) if !format.is_vector() => Err(MbtilesError::SourceMismatch {
+                expected: "vector",
+                found: format,
+            }),
+            Source::Raster(_) if !format.is_raster() => Err(MbtilesError::SourceMismatch {
+                expected: "raster",
+                found: format,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Reads the tile at `coords`. Vector tiles are decompressed if the archive declares them
+    /// as gzipped.
+    pub fn fetch_tile(&self, coords: &WorldTileCoords) -> Result<Vec<u8>, MbtilesError> {
+        // MBTiles stores the rows according to TMS
+        let tile_coords = coords
+            .into_tile(TileAddressingScheme::TMS)
+            .ok_or(MbtilesError::TileNotFound(*coords))?;
+
+        let data: Option<Vec<u8>> = {
+            let connection = self.connection.lock().expect("connection poisoned");
+            // language=SQL
+            connection
+                .query_row(
+                    "SELECT tile_data FROM tiles
+                        WHERE zoom_level = ?1 AND tile_column = ?2 AND tile_row = ?3;",
+                    params![u8::from(tile_coords.z), tile_coords.x, tile_coords.y],
+                    |row| row.get(0),
+                )
+                .optional()?
+        };
+
+        let data = data.ok_or(MbtilesError::TileNotFound(*coords))?;
+
+        // Some generators declare gzip but store plain data, so we check the magic bytes.
+        if self.metadata.compression == TileCompression::Gzip && gzip::is_gzip(&data) {
+            #[cfg(feature = "trace")]
+            let _span = tracing::info_span!("decompress", %coords).entered();
+            Ok(gzip::decompress(&data)?)
+        } else {
+            Ok(data)
+        }
+    }
+
+    /// Whether the tile at `url` is requested by the pipeline which matches the format of the
+    /// archive. URLs without a known file type are served.
+    fn serves(&self, url: &str) -> bool {
+        let path = url.split(['?', '#']).next().unwrap_or(url);
+        let requested = path
+            .rsplit('/')
+            .next()
+            .and_then(|name| name.rsplit_once('.'))
+            .and_then(|(_, filetype)| filetype.parse::<TileFormat>().ok());
+
+        requested.map_or(true, |requested| {
+            requested.is_vector() == self.format().is_vector()
+        })
+    }
+}
+
+#[cfg_attr(not(feature = "thread-safe-futures"), async_trait(?Send))]
+#[cfg_attr(feature = "thread-safe-futures", async_trait)]
+impl HttpClient for MbtilesSource {
+    async fn fetch(&self, url: &str) -> Result<Vec<u8>, SourceFetchError> {
+        let coords = parse_tile_url(url)
+            .filter(|_| self.serves(url))
+            .ok_or_else(|| SourceFetchError::not_found(url))?;
+
+        self.fetch_tile(&coords).map_err(|e| match e {
+            MbtilesError::TileNotFound(coords) => SourceFetchError::not_found(&coords.to_string()),
+            e => SourceFetchError(Box::new(e)),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::{write::GzEncoder, Compression};
+    use rusqlite::{params, Connection};
+
+    use super::{MbtilesSource, TileCompression, TileFormat};
+    use crate::{
+        coords::{WorldTileCoords, ZoomLevel},
+        io::{
+            source_client::HttpSourceClient,
+            source_type::{RasterSource, SourceType, TessellateSource},
+        },
+        style::source::{Source, VectorSource},
+    };
+
+    fn create_archive(metadata: &[(&str, &str)], tile: &[u8]) -> Connection {
+        let connection = Connection::open_in_memory().unwrap();
+        // language=SQL
+        connection
+            .execute_batch(
+                "CREATE TABLE metadata (name text, value text);
+                 CREATE TABLE tiles (zoom_level integer, tile_column integer, tile_row integer, tile_data blob);",
+            )
+            .unwrap();
+        for (name, value) in metadata {
+            connection
+                .execute(
+                    "INSERT INTO metadata (name, value) VALUES (?1, ?2);",
+                    params![name, value],
+                )
+                .unwrap();
+        }
+        // Tile WT(x=1,y=0,z=1) is stored as TMS row 1
+        connection
+            .execute("INSERT INTO tiles VALUES (1, 1, 1, ?1);", params![tile])
+            .unwrap();
+        connection
+    }
+
+    #[test]
+    fn test_gzipped_vector_tiles() {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(b"tile").unwrap();
+        let gzipped = encoder.finish().unwrap();
+
+        let source = MbtilesSource::from_connection(create_archive(
+            &[
+                ("format", "pbf"),
+                ("minzoom", "0"),
+                ("maxzoom", "14"),
+                ("bounds", "-180.0,-85.0,180.0,85.0"),
+            ],
+            &gzipped,
+        ))
+        .unwrap();
+
+        assert_eq!(source.format(), TileFormat::Pbf);
+        assert_eq!(source.metadata().compression, TileCompression::Gzip);
+        assert_eq!(source.maxzoom(), Some(ZoomLevel::new(14)));
+        assert_eq!(source.bounds(), Some((-180.0, -85.0, 180.0, 85.0)));
+
+        let coords = WorldTileCoords::from((1, 0, ZoomLevel::new(1)));
+        assert_eq!(source.fetch_tile(&coords).unwrap(), b"tile");
+        assert!(source
+            .fetch_tile(&WorldTileCoords::from((0, 0, ZoomLevel::new(1))))
+            .is_err());
+    }
+
+    #[test]
+    fn test_raster_source_mismatch() {
+        let source =
+            MbtilesSource::from_connection(create_archive(&[("format", "png")], b"png")).unwrap();
+
+        assert!(source.format().is_raster());
+        assert!(source
+            .validate_source(&Source::Vector(VectorSource {
+                attribution: None,
+                bounds: None,
+                maxzoom: None,
+                minzoom: None,
+                scheme: None,
+                tiles: None,
+            }))
+            .is_err());
+
+        let coords = WorldTileCoords::from((1, 0, ZoomLevel::new(1)));
+        assert_eq!(source.fetch_tile(&coords).unwrap(), b"png");
+    }
+
+    #[tokio::test]
+    async fn test_source_client() {
+        let source =
+            MbtilesSource::from_connection(create_archive(&[("format", "png")], b"tile")).unwrap();
+        let client = HttpSourceClient::new(source);
+        let coords = WorldTileCoords::from((1, 0, ZoomLevel::new(1)));
+
+        let raster = SourceType::Raster(RasterSource::default());
+        assert_eq!(client.fetch(&coords, &raster).await.unwrap(), b"tile");
+
+        // The archive does not contain tiles for the vector pipeline
+        let vector = SourceType::Tessellate(TessellateSource::default());
+        assert!(client
+            .fetch(&coords, &vector)
+            .await
+            .unwrap_err()
+            .is_not_found());
+        let missing = WorldTileCoords::from((0, 0, ZoomLevel::new(1)));
+        assert!(client
+            .fetch(&missing, &raster)
+            .await
+            .unwrap_err()
+            .is_not_found());
+    }
+}
@@ -3,9 +3,22 @@
 pub use geozero::mvt::tile::Layer as RawLayer;
 
 pub mod apc;
+#[cfg(all(feature = "disk-cache", not(target_arch = "wasm32")))]
+pub mod disk_cache;
+pub mod fallback_source;
+pub mod fn_source;
+pub mod geojson_source;
 pub mod geometry_index;
+#[cfg(all(
+    any(feature = "headless", feature = "mbtiles", feature = "disk-cache"),
+    not(target_arch = "wasm32")
+))]
+pub mod gzip;
+#[cfg(all(feature = "mbtiles", not(target_arch = "wasm32")))]
+pub mod mbtiles;
 pub mod scheduler;
 pub mod source_client;
 pub mod source_type;
 #[cfg(feature = "embed-static-tiles")]
 pub mod static_tile_fetcher;
+pub mod tile_expiry;
@@ -1,13 +1,28 @@
 //! HTTP client.
 
+use std::{
+    io,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
 use async_trait::async_trait;
 use thiserror::Error;
 
-use crate::{coords::WorldTileCoords, io::source_type::SourceType};
+use crate::{
+    coords::WorldTileCoords,
+    io::{source_type::SourceType, tile_expiry::StoredTile},
+};
 
 /// A closure that returns a HTTP client.
 pub type HTTPClientFactory<HC> = dyn Fn() -> HC;
 
+/// A closure which rewrites the URL of a tile before it is fetched, for example to route the
+/// request through a proxy.
+pub type UrlRewrite = dyn Fn(WorldTileCoords, &str) -> String + Send + Sync;
+
 /// On the web platform futures are not thread-safe (i.e. not Send). This means we need to tell
 /// async_trait that these bounds should not be placed on the async trait:
 /// [https://github.com/dtolnay/async-trait/blob/b70720c4c1cc0d810b7446efda44f81310ee7bf2/README.md#non-threadsafe-futures](https://github.com/dtolnay/async-trait/blob/b70720c4c1cc0d810b7446efda44f81310ee7bf2/README.md#non-threadsafe-futures)
@@ -18,6 +33,19 @@ pub type HTTPClientFactory<HC> = dyn Fn() -> HC;
 #[cfg_attr(feature = "thread-safe-futures", async_trait)]
 pub trait HttpClient: Clone + Sync + Send + 'static {
     async fn fetch(&self, url: &str) -> Result<Vec<u8>, SourceFetchError>;
+
+    /// Same as [`HttpClient::fetch`], but also returns until when the response is valid. By
+    /// default responses never expire.
+    async fn fetch_stored(&self, url: &str) -> Result<StoredTile, SourceFetchError> {
+        let data = self.fetch(url).await?;
+        Ok(StoredTile::new(data.into_boxed_slice(), None))
+    }
+
+    /// Completes the writes of responses which the client stores, for example in a cache. By
+    /// default there is nothing to complete.
+    async fn flush(&self) -> io::Result<()> {
+        Ok(())
+    }
 }
 
 /// Gives access to the HTTP client which can be of multiple types,
@@ -28,12 +56,35 @@ where
     HC: HttpClient,
 {
     inner_client: HC,
+    url_rewrite: Option<Arc<UrlRewrite>>,
+    subdomains: Arc<[String]>,
+    /// Counts the requests in order to rotate through the subdomains
+    requests: Arc<AtomicUsize>,
 }
 
 #[derive(Error, Debug)]
 #[error("failed to fetch from source")]
 pub struct SourceFetchError(#[source] pub Box<dyn std::error::Error>);
 
+impl SourceFetchError {
+    /// Creates the error for a tile which does not exist at `url`.
+    pub fn not_found(url: &str) -> Self {
+        SourceFetchError(Box::new(TileNotFound(url.to_string())))
+    }
+
+    /// Whether the source does not contain the tile, for example because the server responded
+    /// with 404. In sparse tilesets this is expected and means that the tile has no data.
+    pub fn is_not_found(&self) -> bool {
+        self.0.is::<TileNotFound>()
+    }
+}
+
+/// The source does not contain the tile at the given URL, see
+/// [`SourceFetchError::is_not_found`].
+#[derive(Error, Debug)]
+#[error("tile not found at {0}")]
+pub struct TileNotFound(pub String);
+
 /// Defines the different types of HTTP clients such as basic HTTP and Mbtiles.
 /// More types might be coming such as S3 and other cloud http clients.
 #[derive(Clone)]
@@ -59,6 +110,19 @@ where
     ) -> Result<Vec<u8>, SourceFetchError> {
         self.http.fetch(coords, source_type).await
     }
+
+    pub async fn fetch_stored(
+        &self,
+        coords: &WorldTileCoords,
+        source_type: &SourceType,
+    ) -> Result<StoredTile, SourceFetchError> {
+        self.http.fetch_stored(coords, source_type).await
+    }
+
+    /// See [`HttpClient::flush`].
+    pub async fn flush(&self) -> io::Result<()> {
+        self.http.flush().await
+    }
 }
 
 impl<HC> HttpSourceClient<HC>
@@ -68,6 +132,40 @@ where
     pub fn new(http_client: HC) -> Self {
         Self {
             inner_client: http_client,
+            url_rewrite: None,
+            subdomains: Arc::new([]),
+            requests: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// Rewrites the URL of each tile with `url_rewrite` after the `{s}` token is replaced.
+    pub fn with_url_rewrite<F>(mut self, url_rewrite: F) -> Self
+    where
+        F: Fn(WorldTileCoords, &str) -> String + Send + Sync + 'static,
+    {
+        self.url_rewrite = Some(Arc::new(url_rewrite));
+        self
+    }
+
+    /// Replaces the `{s}` token in tile URLs with one of the `subdomains`. The subdomains are
+    /// used in turn, so that requests are distributed across them.
+    pub fn with_subdomains(mut self, subdomains: Vec<String>) -> Self {
+        self.subdomains = subdomains.into();
+        self
+    }
+
+    /// Returns the URL from which the tile at `coords` is fetched.
+    pub fn tile_url(&self, coords: &WorldTileCoords, source_type: &SourceType) -> String {
+        let mut url = source_type.format(coords);
+
+        if url.contains("{s}") && !self.subdomains.is_empty() {
+            let request = self.requests.fetch_add(1, Ordering::Relaxed);
+            url = url.replace("{s}", &self.subdomains[request % self.subdomains.len()]);
+        }
+
+        match &self.url_rewrite {
+            Some(url_rewrite) => url_rewrite(*coords, &url),
+            None => url,
         }
     }
 
@@ -77,7 +175,68 @@ where
         source_type: &SourceType,
     ) -> Result<Vec<u8>, SourceFetchError> {
         self.inner_client
-            .fetch(source_type.format(coords).as_str())
+            .fetch(self.tile_url(coords, source_type).as_str())
+            .await
+    }
+
+    pub async fn fetch_stored(
+        &self,
+        coords: &WorldTileCoords,
+        source_type: &SourceType,
+    ) -> Result<StoredTile, SourceFetchError> {
+        self.inner_client
+            .fetch_stored(self.tile_url(coords, source_type).as_str())
             .await
     }
+
+    /// See [`HttpClient::flush`].
+    pub async fn flush(&self) -> io::Result<()> {
+        self.inner_client.flush().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{HttpSourceClient, SourceFetchError};
+    use crate::{
+        coords::{WorldTileCoords, ZoomLevel},
+        io::{
+            fn_source::FnSource,
+            source_type::{SourceType, TessellateSource},
+        },
+    };
+
+    #[test]
+    fn test_tile_url() {
+        let source = FnSource::new(|_| async move { Ok(Vec::new()) });
+        let client = HttpSourceClient::new(source)
+            .with_subdomains(vec!["a".to_string(), "b".to_string()])
+            .with_url_rewrite(|coords, url| format!("{url}?z={}", coords.z));
+
+        let coords = WorldTileCoords::from((1, 2, ZoomLevel::new(3)));
+        let source_type =
+            SourceType::Tessellate(TessellateSource::new("https://{s}.example.com", "pbf"));
+
+        assert_eq!(
+            client.tile_url(&coords, &source_type),
+            "https://a.example.com/3/1/2.pbf?z=3"
+        );
+        // Clones share the rotation
+        assert_eq!(
+            client.clone().tile_url(&coords, &source_type),
+            "https://b.example.com/3/1/2.pbf?z=3"
+        );
+        assert_eq!(
+            client.tile_url(&coords, &source_type),
+            "https://a.example.com/3/1/2.pbf?z=3"
+        );
+    }
+
+    #[test]
+    fn test_is_not_found() {
+        assert!(SourceFetchError::not_found("https://example.com/0/0/0.pbf").is_not_found());
+
+        let other = std::io::Error::new(std::io::ErrorKind::Other, "connection reset");
+        assert!(!SourceFetchError(Box::new(other)).is_not_found());
+    }
 }
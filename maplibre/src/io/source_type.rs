@@ -1,4 +1,10 @@
-use crate::{coords::WorldTileCoords, style::source::TileAddressingScheme};
+use crate::{
+    coords::WorldTileCoords,
+    style::{
+        source::{Source, TileAddressingScheme, VectorSource},
+        Style, DEFAULT_SOURCE_ID,
+    },
+};
 
 /// Represents a source from which the vector tile are fetched.
 #[derive(Clone)]
@@ -80,13 +86,31 @@ impl Default for RasterSource {
 pub enum SourceType {
     Raster(RasterSource),
     Tessellate(TessellateSource),
+    /// Vector tiles which are fetched from the tile url of a style source, for example of a
+    /// source which was configured from TileJSON.
+    Vector(VectorSource),
 }
 
 impl SourceType {
+    /// The source of the vector tiles of the layers in `style` which do not name a source. This
+    /// is the [`DEFAULT_SOURCE_ID`] source of the style if it is a vector source with a tile url,
+    /// and the default [`TessellateSource`] otherwise.
+    pub fn default_vector(style: &Style) -> Self {
+        match style.sources.get(DEFAULT_SOURCE_ID) {
+            Some(Source::Vector(source)) if source.tiles.is_some() => {
+                SourceType::Vector(source.clone())
+            }
+            _ => SourceType::Tessellate(TessellateSource::default()),
+        }
+    }
+
     pub fn format(&self, coords: &WorldTileCoords) -> String {
         match self {
             SourceType::Raster(raster_source) => raster_source.format(coords),
             SourceType::Tessellate(tessellate_source) => tessellate_source.format(coords),
+            SourceType::Vector(vector_source) => vector_source
+                .tile_url(coords)
+                .expect("vector source has a tile url"),
         }
     }
 }
@@ -0,0 +1,222 @@
+//! Determines until when fetched tiles are valid from the `Cache-Control` and `Expires` headers
+//! of the response.
+
+use std::time::{Duration, SystemTime};
+
+use crate::util::hash::fnv1a;
+
+/// A fetched tile together with the time until which it is valid.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoredTile {
+    pub data: Box<[u8]>,
+    /// `None` if the tile never expires
+    pub expires_at: Option<SystemTime>,
+    /// The `ETag` header of the response, which identifies the version of the tile
+    pub etag: Option<String>,
+}
+
+impl StoredTile {
+    pub fn new(data: Box<[u8]>, expires_at: Option<SystemTime>) -> Self {
+        Self {
+            data,
+            expires_at,
+            etag: None,
+        }
+    }
+
+    pub fn with_etag(mut self, etag: impl Into<String>) -> Self {
+        self.etag = Some(etag.into());
+        self
+    }
+
+    /// A stable hash of the fetched data. Equal data is tessellated into equal geometry, so a
+    /// re-fetched tile with the same content hash does not have to be processed again. See
+    /// [`DefaultLayerTesselated::fingerprint`](crate::vector::DefaultLayerTesselated::fingerprint)
+    /// for the fingerprint of the tessellated geometry.
+    pub fn content_hash(&self) -> u64 {
+        fnv1a(&self.data)
+    }
+
+    /// Whether the tile is stale at `now` and should be fetched again.
+    pub fn is_expired(&self, now: SystemTime) -> bool {
+        self.expires_at
+            .map_or(false, |expires_at| expires_at <= now)
+    }
+}
+
+/// Returns until when a response which was received at `now` is valid. `max-age` takes
+/// precedence over `Expires`. Responses with `no-store` or `no-cache` are expired immediately.
+/// Returns `None` if neither header determines an expiry.
+///
+/// `max-age` is relative to the time the response was generated, so the `age` of the response,
+/// see [`response_age`], is subtracted from it.
+pub fn expiry_from_headers(
+    cache_control: Option<&str>,
+    expires: Option<&str>,
+    age: Duration,
+    now: SystemTime,
+) -> Option<SystemTime> {
+    if let Some(cache_control) = cache_control {
+        let directives = cache_control
+            .split(',')
+            .map(|directive| directive.trim().to_ascii_lowercase())
+            .collect::<Vec<_>>();
+
+        if directives
+            .iter()
+            .any(|directive| directive == "no-store" || directive == "no-cache")
+        {
+            return Some(now);
+        }
+
+        let max_age = directives.iter().find_map(|directive| {
+            directive
+                .strip_prefix("max-age=")
+                .and_then(|max_age| max_age.trim_matches('"').parse::<u64>().ok())
+        });
+        if let Some(max_age) = max_age {
+            return Some(now + Duration::from_secs(max_age).saturating_sub(age));
+        }
+    }
+
+    // An invalid date, for example "0", means that the response is already expired
+    expires.map(|expires| parse_http_date(expires).unwrap_or(now))
+}
+
+/// Returns how long ago a response which was received at `now` was generated. This is the
+/// larger of the `Age` header, which caches add to the responses they serve, and the time since
+/// the `Date` header, which is kept by caches which replay the stored headers.
+pub fn response_age(age: Option<&str>, date: Option<&str>, now: SystemTime) -> Duration {
+    let age = age
+        .and_then(|age| age.trim().parse::<u64>().ok())
+        .map_or(Duration::ZERO, Duration::from_secs);
+    let since_date = date
+        .and_then(parse_http_date)
+        .and_then(|date| now.duration_since(date).ok())
+        .unwrap_or(Duration::ZERO);
+    age.max(since_date)
+}
+
+/// Parses a date in the preferred format of HTTP, for example `Sun, 06 Nov 1994 08:49:37 GMT`.
+pub fn parse_http_date(date: &str) -> Option<SystemTime> {
+    let mut parts = date.split_whitespace();
+    let _weekday = parts.next()?;
+    let day = parts.next()?.parse::<i64>().ok()?;
+    let month = match parts.next()? {
+        "Jan" => 1,
+        "Feb" => 2,
+        "Mar" => 3,
+        "Apr" => 4,
+        "May" => 5,
+        "Jun" => 6,
+        "Jul" => 7,
+        "Aug" => 8,
+        "Sep" => 9,
+        "Oct" => 10,
+        "Nov" => 11,
+        "Dec" => 12,
+        _ => return None,
+    };
+    let year = parts.next()?.parse::<i64>().ok()?;
+
+    let mut time = parts
+        .next()?
+        .split(':')
+        .map(|part| part.parse::<i64>().ok());
+    let (hours, minutes, seconds) = (time.next()??, time.next()??, time.next()??);
+
+    if parts.next()? != "GMT"
+        || !(1..=31).contains(&day)
+        || hours > 23
+        || minutes > 59
+        || seconds > 60
+    {
+        return None;
+    }
+
+    // Days since the unix epoch, see http://howardhinnant.github.io/date_algorithms.html
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let year_of_era = y - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146097 + day_of_era - 719468;
+
+    let secs = days * 86400 + hours * 3600 + minutes * 60 + seconds;
+    Some(SystemTime::UNIX_EPOCH + Duration::from_secs(u64::try_from(secs).ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use super::{expiry_from_headers, parse_http_date, response_age, StoredTile};
+
+    #[test]
+    fn test_expiry() {
+        let date = parse_http_date("Sun, 06 Nov 1994 08:49:37 GMT").unwrap();
+        assert_eq!(
+            date,
+            SystemTime::UNIX_EPOCH + Duration::from_secs(784111777)
+        );
+        assert_eq!(parse_http_date("06 Nov 1994"), None);
+
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1000);
+        assert_eq!(
+            expiry_from_headers(Some("public, max-age=60"), None, Duration::ZERO, now),
+            Some(now + Duration::from_secs(60))
+        );
+        // max-age takes precedence
+        assert_eq!(
+            expiry_from_headers(
+                Some("max-age=60"),
+                Some("Sun, 06 Nov 1994 08:49:37 GMT"),
+                Duration::ZERO,
+                now
+            ),
+            Some(now + Duration::from_secs(60))
+        );
+        assert_eq!(
+            expiry_from_headers(
+                Some("public"),
+                Some("Sun, 06 Nov 1994 08:49:37 GMT"),
+                Duration::ZERO,
+                now
+            ),
+            Some(date)
+        );
+        assert_eq!(
+            expiry_from_headers(Some("no-cache"), None, Duration::ZERO, now),
+            Some(now)
+        );
+        assert_eq!(
+            expiry_from_headers(None, Some("0"), Duration::ZERO, now),
+            Some(now)
+        );
+        assert_eq!(
+            expiry_from_headers(Some("public"), None, Duration::ZERO, now),
+            None
+        );
+        // The response was cached for 45 of its 60 seconds
+        let age = response_age(Some("45"), None, now);
+        assert_eq!(age, Duration::from_secs(45));
+        assert_eq!(
+            expiry_from_headers(Some("max-age=60"), None, age, now),
+            Some(now + Duration::from_secs(15))
+        );
+        assert_eq!(
+            expiry_from_headers(Some("max-age=60"), None, Duration::from_secs(90), now),
+            Some(now)
+        );
+        assert_eq!(
+            response_age(Some("10"), Some("Thu, 01 Jan 1970 00:16:10 GMT"), now),
+            Duration::from_secs(30)
+        );
+        assert_eq!(response_age(None, None, now), Duration::ZERO);
+
+        let tile = StoredTile::new(Box::new([]), Some(now));
+        assert!(tile.is_expired(now));
+        assert!(!tile.is_expired(now - Duration::from_secs(1)));
+        assert!(!StoredTile::new(Box::new([]), None).is_expired(now));
+    }
+}
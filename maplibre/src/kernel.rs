@@ -42,6 +42,7 @@ pub struct KernelBuilder<E: Environment> {
     apc: Option<E::AsyncProcedureCall>,
     scheduler: Option<E::Scheduler>,
     http_client: Option<E::HttpClient>,
+    source_client: Option<HttpSourceClient<E::HttpClient>>,
 }
 
 impl<E: Environment> Default for KernelBuilder<E> {
@@ -56,6 +57,7 @@ impl<E: Environment> KernelBuilder<E> {
             scheduler: None,
             apc: None,
             http_client: None,
+            source_client: None,
             map_window_config: None,
         }
     }
@@ -80,11 +82,23 @@ impl<E: Environment> KernelBuilder<E> {
         self
     }
 
+    /// Fetches tiles with `source_client` instead of wrapping the client of
+    /// [`KernelBuilder::with_http_client`]. This allows to configure URL rewriting and
+    /// subdomains, see [`HttpSourceClient::with_url_rewrite`].
+    pub fn with_source_client(mut self, source_client: HttpSourceClient<E::HttpClient>) -> Self {
+        self.source_client = Some(source_client);
+        self
+    }
+
     pub fn build(self) -> Kernel<E> {
+        let source_client = self
+            .source_client
+            .unwrap_or_else(|| HttpSourceClient::new(self.http_client.unwrap())); // TODO: Remove unwrap
+
         Kernel {
             scheduler: self.scheduler.unwrap(), // TODO: Remove unwrap
             apc: self.apc.unwrap(),             // TODO: Remove unwrap
-            source_client: SourceClient::new(HttpSourceClient::new(self.http_client.unwrap())), // TODO: Remove unwrap
+            source_client: SourceClient::new(source_client),
             map_window_config: self.map_window_config.unwrap(), // TODO: Remove unwrap
         }
     }
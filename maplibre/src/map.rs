@@ -31,6 +31,8 @@ pub enum MapError {
     RenderGraphInit(RenderGraphError),
     #[error("initializing device failed")]
     DeviceInit(RenderError),
+    #[error("msaa with {0} samples is not supported by the renderer")]
+    UnsupportedMsaaSamples(u32),
 }
 
 pub enum CurrentMapContext {
@@ -1,13 +1,59 @@
+use std::time::{Duration, SystemTime};
+
 use async_trait::async_trait;
-use reqwest::{Client, StatusCode};
+use reqwest::{header, Client, StatusCode};
 use reqwest_middleware::ClientWithMiddleware;
 use reqwest_middleware_cache::{managers::CACacheManager, Cache, CacheMode};
 
-use crate::io::source_client::{HttpClient, SourceFetchError};
+use crate::io::{
+    source_client::{HttpClient, SourceFetchError},
+    tile_expiry::{expiry_from_headers, response_age, StoredTile},
+};
+
+/// Configures the connection pool of a [`ReqwestHttpClient`].
+///
+/// Connections are kept alive and reused for subsequent requests to the same host. HTTP/2 is
+/// negotiated via ALPN, which allows many tile requests to be multiplexed over one connection.
+#[derive(Debug, Clone)]
+pub struct HttpClientSettings {
+    /// The maximum number of idle connections which are kept per host.
+    pub pool_max_idle_per_host: usize,
+    /// How long idle connections are kept in the pool. `None` keeps them forever.
+    pub pool_idle_timeout: Option<Duration>,
+    /// The interval of TCP keep-alive probes. `None` disables them.
+    pub tcp_keepalive: Option<Duration>,
+    /// The interval of HTTP/2 keep-alive pings. `None` disables them.
+    pub http2_keep_alive_interval: Option<Duration>,
+    /// Use HTTP/2 without negotiating it first. Only use this if all hosts support HTTP/2.
+    pub http2_prior_knowledge: bool,
+    /// How long responses without `Cache-Control` or `Expires` headers are valid, see
+    /// [`HttpClient::fetch_stored`]. `None` means that they never expire.
+    pub default_ttl: Option<Duration>,
+}
+
+impl Default for HttpClientSettings {
+    fn default() -> Self {
+        Self {
+            pool_max_idle_per_host: usize::MAX,
+            pool_idle_timeout: Some(Duration::from_secs(90)),
+            tcp_keepalive: Some(Duration::from_secs(60)),
+            http2_keep_alive_interval: None,
+            http2_prior_knowledge: false,
+            default_ttl: None,
+        }
+    }
+}
 
+/// Fetches data via HTTP. Clones share the same connection pool, so a single client should be
+/// created and cloned instead of creating a client per request.
+///
+/// If a cache path is given, responses are cached on disk. Cached responses are only used while
+/// they are fresh according to their `Cache-Control` and `Expires` headers, otherwise they are
+/// revalidated with the server.
 #[derive(Clone)]
 pub struct ReqwestHttpClient {
     client: ClientWithMiddleware,
+    default_ttl: Option<Duration>,
 }
 
 impl From<reqwest::Error> for SourceFetchError {
@@ -26,7 +72,26 @@ impl ReqwestHttpClient {
     /// cache_path: Under which path should we cache requests.
     // TODO: Use Into<Path> instead of String
     pub fn new(cache_path: Option<String>) -> Self {
-        let mut builder = reqwest_middleware::ClientBuilder::new(Client::new());
+        Self::with_settings(cache_path, HttpClientSettings::default())
+    }
+
+    /// Same as [`ReqwestHttpClient::new`], but with a custom connection pool configuration.
+    pub fn with_settings(cache_path: Option<String>, settings: HttpClientSettings) -> Self {
+        let mut client = Client::builder()
+            .pool_max_idle_per_host(settings.pool_max_idle_per_host)
+            .pool_idle_timeout(settings.pool_idle_timeout)
+            .tcp_keepalive(settings.tcp_keepalive)
+            .http2_keep_alive_interval(settings.http2_keep_alive_interval);
+
+        if settings.http2_prior_knowledge {
+            client = client.http2_prior_knowledge();
+        }
+
+        let client = client
+            .build()
+            .expect("Failed to initialize the HTTP client");
+
+        let mut builder = reqwest_middleware::ClientBuilder::new(client);
 
         if let Some(cache_path) = cache_path {
             builder = builder.with(Cache {
@@ -37,6 +102,7 @@ impl ReqwestHttpClient {
 
         Self {
             client: builder.build(),
+            default_ttl: settings.default_ttl,
         }
     }
 }
@@ -45,16 +111,44 @@ impl ReqwestHttpClient {
 #[cfg_attr(feature = "thread-safe-futures", async_trait)]
 impl HttpClient for ReqwestHttpClient {
     async fn fetch(&self, url: &str) -> Result<Vec<u8>, SourceFetchError> {
+        Ok(self.fetch_stored(url).await?.data.into_vec())
+    }
+
+    async fn fetch_stored(&self, url: &str) -> Result<StoredTile, SourceFetchError> {
         let response = self.client.get(url).send().await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Err(SourceFetchError::not_found(url));
+        }
+
         match response.error_for_status() {
             Ok(response) => {
                 if response.status() == StatusCode::NOT_MODIFIED {
                     log::info!("Using data from cache");
                 }
 
+                let now = SystemTime::now();
+                let header_value = |name: header::HeaderName| {
+                    response
+                        .headers()
+                        .get(name)
+                        .and_then(|value| value.to_str().ok())
+                };
+                let expires_at = expiry_from_headers(
+                    header_value(header::CACHE_CONTROL),
+                    header_value(header::EXPIRES),
+                    response_age(header_value(header::AGE), header_value(header::DATE), now),
+                    now,
+                )
+                .or_else(|| self.default_ttl.map(|ttl| now + ttl));
+                let etag = header_value(header::ETAG).map(|etag| etag.to_string());
+
                 let body = response.bytes().await?;
 
-                Ok(Vec::from(body.as_ref()))
+                let tile = StoredTile::new(Box::from(body.as_ref()), expires_at);
+                Ok(match etag {
+                    Some(etag) => tile.with_etag(etag),
+                    None => tile,
+                })
             }
             Err(e) => Err(SourceFetchError(Box::new(e))),
         }
@@ -1,6 +1,7 @@
 use crate::{
     raster::resource::RasterResources,
     render::{
+        clip_mask::ClipMask,
         eventually::{Eventually, Eventually::Initialized},
         render_phase::{LayerItem, PhaseItem, RenderCommand, RenderCommandResult},
         resource::TrackedRenderPass,
@@ -57,7 +58,12 @@ impl RenderCommand<LayerItem> for DrawRasterTile {
 
         let source_shape = &item.source_shape;
 
-        let reference = source_shape.coords().stencil_reference_value_3d() as u32;
+        let tile_reference = source_shape.mask_coords().stencil_reference_value_3d();
+        let reference = world
+            .resources
+            .get::<ClipMask>()
+            .map(|clip_mask| clip_mask.stencil_reference(tile_reference))
+            .unwrap_or(tile_reference as u32);
 
         pass.set_stencil_reference(reference);
 
@@ -19,16 +19,13 @@ pub struct RasterResources {
 }
 
 impl RasterResources {
-    pub fn new(msaa: Msaa, device: &wgpu::Device, pipeline: wgpu::RenderPipeline) -> Self {
-        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
-            address_mode_u: wgpu::AddressMode::ClampToEdge,
-            address_mode_v: wgpu::AddressMode::ClampToEdge,
-            address_mode_w: wgpu::AddressMode::ClampToEdge,
-            mag_filter: wgpu::FilterMode::Linear,
-            min_filter: wgpu::FilterMode::Linear,
-            mipmap_filter: wgpu::FilterMode::Linear,
-            ..Default::default()
-        });
+    pub fn new(
+        msaa: Msaa,
+        device: &wgpu::Device,
+        pipeline: wgpu::RenderPipeline,
+        sampler: &wgpu::SamplerDescriptor,
+    ) -> Self {
+        let sampler = device.create_sampler(sampler);
         Self {
             sampler,
             msaa,
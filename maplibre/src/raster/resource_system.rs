@@ -18,6 +18,7 @@ pub fn resource_system(
         renderer:
             Renderer {
                 device,
+                adapter,
                 resources: RenderResources { surface, .. },
                 settings,
                 ..
@@ -34,6 +35,11 @@ pub fn resource_system(
             format: surface.surface_format(),
         };
 
+        let anisotropy_supported = adapter
+            .get_downlevel_capabilities()
+            .flags
+            .contains(wgpu::DownlevelFlags::ANISOTROPIC_FILTERING);
+
         RasterResources::new(
             Msaa { samples: 1 },
             device,
@@ -51,6 +57,7 @@ pub fn resource_system(
             )
             .describe_render_pipeline()
             .initialize(device),
+            &settings.raster_sampler.describe(anisotropy_supported),
         )
     });
 }
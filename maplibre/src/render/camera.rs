@@ -74,6 +74,8 @@ pub struct Camera {
     position: Point3<f64>, // The z axis never changes, the zoom is used instead
     yaw: cgmath::Rad<f64>,
     pitch: cgmath::Rad<f64>,
+    /// Clockwise rotation of the map around the vertical axis through the camera
+    bearing: cgmath::Rad<f64>,
 
     width: f64,
     height: f64,
@@ -86,6 +88,7 @@ impl SignificantlyDifferent for Camera {
         self.position.abs_diff_ne(&other.position, epsilon)
             || self.yaw.abs_diff_ne(&other.yaw, epsilon)
             || self.pitch.abs_diff_ne(&other.pitch, epsilon)
+            || self.bearing.abs_diff_ne(&other.bearing, epsilon)
     }
 }
 
@@ -101,6 +104,7 @@ impl Camera {
             position: position.into(),
             yaw: yaw.into(),
             pitch: pitch.into(),
+            bearing: Rad(0.0),
             width: width as f64,
             height: height as f64,
         }
@@ -112,11 +116,17 @@ impl Camera {
     }
 
     fn calc_matrix(&self) -> Matrix4<f64> {
+        // Rotating the world counterclockwise around the camera rotates the map clockwise on
+        // the screen
+        let bearing = Matrix4::from_translation(self.position.to_vec())
+            * Matrix4::from_angle_z(-self.bearing)
+            * Matrix4::from_translation(-self.position.to_vec());
+
         Matrix4::look_to_rh(
             self.position,
             Vector3::new(self.yaw.cos(), self.pitch.sin(), self.yaw.sin()).normalize(),
             Vector3::unit_y(),
-        )
+        ) * bearing
     }
 
     #[tracing::instrument(skip_all)]
@@ -367,6 +377,12 @@ impl Camera {
         self.pitch
     }
 
+    /// Sets the pitch, clamped to the supported range.
+    pub fn set_pitch<P: Into<Rad<f64>>>(&mut self, pitch: P) {
+        let pitch: Rad<f64> = pitch.into();
+        self.pitch = Rad(pitch.0.clamp(MIN_PITCH.0, MAX_PITCH.0));
+    }
+
     pub fn tilt<P: Into<Rad<f64>>>(&mut self, delta: P) {
         let new_pitch = self.pitch + delta.into();
 
@@ -375,6 +391,16 @@ impl Camera {
         }
     }
 
+    pub fn bearing(&self) -> cgmath::Rad<f64> {
+        self.bearing
+    }
+
+    /// Sets the direction which is at the top of the screen, clockwise from north.
+    pub fn set_bearing<B: Into<Rad<f64>>>(&mut self, bearing: B) {
+        let bearing: Rad<f64> = bearing.into();
+        self.bearing = bearing.normalize();
+    }
+
     pub fn move_relative(&mut self, delta: Vector3<f64>) {
         self.position += delta;
     }
@@ -523,4 +549,34 @@ mod tests {
 
         //assert!(reverse_world.abs_diff_eq(&world_pos, 0.05))
     }
+
+    #[test]
+    fn test_bearing() {
+        let mut camera = Camera::new(
+            (0.0, 0.0, 1000.0),
+            cgmath::Deg(-90.0),
+            cgmath::Deg(0.0),
+            512,
+            512,
+        );
+        let perspective = Perspective::new(512, 512, cgmath::Deg(90.0), 0.1, 10000.0);
+
+        let project = |camera: &Camera, x: f64, y: f64| {
+            let clip = camera
+                .calc_view_proj(&perspective)
+                .project(Vector4::new(x, y, 0.0, 1.0));
+            Vector2::new(clip.x / clip.w, clip.y / clip.w)
+        };
+
+        // North is up, the y axis of the world points south
+        let north = project(&camera, 0.0, -100.0);
+        assert!(north.x.abs() < 1e-9 && north.y > 0.0);
+
+        // East is up if the map is rotated by 90 degrees
+        camera.set_bearing(cgmath::Deg(90.0));
+        let east = project(&camera, 100.0, 0.0);
+        assert!(east.x.abs() < 1e-9 && east.y > 0.0);
+        let north = project(&camera, 0.0, -100.0);
+        assert!(north.x < 0.0 && north.y.abs() < 1e-9);
+    }
 }
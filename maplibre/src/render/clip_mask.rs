@@ -0,0 +1,266 @@
+//! Clips the rendered layers to a polygon.
+//!
+//! The stencil values of the tile masks are below [`CLIP_MASK_STENCIL_BIT`], see
+//! [`WorldTileCoords::stencil_reference_value_3d`](crate::coords::WorldTileCoords::stencil_reference_value_3d).
+//! The clip mask sets this bit within the polygon before the tile masks are drawn. Layers are
+//! then drawn with a stencil reference which includes the bit. Therefore, they only pass the
+//! stencil test within the polygon.
+
+use std::{mem, ops::Deref};
+
+use lyon::{
+    lyon_tessellation::VertexBuffers,
+    path::Path,
+    tessellation::{BuffersBuilder, FillOptions, FillTessellator, FillVertex, TessellationError},
+};
+
+use crate::{
+    coords::{LatLon, WorldCoords, Zoom},
+    render::{
+        camera::ViewProjection,
+        resource::{RenderPipelineDescriptor, TrackedRenderPass},
+        settings::RendererSettings,
+        shaders::{ClipMaskShader, Shader, ShaderTileMetadata, Vec2f32},
+        INDEX_FORMAT,
+    },
+};
+
+/// The stencil bit which is set within the clip mask.
+pub const CLIP_MASK_STENCIL_BIT: u8 = 0x80;
+
+/// The highest latitude which can be represented in Web Mercator.
+const MAX_LATITUDE: f64 = 85.051129;
+
+pub struct ClipMaskPipeline(pub wgpu::RenderPipeline);
+impl Deref for ClipMaskPipeline {
+    type Target = wgpu::RenderPipeline;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl ClipMaskPipeline {
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        settings: &RendererSettings,
+    ) -> Self {
+        let shader = ClipMaskShader { format };
+
+        let stencil_state = wgpu::StencilFaceState {
+            compare: wgpu::CompareFunction::Always,
+            fail_op: wgpu::StencilOperation::Keep,
+            depth_fail_op: wgpu::StencilOperation::Keep,
+            pass_op: wgpu::StencilOperation::Replace,
+        };
+
+        let descriptor = RenderPipelineDescriptor {
+            label: Some("clip_mask_pipeline".into()),
+            layout: None,
+            vertex: shader.describe_vertex(),
+            fragment: shader.describe_fragment(),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                front_face: wgpu::FrontFace::Ccw,
+                strip_index_format: None,
+                cull_mode: None,
+                conservative: false,
+                unclipped_depth: false,
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: settings.depth_texture_format,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::Always,
+                stencil: wgpu::StencilState {
+                    front: stencil_state,
+                    back: stencil_state,
+                    read_mask: 0xff,
+                    // Only the clip bit is written, the tile masks use the other bits
+                    write_mask: CLIP_MASK_STENCIL_BIT as u32,
+                },
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState {
+                count: settings.msaa.samples,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+        };
+
+        ClipMaskPipeline(descriptor.initialize(device))
+    }
+}
+
+struct ClipMaskBuffers {
+    vertices: wgpu::Buffer,
+    indices: wgpu::Buffer,
+    index_count: u32,
+    transform: wgpu::Buffer,
+}
+
+/// A polygon to which all layers are clipped. The area outside the polygon stays transparent.
+#[derive(Default)]
+pub struct ClipMask {
+    /// The tessellated polygon in world coordinates at zoom level 0
+    geometry: Option<VertexBuffers<Vec2f32, u32>>,
+    buffers: Option<ClipMaskBuffers>,
+}
+
+impl ClipMask {
+    /// Sets the polygon as list of `(longitude, latitude)` pairs. The polygon is closed
+    /// automatically. Polygons with less than three points disable the clip mask.
+    pub fn set_polygon(&mut self, polygon: &[(f64, f64)]) -> Result<(), TessellationError> {
+        self.buffers = None;
+        self.geometry = None;
+
+        if polygon.len() < 3 {
+            return Ok(());
+        }
+
+        let mut builder = Path::builder();
+        for (i, (longitude, latitude)) in polygon.iter().enumerate() {
+            let world = WorldCoords::from_lat_lon(
+                LatLon::new(latitude.clamp(-MAX_LATITUDE, MAX_LATITUDE), *longitude),
+                Zoom::default(),
+            );
+            let point = lyon::geom::point(world.x as f32, world.y as f32);
+            if i == 0 {
+                builder.begin(point);
+            } else {
+                builder.line_to(point);
+            }
+        }
+        builder.close();
+
+        let mut geometry: VertexBuffers<Vec2f32, u32> = VertexBuffers::new();
+        FillTessellator::new().tessellate_path(
+            &builder.build(),
+            &FillOptions::default(),
+            &mut BuffersBuilder::new(&mut geometry, |vertex: FillVertex| {
+                vertex.position().to_array()
+            }),
+        )?;
+
+        self.geometry = Some(geometry);
+        Ok(())
+    }
+
+    pub fn clear(&mut self) {
+        self.geometry = None;
+        self.buffers = None;
+    }
+
+    /// Whether the clip mask is drawn in the next frame.
+    pub fn is_active(&self) -> bool {
+        self.buffers.is_some()
+    }
+
+    /// Returns the stencil reference for layers which are drawn with the tile mask
+    /// `tile_reference`.
+    pub fn stencil_reference(&self, tile_reference: u8) -> u32 {
+        if self.is_active() {
+            (tile_reference | CLIP_MASK_STENCIL_BIT) as u32
+        } else {
+            tile_reference as u32
+        }
+    }
+
+    /// Uploads the polygon if it changed and the transform for the current view.
+    pub fn upload(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        view_proj: &ViewProjection,
+        zoom: Zoom,
+    ) {
+        let Some(geometry) = &self.geometry else { return; };
+
+        if geometry.indices.is_empty() {
+            return;
+        }
+
+        let buffers = self.buffers.get_or_insert_with(|| {
+            let vertices = device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("clip mask vertices"),
+                size: (geometry.vertices.len() * mem::size_of::<Vec2f32>()) as wgpu::BufferAddress,
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+            queue.write_buffer(&vertices, 0, bytemuck::cast_slice(&geometry.vertices));
+
+            let indices = device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("clip mask indices"),
+                size: (geometry.indices.len() * mem::size_of::<u32>()) as wgpu::BufferAddress,
+                usage: wgpu::BufferUsages::INDEX | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+            queue.write_buffer(&indices, 0, bytemuck::cast_slice(&geometry.indices));
+
+            let transform = device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("clip mask transform"),
+                size: mem::size_of::<ShaderTileMetadata>() as wgpu::BufferAddress,
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+
+            ClipMaskBuffers {
+                vertices,
+                indices,
+                index_count: geometry.indices.len() as u32,
+                transform,
+            }
+        });
+
+        // The polygon is stored at zoom level 0 and scaled to the current zoom
+        let scale = Zoom::default().scale_delta(&zoom);
+        let metadata = ShaderTileMetadata::new(
+            view_proj
+                .to_model_view_projection(cgmath::Matrix4::from_nonuniform_scale(scale, scale, 1.0))
+                .downcast()
+                .into(),
+            1.0,
+        );
+        queue.write_buffer(&buffers.transform, 0, bytemuck::bytes_of(&metadata));
+    }
+
+    /// Sets the clip bit of the stencil buffer within the polygon.
+    pub fn draw<'a>(&'a self, pipeline: &'a ClipMaskPipeline, pass: &mut TrackedRenderPass<'a>) {
+        let Some(buffers) = &self.buffers else { return; };
+
+        pass.set_render_pipeline(pipeline);
+        pass.set_stencil_reference(CLIP_MASK_STENCIL_BIT as u32);
+        pass.set_vertex_buffer(0, buffers.vertices.slice(..));
+        pass.set_vertex_buffer(1, buffers.transform.slice(..));
+        pass.set_index_buffer(buffers.indices.slice(..), INDEX_FORMAT);
+        pass.draw_indexed(0..buffers.index_count, 0, 0..1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ClipMask, CLIP_MASK_STENCIL_BIT};
+    use crate::coords::{WorldTileCoords, ZoomLevel, MAX_ZOOM};
+
+    #[test]
+    fn test_clip_bit_is_free() {
+        let coords = WorldTileCoords::from((1, 1, ZoomLevel::new(MAX_ZOOM as u8 - 1)));
+        assert_eq!(
+            coords.stencil_reference_value_3d() & CLIP_MASK_STENCIL_BIT,
+            0
+        );
+    }
+
+    #[test]
+    fn test_set_polygon() {
+        let mut mask = ClipMask::default();
+        mask.set_polygon(&[(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0)])
+            .unwrap();
+        assert_eq!(mask.geometry.as_ref().unwrap().indices.len(), 6);
+        assert_eq!(mask.stencil_reference(5), 5);
+
+        mask.set_polygon(&[(0.0, 0.0)]).unwrap();
+        assert!(mask.geometry.is_none());
+    }
+}
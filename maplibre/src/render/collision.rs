@@ -0,0 +1,199 @@
+//! Places labels in screen space so that they do not overlap.
+//!
+//! The labels are placed greedily: Labels with a higher priority are placed first and every
+//! label whose bounding box overlaps an already placed label is skipped. The placed bounding boxes
+//! are stored in a uniform grid, so that a label is only tested against the labels in the cells
+//! it covers.
+//!
+//! The placement is independent of how the labels are drawn. The bounding boxes are expected to
+//! be in pixels, including any padding around the text or icon.
+
+use std::{cmp::Ordering, collections::HashMap};
+
+use cgmath::Point2;
+
+use crate::util::math::Aabb2;
+
+/// The default size of the grid cells in pixels
+pub const DEFAULT_COLLISION_CELL_SIZE: f64 = 64.0;
+
+/// A label which should be placed, see [`place_labels`].
+#[derive(Debug)]
+pub struct LabelCandidate {
+    pub id: u32,
+    /// The bounding box in pixels
+    pub bounding_box: Aabb2<f64>,
+    /// Labels with a higher priority are placed first.
+    pub priority: f64,
+    /// The value of `symbol-sort-key`. Labels with a lower sort key are placed first if their
+    /// priority is equal. Labels without a sort key are placed last.
+    pub sort_key: Option<f64>,
+}
+
+impl LabelCandidate {
+    pub fn new(id: u32, bounding_box: Aabb2<f64>) -> Self {
+        Self {
+            id,
+            bounding_box,
+            priority: 0.0,
+            sort_key: None,
+        }
+    }
+
+    pub fn with_priority(mut self, priority: f64) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    pub fn with_sort_key(mut self, sort_key: f64) -> Self {
+        self.sort_key = Some(sort_key);
+        self
+    }
+
+    /// The order in which labels are placed. Labels which compare as equal keep their order.
+    fn placement_order(&self, other: &Self) -> Ordering {
+        let by_sort_key = match (self.sort_key, other.sort_key) {
+            (Some(a), Some(b)) => a.partial_cmp(&b).unwrap_or(Ordering::Equal),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        };
+
+        other
+            .priority
+            .partial_cmp(&self.priority)
+            .unwrap_or(Ordering::Equal)
+            .then(by_sort_key)
+    }
+}
+
+/// The result of [`place_labels`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LabelPlacement {
+    /// The ids of the placed labels in the order in which they were placed
+    pub placed: Vec<u32>,
+    /// The ids of the skipped labels together with the id of the placed label they overlap
+    pub skipped: Vec<(u32, u32)>,
+}
+
+impl LabelPlacement {
+    pub fn is_placed(&self, id: u32) -> bool {
+        self.placed.contains(&id)
+    }
+}
+
+/// Stores the bounding boxes of placed labels in a uniform grid.
+pub struct CollisionGrid {
+    cell_size: f64,
+    cells: HashMap<(i64, i64), Vec<usize>>,
+    boxes: Vec<(u32, Aabb2<f64>)>,
+}
+
+impl CollisionGrid {
+    pub fn new(cell_size: f64) -> Self {
+        Self {
+            cell_size,
+            cells: HashMap::new(),
+            boxes: Vec::new(),
+        }
+    }
+
+    /// Returns the id of a label which overlaps `bounding_box`. Boxes which only touch do not
+    /// overlap.
+    pub fn find_collision(&self, bounding_box: &Aabb2<f64>) -> Option<u32> {
+        self.cells_of(bounding_box)
+            .filter_map(|cell| self.cells.get(&cell))
+            .flatten()
+            .map(|index| &self.boxes[*index])
+            .find(|(_, placed)| overlaps(placed, bounding_box))
+            .map(|(id, _)| *id)
+    }
+
+    pub fn insert(&mut self, id: u32, bounding_box: &Aabb2<f64>) {
+        let index = self.boxes.len();
+        for cell in self.cells_of(bounding_box).collect::<Vec<_>>() {
+            self.cells.entry(cell).or_default().push(index);
+        }
+        self.boxes
+            .push((id, Aabb2::new(bounding_box.min, bounding_box.max)));
+    }
+
+    fn cells_of(&self, bounding_box: &Aabb2<f64>) -> impl Iterator<Item = (i64, i64)> {
+        let cell = |point: Point2<f64>| {
+            (
+                (point.x / self.cell_size).floor() as i64,
+                (point.y / self.cell_size).floor() as i64,
+            )
+        };
+        let (min_x, min_y) = cell(bounding_box.min);
+        let (max_x, max_y) = cell(bounding_box.max);
+
+        (min_x..=max_x).flat_map(move |x| (min_y..=max_y).map(move |y| (x, y)))
+    }
+}
+
+impl Default for CollisionGrid {
+    fn default() -> Self {
+        Self::new(DEFAULT_COLLISION_CELL_SIZE)
+    }
+}
+
+fn overlaps(a: &Aabb2<f64>, b: &Aabb2<f64>) -> bool {
+    a.min.x < b.max.x && a.max.x > b.min.x && a.min.y < b.max.y && a.max.y > b.min.y
+}
+
+/// Places the `candidates` greedily by their priority and sort key and skips every label which
+/// overlaps an already placed label.
+pub fn place_labels(candidates: &[LabelCandidate]) -> LabelPlacement {
+    let mut ordered = candidates.iter().collect::<Vec<_>>();
+    ordered.sort_by(|a, b| a.placement_order(b));
+
+    let mut grid = CollisionGrid::default();
+    let mut placement = LabelPlacement::default();
+
+    for candidate in ordered {
+        match grid.find_collision(&candidate.bounding_box) {
+            Some(placed) => placement.skipped.push((candidate.id, placed)),
+            None => {
+                grid.insert(candidate.id, &candidate.bounding_box);
+                placement.placed.push(candidate.id);
+            }
+        }
+    }
+
+    placement
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::Point2;
+
+    use super::{place_labels, LabelCandidate};
+    use crate::util::math::Aabb2;
+
+    fn label(id: u32, x: f64, y: f64) -> LabelCandidate {
+        LabelCandidate::new(
+            id,
+            Aabb2::new(Point2::new(x, y), Point2::new(x + 100.0, y + 20.0)),
+        )
+    }
+
+    #[test]
+    fn test_place_labels() {
+        let placement = place_labels(&[
+            label(0, 0.0, 0.0),
+            // Overlaps 0, but has a higher priority
+            label(1, 50.0, 10.0).with_priority(1.0),
+            // Only touches 1, but overlaps 4
+            label(2, 150.0, 10.0),
+            // Far away in another grid cell
+            label(3, 1000.0, 1000.0),
+            // Overlaps 2, but has a lower sort key
+            label(4, 200.0, 20.0).with_sort_key(1.0),
+        ]);
+
+        assert_eq!(placement.placed, vec![1, 4, 3]);
+        assert_eq!(placement.skipped, vec![(0, 1), (2, 4)]);
+        assert!(!placement.is_placed(0));
+    }
+}
@@ -10,6 +10,11 @@ pub enum RenderError {
     Graph(#[from] RenderGraphError),
     #[error("error while requesting device")]
     RequestDevice(#[from] wgpu::RequestDeviceError),
+    #[error("the backend {requested:?} is unavailable, available backends: {available:?}")]
+    BackendUnavailable {
+        requested: wgpu::Backend,
+        available: Vec<wgpu::Backend>,
+    },
 }
 
 impl RenderError {
@@ -0,0 +1,110 @@
+//! Skips tiles in the main pass which are outside of the view.
+//!
+//! The view is approximated by the bounding box of the visible ground, see
+//! [`Camera::view_region_bounding_box`](crate::render::camera::Camera::view_region_bounding_box).
+//! Tiles whose bounding box does not intersect it can not contribute to the frame.
+
+use std::cell::Cell;
+
+use cgmath::Point2;
+
+use crate::{
+    coords::{WorldTileCoords, Zoom, TILE_SIZE},
+    util::math::Aabb2,
+    view_state::ViewState,
+};
+
+/// Counts the tiles of the last frame which were drawn or culled by the main pass.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FrustumCullingStats {
+    pub drawn_tiles: usize,
+    pub culled_tiles: usize,
+}
+
+/// The bounding box of the view in world coordinates at the current zoom.
+#[derive(Default)]
+pub struct ViewFrustum {
+    bounding_box: Option<Aabb2<f64>>,
+    zoom: Zoom,
+    stats: Cell<FrustumCullingStats>,
+}
+
+impl ViewFrustum {
+    pub fn update(&mut self, view_state: &ViewState) {
+        let inverted_view_proj = view_state.view_projection().invert();
+        self.bounding_box = view_state
+            .camera()
+            .view_region_bounding_box(&inverted_view_proj);
+        self.zoom = view_state.zoom();
+    }
+
+    /// Returns whether the tile at `coords` intersects the view. If the view can not be
+    /// determined, for example because the camera looks above the horizon, no tile is culled.
+    pub fn is_visible(&self, coords: &WorldTileCoords) -> bool {
+        let Some(view) = &self.bounding_box else { return true; };
+        let tile = tile_bounding_box(coords, self.zoom);
+
+        tile.min.x <= view.max.x
+            && tile.max.x >= view.min.x
+            && tile.min.y <= view.max.y
+            && tile.max.y >= view.min.y
+    }
+
+    pub fn stats(&self) -> FrustumCullingStats {
+        self.stats.get()
+    }
+
+    pub(crate) fn set_stats(&self, stats: FrustumCullingStats) {
+        self.stats.set(stats)
+    }
+}
+
+/// The bounding box of the tile at `coords` in world coordinates at `zoom`.
+pub fn tile_bounding_box(coords: &WorldTileCoords, zoom: Zoom) -> Aabb2<f64> {
+    let tile_scale = TILE_SIZE * Zoom::from(coords.z).scale_delta(&zoom);
+    let min = Point2::new(coords.x as f64 * tile_scale, coords.y as f64 * tile_scale);
+    Aabb2::new(min, Point2::new(min.x + tile_scale, min.y + tile_scale))
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::Point2;
+
+    use super::{tile_bounding_box, ViewFrustum};
+    use crate::{
+        coords::{WorldTileCoords, Zoom, ZoomLevel, TILE_SIZE},
+        util::math::Aabb2,
+    };
+
+    #[test]
+    fn test_is_visible() {
+        let zoom = Zoom::new(2.0);
+        let bounding_box =
+            tile_bounding_box(&WorldTileCoords::from((1, 2, ZoomLevel::new(2))), zoom);
+        assert_eq!(bounding_box.min, Point2::new(TILE_SIZE, 2.0 * TILE_SIZE));
+        assert_eq!(
+            bounding_box.max,
+            Point2::new(2.0 * TILE_SIZE, 3.0 * TILE_SIZE)
+        );
+
+        // A view which covers the tile (1, 1) at zoom 2 only
+        let frustum = ViewFrustum {
+            bounding_box: Some(Aabb2::new(
+                Point2::new(TILE_SIZE + 10.0, TILE_SIZE + 10.0),
+                Point2::new(2.0 * TILE_SIZE - 10.0, 2.0 * TILE_SIZE - 10.0),
+            )),
+            zoom,
+            ..ViewFrustum::default()
+        };
+
+        assert!(frustum.is_visible(&WorldTileCoords::from((1, 1, ZoomLevel::new(2)))));
+        assert!(!frustum.is_visible(&WorldTileCoords::from((3, 1, ZoomLevel::new(2)))));
+        // Tiles of other zoom levels are scaled to the current zoom
+        assert!(frustum.is_visible(&WorldTileCoords::from((0, 0, ZoomLevel::new(1)))));
+        assert!(!frustum.is_visible(&WorldTileCoords::from((1, 0, ZoomLevel::new(1)))));
+
+        // Without a view nothing is culled
+        let outside = WorldTileCoords::from((3, 1, ZoomLevel::new(2)));
+        assert!(ViewFrustum::default().is_visible(&outside));
+    }
+}
@@ -3,20 +3,47 @@
 //! Right now there is only one render graph. A use case for multiple render passes would be
 //! [shadows](https://www.raywenderlich.com/books/metal-by-tutorials/v2.0/chapters/14-multipass-deferred-rendering).
 
-use std::ops::Deref;
+use std::{cell::Cell, collections::HashSet, ops::Deref};
 
 use crate::{
+    coords::WorldTileCoords,
     render::{
+        clip_mask::{ClipMask, ClipMaskPipeline},
         draw_graph,
+        frustum_culling::{FrustumCullingStats, ViewFrustum},
         graph::{Node, NodeRunError, RenderContext, RenderGraphContext, SlotInfo},
         render_phase::{LayerItem, RenderPhase, TileMaskItem},
         resource::TrackedRenderPass,
+        Eventually,
         Eventually::Initialized,
         RenderResources,
     },
     tcs::world::World,
 };
 
+/// The color with which the frame is cleared before the layers are drawn. Within a clip mask
+/// the frame is always cleared with [`wgpu::Color::TRANSPARENT`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClearColor(pub wgpu::Color);
+
+impl Default for ClearColor {
+    fn default() -> Self {
+        ClearColor(wgpu::Color::WHITE)
+    }
+}
+
+/// Counts the draw calls of the last frame of the main pass.
+#[derive(Default)]
+pub struct MainPassStats {
+    draw_calls: Cell<u32>,
+}
+
+impl MainPassStats {
+    pub fn draw_calls(&self) -> u32 {
+        self.draw_calls.get()
+    }
+}
+
 pub struct MainPassNode {}
 
 impl MainPassNode {
@@ -49,11 +76,28 @@ impl Node for MainPassNode {
             return Ok(());
         };
 
+        let clip_mask = world
+            .resources
+            .get::<ClipMask>()
+            .filter(|clip_mask| clip_mask.is_active());
+
+        // Everything outside of the clip mask stays transparent
+        let clear_color = if clip_mask.is_some() {
+            wgpu::Color::TRANSPARENT
+        } else {
+            world
+                .resources
+                .get::<ClearColor>()
+                .copied()
+                .unwrap_or_default()
+                .0
+        };
+
         let color_attachment = if let Some(texture) = multisampling_texture {
             wgpu::RenderPassColorAttachment {
                 view: &texture.view,
                 ops: wgpu::Operations {
-                    load: wgpu::LoadOp::Clear(wgpu::Color::WHITE),
+                    load: wgpu::LoadOp::Clear(clear_color),
                     store: true,
                 },
                 resolve_target: Some(render_target.deref()),
@@ -62,7 +106,7 @@ impl Node for MainPassNode {
             wgpu::RenderPassColorAttachment {
                 view: render_target.deref(),
                 ops: wgpu::Operations {
-                    load: wgpu::LoadOp::Clear(wgpu::Color::WHITE),
+                    load: wgpu::LoadOp::Clear(clear_color),
                     store: true,
                 },
                 resolve_target: None,
@@ -90,20 +134,53 @@ impl Node for MainPassNode {
 
         let mut tracked_pass = TrackedRenderPass::new(render_pass);
 
+        if let (Some(clip_mask), Some(Initialized(pipeline))) = (
+            clip_mask,
+            world.resources.get::<Eventually<ClipMaskPipeline>>(),
+        ) {
+            clip_mask.draw(pipeline, &mut tracked_pass);
+        }
+
+        let view_frustum = world.resources.get::<ViewFrustum>();
+        let is_visible = |coords: &WorldTileCoords| {
+            view_frustum.map_or(true, |view_frustum| view_frustum.is_visible(coords))
+        };
+
         if let Some(mask_items) = world.resources.get::<RenderPhase<TileMaskItem>>() {
             log::trace!("RenderPhase<TileMaskItem>::size() = {}", mask_items.size());
             for item in mask_items {
-                item.draw_function.draw(&mut tracked_pass, world, item);
+                if is_visible(&item.source_shape.coords()) {
+                    item.draw_function.draw(&mut tracked_pass, world, item);
+                }
             }
         }
 
+        let mut drawn_tiles = HashSet::new();
+        let mut culled_tiles = HashSet::new();
         if let Some(layer_items) = world.resources.get::<RenderPhase<LayerItem>>() {
             log::trace!("RenderPhase<LayerItem>::size() = {}", layer_items.size());
             for item in layer_items {
-                item.draw_function.draw(&mut tracked_pass, world, item);
+                let coords = item.source_shape.coords();
+                if is_visible(&coords) {
+                    drawn_tiles.insert(coords);
+                    item.draw_function.draw(&mut tracked_pass, world, item);
+                } else {
+                    culled_tiles.insert(coords);
+                }
             }
         }
 
+        if let Some(view_frustum) = view_frustum {
+            view_frustum.set_stats(FrustumCullingStats {
+                drawn_tiles: drawn_tiles.len(),
+                culled_tiles: culled_tiles.len(),
+            });
+        }
+
+        if let Some(stats) = world.resources.get::<MainPassStats>() {
+            stats.draw_calls.set(tracked_pass.draw_calls());
+        }
+
         Ok(())
     }
 }
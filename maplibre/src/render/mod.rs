@@ -25,12 +25,15 @@ use crate::{
     kernel::Kernel,
     plugin::Plugin,
     render::{
+        clip_mask::{ClipMask, ClipMaskPipeline},
         error::RenderError,
         eventually::Eventually,
+        frustum_culling::ViewFrustum,
         graph::{EmptyNode, RenderGraph},
         main_pass::{MainPassDriverNode, MainPassNode},
         resource::{Head, Surface, Texture, TextureView},
         settings::{RendererSettings, WgpuSettings},
+        shaders::GlobalUniforms,
         systems::{
             cleanup_system::cleanup_system, resource_system::ResourceSystem,
             sort_phase_system::sort_phase_system,
@@ -57,13 +60,17 @@ pub mod shaders; // TODO: Make private
 // Public API
 pub mod builder;
 pub mod camera;
+pub mod clip_mask;
+pub mod collision;
 pub mod error;
 pub mod eventually;
+pub mod frustum_culling;
 pub mod render_commands;
 pub mod render_phase;
 pub mod settings;
 pub mod tile_view_pattern;
 
+pub use main_pass::{ClearColor, MainPassStats};
 pub use shaders::ShaderVertex;
 
 use crate::render::{
@@ -72,7 +79,7 @@ use crate::render::{
     tile_view_pattern::{ViewTileSources, WgpuTileViewPattern},
 };
 
-pub(crate) const INDEX_FORMAT: wgpu::IndexFormat = wgpu::IndexFormat::Uint32; // Must match IndexDataType
+pub(crate) const INDEX_FORMAT: wgpu::IndexFormat = wgpu::IndexFormat::Uint32; // Must match IndexDataType for buffers which are not narrowed
 
 /// The labels of the default App rendering stages.
 #[derive(Debug, Hash, PartialEq, Eq, Clone)]
@@ -160,7 +167,8 @@ impl Renderer {
     where
         MW: MapWindow + HeadedMapWindow,
     {
-        let instance = wgpu::Instance::new(wgpu_settings.backends.unwrap_or(wgpu::Backends::all()));
+        Self::check_backend(&wgpu_settings)?;
+        let instance = wgpu::Instance::new(wgpu_settings.enabled_backends());
 
         let surface: wgpu::Surface = unsafe { instance.create_surface(window.raw()) };
 
@@ -202,7 +210,8 @@ impl Renderer {
     where
         MW: MapWindow,
     {
-        let instance = wgpu::Instance::new(wgpu_settings.backends.unwrap_or(wgpu::Backends::all()));
+        Self::check_backend(&wgpu_settings)?;
+        let instance = wgpu::Instance::new(wgpu_settings.enabled_backends());
 
         let (adapter, device, queue) = Self::request_device(
             &instance,
@@ -233,6 +242,32 @@ impl Renderer {
         self.resources.surface.resize(width, height)
     }
 
+    /// Checks that the backend which is forced by [`WgpuSettings::backend`] is available.
+    fn check_backend(settings: &WgpuSettings) -> Result<(), RenderError> {
+        let Some(requested) = settings.backend else { return Ok(()); };
+
+        // Adapters can not be enumerated on the web, requesting the adapter fails instead
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let instance = wgpu::Instance::new(wgpu::Backends::all());
+            let mut available = instance
+                .enumerate_adapters(wgpu::Backends::all())
+                .map(|adapter| adapter.get_info().backend)
+                .collect::<Vec<_>>();
+            available.sort_by_key(|backend| *backend as u8);
+            available.dedup();
+
+            if !available.contains(&requested) {
+                return Err(RenderError::BackendUnavailable {
+                    requested,
+                    available,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
     /// Requests a device
     async fn request_device(
         instance: &wgpu::Instance,
@@ -554,6 +589,15 @@ impl<E: Environment> Plugin<E> for RenderPlugin {
         resources.init::<ViewTileSources>();
         // masks
         resources.insert(Eventually::<MaskPipeline>::Uninitialized);
+        // clip mask
+        resources.init::<ClipMask>();
+        resources.insert(Eventually::<ClipMaskPipeline>::Uninitialized);
+        // frustum culling
+        resources.init::<ViewFrustum>();
+        // main pass
+        resources.init::<ClearColor>();
+        resources.init::<GlobalUniforms>();
+        resources.init::<MainPassStats>();
 
         schedule.add_stage(RenderStageLabel::Extract, SystemStage::default());
         schedule.add_stage(
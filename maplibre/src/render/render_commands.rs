@@ -36,6 +36,9 @@ impl RenderCommand<TileMaskItem> for DrawMask {
             .get::<Eventually<WgpuTileViewPattern>>() else { return RenderCommandResult::Failure; };
 
         let tile_mask = &item.source_shape;
+        if !tile_mask.has_own_mask() {
+            return RenderCommandResult::Success;
+        }
 
         // Draw mask with stencil value of e.g. parent
         let reference = tile_mask.coords().stencil_reference_value_3d() as u32;
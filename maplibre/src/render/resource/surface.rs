@@ -33,6 +33,28 @@ impl BufferDimensions {
             padded_bytes_per_row: NonZeroU32::new(padded_bytes_per_row).expect("can not be zero"),
         }
     }
+
+    /// See [`BufferedTextureHead::unpad_rows`].
+    #[cfg(feature = "headless")]
+    fn unpad_rows(&self, padded_buffer: &[u8], flip_y: bool) -> Vec<u8> {
+        let padded_bytes_per_row = self.padded_bytes_per_row.get() as usize;
+        let unpadded_bytes_per_row = self.unpadded_bytes_per_row.get() as usize;
+
+        let mut rgba = Vec::with_capacity(unpadded_bytes_per_row * self.height as usize);
+        let rows = padded_buffer.chunks(padded_bytes_per_row);
+
+        if flip_y {
+            for row in rows.rev() {
+                rgba.extend_from_slice(&row[..unpadded_bytes_per_row]);
+            }
+        } else {
+            for row in rows {
+                rgba.extend_from_slice(&row[..unpadded_bytes_per_row]);
+            }
+        }
+
+        rgba
+    }
 }
 
 pub struct WindowHead {
@@ -98,8 +120,37 @@ impl BufferedTextureHead {
         self.output_buffer.unmap();
     }
 
-    pub fn write_png<'a>(&self, padded_buffer: &wgpu::BufferView<'a>, png_output_path: &str) {
-        use std::{fs::File, io::Write};
+    /// Copies the rows of the mapped `padded_buffer` into a tightly packed RGBA buffer, dropping
+    /// the row padding which is required by wgpu.
+    ///
+    /// Without `flip_y` the first row is the top of the image, which matches the top-left origin
+    /// of the `image` crate. With `flip_y` the order of the rows is reversed.
+    pub fn unpad_rows(&self, padded_buffer: &[u8], flip_y: bool) -> Vec<u8> {
+        self.buffer_dimensions.unpad_rows(padded_buffer, flip_y)
+    }
+
+    /// Maps the output buffer and reads back the last copied frame as RGBA.
+    /// See [`BufferedTextureHead::unpad_rows`] for the meaning of `flip_y`.
+    pub fn read_rgba(&self, device: &wgpu::Device, flip_y: bool) -> Vec<u8> {
+        let buffer_slice = self.map_async(device);
+        let padded_buffer = buffer_slice.get_mapped_range();
+        let rgba = self.unpad_rows(&padded_buffer, flip_y);
+
+        // With the current interface, we have to make sure all mapped views are
+        // dropped before we unmap the buffer.
+        drop(padded_buffer);
+        self.unmap();
+
+        rgba
+    }
+
+    pub fn write_png<'a>(
+        &self,
+        padded_buffer: &wgpu::BufferView<'a>,
+        png_output_path: &str,
+        flip_y: bool,
+    ) {
+        use std::fs::File;
         let mut png_encoder = png::Encoder::new(
             File::create(png_output_path).unwrap(), // TODO: Remove unwrap
             self.buffer_dimensions.width as u32,
@@ -107,23 +158,20 @@ impl BufferedTextureHead {
         );
         png_encoder.set_depth(png::BitDepth::Eight);
         png_encoder.set_color(png::ColorType::Rgba);
-        let mut png_writer = png_encoder
-            .write_header()
-            .unwrap() // TODO: Remove unwrap
-            .into_stream_writer_with_size(
-                self.buffer_dimensions.unpadded_bytes_per_row.get() as usize
-            )
-            .unwrap(); // TODO: Remove unwrap
+        let mut png_writer = png_encoder.write_header().unwrap(); // TODO: Remove unwrap
 
         // from the padded_buffer we write just the unpadded bytes into the image
-        for chunk in
-            padded_buffer.chunks(self.buffer_dimensions.padded_bytes_per_row.get() as usize)
-        {
-            png_writer
-                .write_all(&chunk[..self.buffer_dimensions.unpadded_bytes_per_row.get() as usize])
-                .unwrap(); // TODO: Remove unwrap
-        }
-        png_writer.finish().unwrap(); // TODO: Remove unwrap
+        png_writer
+            .write_image_data(&self.unpad_rows(padded_buffer, flip_y))
+            .unwrap(); // TODO: Remove unwrap
+    }
+
+    pub fn width(&self) -> u32 {
+        self.buffer_dimensions.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.buffer_dimensions.height
     }
 
     pub fn copy_texture(&self) -> wgpu::ImageCopyTexture<'_> {
@@ -316,3 +364,25 @@ impl HasChanged for WindowHead {
         self.size.width() != criteria.0 || self.size.height() != criteria.1
     }
 }
+
+#[cfg(all(test, feature = "headless"))]
+mod tests {
+    use super::BufferDimensions;
+    use crate::window::WindowSize;
+
+    #[test]
+    fn test_unpad_rows() {
+        // Rows of 3 pixels are padded to 256 bytes
+        let dimensions = BufferDimensions::new(WindowSize::new(3, 2).unwrap());
+        assert_eq!(dimensions.padded_bytes_per_row.get(), 256);
+
+        let mut padded = vec![0xff; 2 * 256];
+        padded[..12].fill(1);
+        padded[256..268].fill(2);
+
+        let rows = |rgba: Vec<u8>| rgba.chunks(12).map(|row| row[0]).collect::<Vec<_>>();
+        assert_eq!(dimensions.unpad_rows(&padded, false).len(), 2 * 12);
+        assert_eq!(rows(dimensions.unpad_rows(&padded, false)), vec![1, 2]);
+        assert_eq!(rows(dimensions.unpad_rows(&padded, true)), vec![2, 1]);
+    }
+}
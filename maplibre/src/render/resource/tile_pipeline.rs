@@ -3,6 +3,7 @@
 use std::borrow::Cow;
 
 use crate::render::{
+    clip_mask::CLIP_MASK_STENCIL_BIT,
     resource::{FragmentState, RenderPipeline, RenderPipelineDescriptor, VertexState},
     settings::RendererSettings,
 };
@@ -121,12 +122,19 @@ impl RenderPipeline for TilePipeline {
                 Some(wgpu::DepthStencilState {
                     format: self.settings.depth_texture_format,
                     depth_write_enabled: !self.update_stencil,
-                    depth_compare: wgpu::CompareFunction::Greater,
+                    // Cross-faded tiles are drawn on top of their parent at the same depth
+                    depth_compare: if self.settings.tile_fade.enabled && !self.update_stencil {
+                        wgpu::CompareFunction::GreaterEqual
+                    } else {
+                        wgpu::CompareFunction::Greater
+                    },
                     stencil: wgpu::StencilState {
                         front: stencil_state,
                         back: stencil_state,
                         read_mask: 0xff, // Applied to stencil values being read from the stencil buffer
-                        write_mask: 0xff, // Applied to fragment stencil values before being written to  the stencil buffer
+                        // Applied to fragment stencil values before being written to  the stencil buffer.
+                        // The tile masks must not overwrite the clip mask.
+                        write_mask: !CLIP_MASK_STENCIL_BIT as u32,
                     },
                     bias: wgpu::DepthBiasState::default(),
                 })
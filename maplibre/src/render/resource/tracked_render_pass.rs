@@ -9,12 +9,21 @@ use log::trace;
 /// After all requirements are specified, draw calls can be issued.
 pub struct TrackedRenderPass<'a> {
     pass: wgpu::RenderPass<'a>,
+    draw_calls: u32,
 }
 
 impl<'a> TrackedRenderPass<'a> {
     /// Tracks the supplied render pass.
     pub fn new(pass: wgpu::RenderPass<'a>) -> Self {
-        Self { pass }
+        Self {
+            pass,
+            draw_calls: 0,
+        }
+    }
+
+    /// The number of draw calls which were issued so far.
+    pub fn draw_calls(&self) -> u32 {
+        self.draw_calls
     }
 
     /// Sets the active [`RenderPipeline`].
@@ -65,6 +74,7 @@ impl<'a> TrackedRenderPass<'a> {
     /// The active vertex buffer(s) can be set with [`TrackedRenderPass::set_vertex_buffer`].
     pub fn draw(&mut self, vertices: Range<u32>, instances: Range<u32>) {
         trace!("draw: {:?} {:?}", vertices, instances);
+        self.draw_calls += 1;
         self.pass.draw(vertices, instances);
     }
 
@@ -79,6 +89,7 @@ impl<'a> TrackedRenderPass<'a> {
             base_vertex,
             instances
         );
+        self.draw_calls += 1;
         self.pass.draw_indexed(indices, base_vertex, instances);
     }
 
@@ -100,6 +111,7 @@ impl<'a> TrackedRenderPass<'a> {
     /// ```
     pub fn draw_indirect(&mut self, indirect_buffer: &'a wgpu::Buffer, indirect_offset: u64) {
         trace!("draw indirect: {:?} {}", indirect_buffer, indirect_offset);
+        self.draw_calls += 1;
         self.pass.draw_indirect(indirect_buffer, indirect_offset);
     }
 
@@ -132,6 +144,7 @@ impl<'a> TrackedRenderPass<'a> {
             indirect_buffer,
             indirect_offset
         );
+        self.draw_calls += 1;
         self.pass
             .draw_indexed_indirect(indirect_buffer, indirect_offset);
     }
@@ -1,9 +1,14 @@
 //! Settings for the renderer
 
-use std::borrow::Cow;
+use std::{borrow::Cow, num::NonZeroU8};
 
 use wgpu::PresentMode;
-pub use wgpu::{Backends, Features, Limits, PowerPreference, TextureFormat};
+pub use wgpu::{Backend, Backends, Features, FilterMode, Limits, PowerPreference, TextureFormat};
+
+use crate::coords::Zoom;
+
+/// The seed which is used if no other seed is configured, see [`RendererSettings::render_seed`]
+pub const DEFAULT_RENDER_SEED: u64 = 0x5eed;
 
 /// Provides configuration for renderer initialization. Use [`Device::features`](crate::renderer::Device::features),
 /// [`Device::limits`](crate::renderer::Device::limits), and the [`WgpuAdapterInfo`](crate::render_resource::WgpuAdapterInfo)
@@ -12,6 +17,10 @@ pub use wgpu::{Backends, Features, Limits, PowerPreference, TextureFormat};
 pub struct WgpuSettings {
     pub device_label: Option<Cow<'static, str>>,
     pub backends: Option<Backends>,
+    /// Forces a single backend, for example [`Backend::Gl`] in order to match the WebGL code path.
+    /// Takes precedence over `backends`. If the backend is unavailable, initializing the renderer
+    /// fails instead of falling back to another backend.
+    pub backend: Option<Backend>,
     pub power_preference: PowerPreference,
     /// The features to ensure are enabled regardless of what the adapter/backend supports.
     /// Setting these explicitly may cause renderer initialization to fail.
@@ -58,6 +67,7 @@ impl Default for WgpuSettings {
         Self {
             device_label: Default::default(),
             backends,
+            backend: None,
             power_preference: PowerPreference::HighPerformance,
             features,
             disabled_features: None,
@@ -68,6 +78,24 @@ impl Default for WgpuSettings {
     }
 }
 
+impl WgpuSettings {
+    /// The backends from which the adapter is chosen.
+    pub fn enabled_backends(&self) -> Backends {
+        match self.backend {
+            Some(backend) => match backend {
+                Backend::Empty => Backends::empty(),
+                Backend::Vulkan => Backends::VULKAN,
+                Backend::Metal => Backends::METAL,
+                Backend::Dx12 => Backends::DX12,
+                Backend::Dx11 => Backends::DX11,
+                Backend::Gl => Backends::GL,
+                Backend::BrowserWebGpu => Backends::BROWSER_WEBGPU,
+            },
+            None => self.backends.unwrap_or(Backends::all()),
+        }
+    }
+}
+
 #[derive(Clone)]
 pub enum SurfaceType {
     Headless,
@@ -100,6 +128,102 @@ impl Default for Msaa {
     }
 }
 
+/// The highest anisotropy level which is supported by WebGPU.
+const MAX_ANISOTROPY: u8 = 16;
+
+/// Configuration of the sampler which is used for raster tiles.
+///
+/// Raster tiles have no mipmaps, so there is no mipmap filter and anisotropic filtering always
+/// samples the full resolution of a tile.
+#[derive(Clone, Copy, Debug)]
+pub struct RasterSamplerSettings {
+    pub mag_filter: FilterMode,
+    pub min_filter: FilterMode,
+    /// The level of anisotropic filtering. Values of 0 and 1 disable anisotropic filtering.
+    ///
+    /// The value is clamped to the highest supported power of two, which is at most 16.
+    /// Anisotropic filtering is only applied if both filters are [`FilterMode::Linear`] and the
+    /// device supports it.
+    pub anisotropy: u8,
+}
+
+impl RasterSamplerSettings {
+    fn anisotropy_clamp(&self, anisotropy_supported: bool) -> Option<NonZeroU8> {
+        let all_linear = [self.mag_filter, self.min_filter]
+            .iter()
+            .all(|filter| *filter == FilterMode::Linear);
+
+        if !anisotropy_supported || !all_linear || self.anisotropy <= 1 {
+            return None;
+        }
+
+        let clamped = self.anisotropy.min(MAX_ANISOTROPY);
+        // Round down to the next power of two
+        NonZeroU8::new(1 << (7 - clamped.leading_zeros()))
+    }
+
+    /// Creates the sampler descriptor for these settings. Use
+    /// [`wgpu::DownlevelFlags::ANISOTROPIC_FILTERING`] of the adapter to determine whether
+    /// anisotropic filtering is supported.
+    pub fn describe(&self, anisotropy_supported: bool) -> wgpu::SamplerDescriptor<'static> {
+        wgpu::SamplerDescriptor {
+            label: Some("raster sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: self.mag_filter,
+            min_filter: self.min_filter,
+            // Without mipmaps the mipmap filter has no effect, but anisotropic filtering requires
+            // it to be linear
+            mipmap_filter: FilterMode::Linear,
+            anisotropy_clamp: self.anisotropy_clamp(anisotropy_supported),
+            ..Default::default()
+        }
+    }
+}
+
+impl Default for RasterSamplerSettings {
+    fn default() -> Self {
+        Self {
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            anisotropy: 1,
+        }
+    }
+}
+
+/// Cross-fades vector tiles with their parent tile while zooming, instead of swapping the tiles
+/// abruptly when the zoom crosses an integer zoom level. Raster tiles are not cross-faded.
+#[derive(Clone, Copy, Debug)]
+pub struct TileFadeSettings {
+    pub enabled: bool,
+    /// The number of zoom levels after an integer zoom level, over which a tile fades in while
+    /// its parent fades out. For example with `0.5` a tile is fully opaque from the zoom `3.5`
+    /// until the zoom `4.0`.
+    pub fade_duration: f64,
+}
+
+impl TileFadeSettings {
+    /// Returns the opacity of the tiles of the visible zoom level at `zoom`. Their parents are
+    /// drawn with the remaining opacity.
+    pub fn opacity(&self, zoom: Zoom) -> f64 {
+        if !self.enabled || self.fade_duration <= 0.0 {
+            return 1.0;
+        }
+
+        (zoom.fract() / self.fade_duration).clamp(0.0, 1.0)
+    }
+}
+
+impl Default for TileFadeSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            fade_duration: 0.5,
+        }
+    }
+}
+
 #[derive(Clone, Copy)]
 pub struct RendererSettings {
     pub msaa: Msaa,
@@ -108,6 +232,13 @@ pub struct RendererSettings {
     pub depth_texture_format: TextureFormat,
     /// Present mode for surfaces if a surface is used.
     pub present_mode: PresentMode,
+    /// Sampling of raster tiles
+    pub raster_sampler: RasterSamplerSettings,
+    pub tile_fade: TileFadeSettings,
+    /// Seeds the randomness of rendering, so that repeated renders of the same input are
+    /// identical. Rendering is not randomized yet, so the seed does not affect the output. A
+    /// subsystem which introduces randomness has to derive it from this seed.
+    pub render_seed: u64,
 }
 
 impl Default for RendererSettings {
@@ -118,6 +249,55 @@ impl Default for RendererSettings {
 
             depth_texture_format: TextureFormat::Depth24PlusStencil8,
             present_mode: PresentMode::AutoVsync,
+            raster_sampler: RasterSamplerSettings::default(),
+            tile_fade: TileFadeSettings::default(),
+            render_seed: DEFAULT_RENDER_SEED,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{FilterMode, RasterSamplerSettings, TileFadeSettings};
+    use crate::coords::Zoom;
+
+    #[test]
+    fn test_anisotropy_clamp() {
+        let sampler = |anisotropy| RasterSamplerSettings {
+            anisotropy,
+            ..RasterSamplerSettings::default()
+        };
+        let clamp = |settings: RasterSamplerSettings, supported| {
+            settings
+                .anisotropy_clamp(supported)
+                .map(|clamp| clamp.get())
+        };
+
+        assert_eq!(clamp(sampler(0), true), None);
+        assert_eq!(clamp(sampler(1), true), None);
+        // Rounded down to a power of two
+        assert_eq!(clamp(sampler(3), true), Some(2));
+        assert_eq!(clamp(sampler(16), true), Some(16));
+        assert_eq!(clamp(sampler(255), true), Some(16));
+
+        assert_eq!(clamp(sampler(8), false), None);
+        let nearest = RasterSamplerSettings {
+            mag_filter: FilterMode::Nearest,
+            ..sampler(8)
+        };
+        assert_eq!(clamp(nearest, true), None);
+    }
+
+    #[test]
+    fn test_tile_fade_opacity() {
+        let fade = TileFadeSettings {
+            enabled: true,
+            fade_duration: 0.5,
+        };
+        assert_eq!(fade.opacity(Zoom::new(3.0)), 0.0);
+        assert_eq!(fade.opacity(Zoom::new(3.25)), 0.5);
+        assert_eq!(fade.opacity(Zoom::new(3.75)), 1.0);
+
+        assert_eq!(TileFadeSettings::default().opacity(Zoom::new(3.25)), 1.0);
+    }
+}
@@ -0,0 +1,21 @@
+struct VertexOutput {
+    @location(0) v_color: vec4<f32>,
+    @builtin(position) position: vec4<f32>,
+};
+
+var<private> DEBUG_COLOR: vec4<f32> = vec4<f32>(0.0, 0.0, 1.0, 1.0);
+
+@vertex
+fn main(
+    @location(0) position: vec2<f32>,
+    @location(4) translate1: vec4<f32>,
+    @location(5) translate2: vec4<f32>,
+    @location(6) translate3: vec4<f32>,
+    @location(7) translate4: vec4<f32>,
+) -> VertexOutput {
+    var final_position = mat4x4<f32>(translate1, translate2, translate3, translate4) * vec4<f32>(position, 0.0, 1.0);
+    // The clip mask is drawn before anything else and does not write depth
+    final_position.z = 1.0;
+
+    return VertexOutput(DEBUG_COLOR, final_position);
+}
@@ -92,8 +92,80 @@ impl Shader for TileMaskShader {
     }
 }
 
+/// Writes the clip mask polygon into the stencil buffer, see
+/// [`ClipMask`](crate::render::clip_mask::ClipMask).
+pub struct ClipMaskShader {
+    pub format: wgpu::TextureFormat,
+}
+
+impl Shader for ClipMaskShader {
+    fn describe_vertex(&self) -> VertexState {
+        VertexState {
+            source: include_str!("clip_mask.vertex.wgsl"),
+            entry_point: "main",
+            buffers: vec![
+                // vertex data
+                VertexBufferLayout {
+                    array_stride: std::mem::size_of::<Vec2f32>() as u64,
+                    step_mode: wgpu::VertexStepMode::Vertex,
+                    attributes: vec![
+                        // position
+                        wgpu::VertexAttribute {
+                            offset: 0,
+                            format: wgpu::VertexFormat::Float32x2,
+                            shader_location: 0,
+                        },
+                    ],
+                },
+                // transform
+                VertexBufferLayout {
+                    array_stride: std::mem::size_of::<ShaderTileMetadata>() as u64,
+                    step_mode: wgpu::VertexStepMode::Instance,
+                    attributes: vec![
+                        wgpu::VertexAttribute {
+                            offset: 0,
+                            format: wgpu::VertexFormat::Float32x4,
+                            shader_location: 4,
+                        },
+                        wgpu::VertexAttribute {
+                            offset: 1 * wgpu::VertexFormat::Float32x4.size(),
+                            format: wgpu::VertexFormat::Float32x4,
+                            shader_location: 5,
+                        },
+                        wgpu::VertexAttribute {
+                            offset: 2 * wgpu::VertexFormat::Float32x4.size(),
+                            format: wgpu::VertexFormat::Float32x4,
+                            shader_location: 6,
+                        },
+                        wgpu::VertexAttribute {
+                            offset: 3 * wgpu::VertexFormat::Float32x4.size(),
+                            format: wgpu::VertexFormat::Float32x4,
+                            shader_location: 7,
+                        },
+                    ],
+                },
+            ],
+        }
+    }
+
+    fn describe_fragment(&self) -> FragmentState {
+        FragmentState {
+            source: include_str!("basic.fragment.wgsl"),
+            entry_point: "main",
+            targets: vec![Some(wgpu::ColorTargetState {
+                format: self.format,
+                blend: None,
+                write_mask: wgpu::ColorWrites::empty(),
+            })],
+        }
+    }
+}
+
 pub struct VectorTileShader {
     pub format: wgpu::TextureFormat,
+    /// Blends the tiles with the framebuffer according to their opacity. Required for
+    /// cross-fading tiles.
+    pub alpha_blending: bool,
 }
 
 impl Shader for VectorTileShader {
@@ -153,6 +225,13 @@ impl Shader for VectorTileShader {
                             format: wgpu::VertexFormat::Float32,
                             shader_location: 9,
                         },
+                        // opacity
+                        wgpu::VertexAttribute {
+                            offset: 4 * wgpu::VertexFormat::Float32x4.size()
+                                + wgpu::VertexFormat::Float32.size(),
+                            format: wgpu::VertexFormat::Float32,
+                            shader_location: 11,
+                        },
                     ],
                 },
                 // layer metadata
@@ -191,7 +270,11 @@ impl Shader for VectorTileShader {
             entry_point: "main",
             targets: vec![Some(wgpu::ColorTargetState {
                 format: self.format,
-                blend: None,
+                blend: if self.alpha_blending {
+                    Some(wgpu::BlendState::ALPHA_BLENDING)
+                } else {
+                    None
+                },
                 write_mask: wgpu::ColorWrites::ALL,
             })],
         }
@@ -237,6 +320,27 @@ impl ShaderGlobals {
     }
 }
 
+/// Uniforms which are read by the vector tile shader. Change the resource at runtime to apply
+/// effects to the whole map without editing the shaders.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Pod, Zeroable)]
+pub struct GlobalUniforms {
+    /// Multiplied with the color of every feature
+    pub tint: Vec4f32,
+    /// Values which are available to shaders as `uniforms.extra`, but are unused by the default
+    /// shaders
+    pub extra: Vec4f32,
+}
+
+impl Default for GlobalUniforms {
+    fn default() -> Self {
+        Self {
+            tint: [1.0; 4],
+            extra: [0.0; 4],
+        }
+    }
+}
+
 #[repr(C)]
 #[derive(Copy, Clone, Pod, Zeroable)]
 pub struct ShaderVertex {
@@ -279,6 +383,9 @@ impl ShaderLayerMetadata {
 pub struct ShaderTileMetadata {
     pub transform: Mat4x4f32,
     pub zoom_factor: f32,
+    /// Opacity of the tile while it is cross-faded, see
+    /// [`TileFadeSettings`](crate::render::settings::TileFadeSettings)
+    pub opacity: f32,
 }
 
 impl ShaderTileMetadata {
@@ -286,6 +393,7 @@ impl ShaderTileMetadata {
         Self {
             transform,
             zoom_factor,
+            opacity: 1.0,
         }
     }
 }
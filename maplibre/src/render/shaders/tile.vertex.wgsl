@@ -1,13 +1,9 @@
-struct ShaderCamera {
-    view_proj: mat4x4<f32>,
-    view_position: vec4<f32>,
+struct GlobalUniforms {
+    tint: vec4<f32>,
+    extra: vec4<f32>,
 };
 
-struct ShaderGlobals {
-    camera: ShaderCamera,
-};
-
-@group(0) @binding(0) var<uniform> globals: ShaderGlobals;
+@group(0) @binding(0) var<uniform> uniforms: GlobalUniforms;
 
 struct VertexOutput {
     @location(0)  v_color: vec4<f32>,
@@ -25,6 +21,7 @@ fn main(
     @location(8) color: vec4<f32>,
     @location(9) zoom_factor: f32,
     @location(10) z_index: f32,
+    @location(11) opacity: f32,
     @builtin(instance_index) instance_idx: u32 // instance_index is used when we have multiple instances of the same "object"
 ) -> VertexOutput {
     let z = 0.0;
@@ -39,5 +36,6 @@ fn main(
     // FIXME: how to fix z-fighting?
     position.z = z_index;
 
-    return VertexOutput(color, position);
+    let tinted = color * uniforms.tint;
+    return VertexOutput(vec4<f32>(tinted.rgb, tinted.a * opacity), position);
 }
@@ -5,6 +5,7 @@ use std::{borrow::Cow, mem};
 use crate::{
     context::MapContext,
     render::{
+        clip_mask::ClipMaskPipeline,
         eventually::Eventually,
         resource::{BackingBufferDescriptor, RenderPipeline, Texture, TilePipeline},
         shaders,
@@ -39,10 +40,12 @@ impl System for ResourceSystem {
     ) {
         let Some((
             tile_view_pattern,
-            mask_pipeline
+            mask_pipeline,
+            clip_mask_pipeline
         )) = world.resources.query_mut::<(
             &mut Eventually<WgpuTileViewPattern>,
             &mut Eventually<MaskPipeline>,
+            &mut Eventually<ClipMaskPipeline>,
         )>() else { return; };
 
         let surface = &mut state.surface;
@@ -127,5 +130,8 @@ impl System for ResourceSystem {
             .initialize(device);
             MaskPipeline(pipeline)
         });
+
+        clip_mask_pipeline
+            .initialize(|| ClipMaskPipeline::new(device, surface.surface_format(), settings));
     }
 }
@@ -4,13 +4,18 @@ use crate::{
     context::MapContext,
     render::{
         eventually::{Eventually, Eventually::Initialized},
+        settings::TileFadeSettings,
         tile_view_pattern::{ViewTileSources, WgpuTileViewPattern},
     },
+    vector::picking::VectorRenderMode,
 };
 
 pub fn tile_view_pattern_system(
     MapContext {
-        view_state, world, ..
+        view_state,
+        world,
+        renderer,
+        ..
     }: &mut MapContext,
 ) {
     let Some((
@@ -25,8 +30,17 @@ pub fn tile_view_pattern_system(
     if let Some(view_region) = &view_region {
         let zoom = view_state.zoom();
 
+        // Feature ids are encoded in the colors, so they must not be blended
+        let fade = match world.resources.get::<VectorRenderMode>() {
+            Some(VectorRenderMode::FeatureIds) => TileFadeSettings {
+                enabled: false,
+                ..renderer.settings.tile_fade
+            },
+            _ => renderer.settings.tile_fade,
+        };
+
         let view_tiles =
-            tile_view_pattern.generate_pattern(view_region, view_tile_sources, zoom, world);
+            tile_view_pattern.generate_pattern(view_region, view_tile_sources, zoom, fade, world);
 
         // TODO: Can we &mut borrow initially somehow instead of here?
         let Some(Initialized(tile_view_pattern)) = world
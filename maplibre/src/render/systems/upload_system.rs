@@ -2,7 +2,9 @@
 use crate::{
     context::MapContext,
     render::{
+        clip_mask::ClipMask,
         eventually::{Eventually, Eventually::Initialized},
+        frustum_culling::ViewFrustum,
         tile_view_pattern::WgpuTileViewPattern,
         Renderer,
     },
@@ -17,12 +19,18 @@ pub fn upload_system(
         ..
     }: &mut MapContext,
 ) {
-    let Some(
-        Initialized(tile_view_pattern)
-    ) = world.resources.query_mut::<
-        &mut Eventually<WgpuTileViewPattern>
-    >() else { return; };
+    let Some((
+        Initialized(tile_view_pattern),
+        clip_mask,
+        view_frustum
+    )) = world.resources.query_mut::<(
+        &mut Eventually<WgpuTileViewPattern>,
+        &mut ClipMask,
+        &mut ViewFrustum,
+    )>() else { return; };
 
     let view_proj = view_state.view_projection();
     tile_view_pattern.upload_pattern(queue, &view_proj);
+    clip_mask.upload(device, queue, &view_proj, view_state.zoom());
+    view_frustum.update(view_state);
 }
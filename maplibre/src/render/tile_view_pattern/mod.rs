@@ -29,6 +29,12 @@ pub enum SourceShapes {
     /// Source and target are equal, so no need to differentiate. We render the `source` shape
     /// exactly at the `target`.
     SourceEqTarget(TileShape),
+    /// The `target` fades in on top of its `parent` which fades out, see
+    /// [`TileFadeSettings`](crate::render::settings::TileFadeSettings).
+    CrossFade {
+        parent: TileShape,
+        target: TileShape,
+    },
     /// No data available so nothing to render
     None,
 }
@@ -57,6 +63,10 @@ impl ViewTile {
                 }
             }
             SourceShapes::SourceEqTarget(source_shape) => callback(source_shape),
+            SourceShapes::CrossFade { parent, target } => {
+                callback(parent);
+                callback(target)
+            }
             SourceShapes::None => {}
         }
     }
@@ -72,6 +82,10 @@ pub struct TileShape {
     zoom_factor: f64,
     transform: Matrix4<f64>,
 
+    opacity: f64,
+    /// The tile whose mask clips this shape
+    mask_coords: WorldTileCoords,
+
     buffer_range: Option<Range<wgpu::BufferAddress>>,
 }
 
@@ -81,10 +95,21 @@ impl TileShape {
             coords,
             zoom_factor: zoom.scale_to_tile(&coords),
             transform: coords.transform_for_zoom(zoom),
+            opacity: 1.0,
+            mask_coords: coords,
             buffer_range: None,
         }
     }
 
+    /// Creates a shape which is drawn with `opacity` and clipped by the mask of `target`.
+    fn faded(coords: WorldTileCoords, target: WorldTileCoords, zoom: Zoom, opacity: f64) -> Self {
+        Self {
+            opacity,
+            mask_coords: target,
+            ..Self::new(coords, zoom)
+        }
+    }
+
     fn set_buffer_range(&mut self, index: u64) {
         const STRIDE: u64 = size_of::<ShaderTileMetadata>() as u64;
         self.buffer_range = Some(index * STRIDE..(index + 1) * STRIDE);
@@ -97,6 +122,21 @@ impl TileShape {
     pub fn coords(&self) -> WorldTileCoords {
         self.coords
     }
+
+    pub fn opacity(&self) -> f64 {
+        self.opacity
+    }
+
+    /// The tile whose stencil reference value is used when drawing this shape
+    pub fn mask_coords(&self) -> WorldTileCoords {
+        self.mask_coords
+    }
+
+    /// Whether a mask is drawn for this shape. Shapes which are clipped by the mask of another
+    /// tile, like the parent of a cross-faded tile, must not draw their own.
+    pub fn has_own_mask(&self) -> bool {
+        self.mask_coords == self.coords
+    }
 }
 
 pub trait HasTile {
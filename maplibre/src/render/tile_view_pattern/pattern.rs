@@ -1,10 +1,11 @@
 use std::marker::PhantomData;
 
 use crate::{
-    coords::{ViewRegion, Zoom},
+    coords::{ViewRegion, WorldTileCoords, Zoom},
     render::{
         camera::ViewProjection,
         resource::{BackingBufferDescriptor, Queue},
+        settings::TileFadeSettings,
         shaders::ShaderTileMetadata,
         tile_view_pattern::{HasTile, SourceShapes, TileShape, ViewTile},
     },
@@ -54,9 +55,11 @@ impl<Q: Queue<B>, B> TileViewPattern<Q, B> {
         view_region: &ViewRegion,
         container: &T,
         zoom: Zoom,
+        fade: TileFadeSettings,
         world: &World,
     ) -> Vec<ViewTile> {
         let mut view_tiles = Vec::with_capacity(self.view_tiles.len());
+        let opacity = fade.opacity(zoom);
 
         for coords in view_region.iter() {
             if coords.build_quad_key().is_none() {
@@ -65,7 +68,7 @@ impl<Q: Queue<B>, B> TileViewPattern<Q, B> {
 
             let source_shapes = {
                 if container.has_tile(coords, world) {
-                    SourceShapes::SourceEqTarget(TileShape::new(coords, zoom))
+                    available_shapes(coords, container, world, zoom, opacity)
                 } else if let Some(parent_coords) = container.get_available_parent(coords, world) {
                     log::debug!("Could not find data at {coords}. Falling back to {parent_coords}");
 
@@ -125,6 +128,7 @@ impl<Q: Queue<B>, B> TileViewPattern<Q, B> {
                     .downcast()
                     .into(), // TODO: move this calculation to update() fn above
                 zoom_factor: shape.zoom_factor as f32,
+                opacity: shape.opacity as f32,
             });
         };
 
@@ -139,6 +143,10 @@ impl<Q: Queue<B>, B> TileViewPattern<Q, B> {
                     }
                 }
                 SourceShapes::SourceEqTarget(source_shape) => add_to_buffer(source_shape),
+                SourceShapes::CrossFade { parent, target } => {
+                    add_to_buffer(parent);
+                    add_to_buffer(target);
+                }
                 SourceShapes::None => {}
             }
         }
@@ -152,3 +160,85 @@ impl<Q: Queue<B>, B> TileViewPattern<Q, B> {
         queue.write_buffer(&self.view_tiles_buffer.inner, 0, raw_buffer);
     }
 }
+
+/// The shapes of the tile at `coords`, which is available in the `container`. While the `opacity`
+/// of the tile is below 1, it is cross-faded with its closest available parent.
+fn available_shapes<T: HasTile>(
+    coords: WorldTileCoords,
+    container: &T,
+    world: &World,
+    zoom: Zoom,
+    opacity: f64,
+) -> SourceShapes {
+    let fading_parent = coords
+        .get_parent()
+        .filter(|_| opacity < 1.0)
+        .and_then(|parent| container.get_available_parent(parent, world));
+
+    if let Some(parent_coords) = fading_parent {
+        SourceShapes::CrossFade {
+            parent: TileShape::faded(parent_coords, coords, zoom, 1.0 - opacity),
+            target: TileShape::faded(coords, coords, zoom, opacity),
+        }
+    } else {
+        SourceShapes::SourceEqTarget(TileShape::new(coords, zoom))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::available_shapes;
+    use crate::{
+        coords::{WorldTileCoords, Zoom, ZoomLevel},
+        render::tile_view_pattern::{HasTile, SourceShapes},
+        tcs::world::World,
+    };
+
+    struct AvailableTiles(HashSet<WorldTileCoords>);
+
+    impl HasTile for AvailableTiles {
+        fn has_tile(&self, coords: WorldTileCoords, _world: &World) -> bool {
+            self.0.contains(&coords)
+        }
+    }
+
+    #[test]
+    fn test_cross_fade() {
+        let world = World::default();
+        let zoom = Zoom::new(2.25);
+        let grandparent = WorldTileCoords::from((0, 0, ZoomLevel::new(0)));
+        let target = WorldTileCoords::from((1, 1, ZoomLevel::new(2)));
+        let tiles = AvailableTiles([grandparent, target].into_iter().collect());
+
+        // The closest available parent fades out while the target fades in
+        match available_shapes(target, &tiles, &world, zoom, 0.25) {
+            SourceShapes::CrossFade {
+                parent,
+                target: shape,
+            } => {
+                assert_eq!(parent.coords(), grandparent);
+                assert_eq!(parent.opacity(), 0.75);
+                assert_eq!(shape.coords(), target);
+                assert_eq!(shape.opacity(), 0.25);
+                // Both are clipped by the mask of the target
+                assert_eq!(parent.mask_coords(), target);
+                assert!(!parent.has_own_mask());
+                assert!(shape.has_own_mask());
+            }
+            _ => panic!("the tile is not cross-faded"),
+        }
+
+        assert!(matches!(
+            available_shapes(target, &tiles, &world, zoom, 1.0),
+            SourceShapes::SourceEqTarget(shape) if shape.opacity() == 1.0
+        ));
+        // Without a parent there is nothing to fade from
+        let tiles = AvailableTiles([target].into_iter().collect());
+        assert!(matches!(
+            available_shapes(target, &tiles, &world, zoom, 0.25),
+            SourceShapes::SourceEqTarget(_)
+        ));
+    }
+}
@@ -106,6 +106,22 @@ impl Schedule {
         self
     }
 
+    /// Removes the stage identified by `label`, for example to skip a stage which is not needed
+    /// when rendering to a texture.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use maplibre::schedule::{Schedule, NopStage};
+    /// #
+    /// # let mut schedule = Schedule::default();
+    /// # schedule.add_stage("my_stage", NopStage);
+    /// schedule.remove_stage("my_stage");
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the stage does not exist.
     pub fn remove_stage(&mut self, label: impl StageLabel) -> &mut Self {
         let remove: Box<dyn StageLabel> = Box::new(label);
         self.stages.remove(&remove).expect("stage not found");
@@ -131,14 +147,7 @@ impl Schedule {
         stage: S,
     ) -> &mut Self {
         let label: Box<dyn StageLabel> = Box::new(label);
-        let target = &target as &dyn StageLabel;
-        let target_index = self
-            .stage_order
-            .iter()
-            .enumerate()
-            .find(|(_i, stage_label)| &***stage_label == target)
-            .map(|(i, _)| i)
-            .unwrap_or_else(|| panic!("Target stage does not exist: {:?}.", target));
+        let target_index = self.stage_index(&target);
 
         self.stage_order.insert(target_index + 1, label.clone());
         let prev = self.stages.insert(label.clone(), Box::new(stage));
@@ -165,14 +174,7 @@ impl Schedule {
         stage: S,
     ) -> &mut Self {
         let label: Box<dyn StageLabel> = Box::new(label);
-        let target = &target as &dyn StageLabel;
-        let target_index = self
-            .stage_order
-            .iter()
-            .enumerate()
-            .find(|(_i, stage_label)| &***stage_label == target)
-            .map(|(i, _)| i)
-            .unwrap_or_else(|| panic!("Target stage does not exist: {:?}.", target));
+        let target_index = self.stage_index(&target);
 
         self.stage_order.insert(target_index, label.clone());
         let prev = self.stages.insert(label.clone(), Box::new(stage));
@@ -180,6 +182,61 @@ impl Schedule {
         self
     }
 
+    /// Moves the existing stage `label` immediately after the `target` stage.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use maplibre::schedule::{Schedule, NopStage};
+    /// #
+    /// # let mut schedule = Schedule::default();
+    /// schedule.add_stage("first", NopStage).add_stage("second", NopStage);
+    /// schedule.move_stage_after("second", "first");
+    ///
+    /// let labels = schedule.stages().map(|label| format!("{label:?}")).collect::<Vec<_>>();
+    /// assert_eq!(labels, ["\"second\"", "\"first\""]);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if either stage does not exist.
+    pub fn move_stage_after(
+        &mut self,
+        target: impl StageLabel,
+        label: impl StageLabel,
+    ) -> &mut Self {
+        let label_index = self.stage_index(&label);
+        let label = self.stage_order.remove(label_index);
+        let target_index = self.stage_index(&target);
+        self.stage_order.insert(target_index + 1, label);
+        self
+    }
+
+    /// Moves the existing stage `label` immediately before the `target` stage.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either stage does not exist.
+    pub fn move_stage_before(
+        &mut self,
+        target: impl StageLabel,
+        label: impl StageLabel,
+    ) -> &mut Self {
+        let label_index = self.stage_index(&label);
+        let label = self.stage_order.remove(label_index);
+        let target_index = self.stage_index(&target);
+        self.stage_order.insert(target_index, label);
+        self
+    }
+
+    /// Returns the position of `target` in the execution order.
+    fn stage_index(&self, target: &dyn StageLabel) -> usize {
+        self.stage_order
+            .iter()
+            .position(|stage_label| &**stage_label == target)
+            .unwrap_or_else(|| panic!("Target stage does not exist: {:?}.", target))
+    }
+
     /// Fetches the [`Stage`] of type `T` marked with `label`, then executes the provided
     /// `func` passing the fetched stage to it as an argument.
     ///
@@ -266,6 +323,11 @@ impl Schedule {
         }
     }
 
+    /// Iterates over the labels of the schedule's stages, in execution order.
+    pub fn stages(&self) -> impl Iterator<Item = &dyn StageLabel> {
+        self.stage_order.iter().map(|label| &**label)
+    }
+
     /// Iterates over all of schedule's stages and their labels, in execution order.
     pub fn iter_stages(&self) -> impl Iterator<Item = (&dyn StageLabel, &dyn Stage)> {
         self.stage_order
@@ -0,0 +1,40 @@
+use std::fmt;
+
+use thiserror::Error;
+
+use crate::io::source_client::SourceFetchError;
+
+/// The kinds of external resources which a style can reference.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StyleResourceKind {
+    Sprite,
+    Glyphs,
+}
+
+impl fmt::Display for StyleResourceKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StyleResourceKind::Sprite => write!(f, "sprite"),
+            StyleResourceKind::Glyphs => write!(f, "glyphs"),
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum StyleError {
+    /// A resource which is referenced by the style could not be fetched
+    #[error("{kind} resource at {url} is unavailable")]
+    ResourceUnavailable {
+        kind: StyleResourceKind,
+        url: String,
+        #[source]
+        source: SourceFetchError,
+    },
+    /// The URL of a resource is not a valid template
+    #[error("{kind} url `{url}` is missing the placeholder {placeholder}")]
+    InvalidTemplate {
+        kind: StyleResourceKind,
+        url: String,
+        placeholder: &'static str,
+    },
+}
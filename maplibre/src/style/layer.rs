@@ -6,9 +6,9 @@ use cint::{Alpha, EncodedSrgb};
 use csscolorparser::Color;
 use serde::{Deserialize, Serialize};
 
-use crate::style::raster::RasterLayer;
+use crate::{coords::Zoom, style::raster::RasterLayer};
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct BackgroundPaint {
     #[serde(rename = "background-color")]
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -16,7 +16,7 @@ pub struct BackgroundPaint {
     // TODO a lot
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct FillPaint {
     #[serde(rename = "fill-color")]
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -24,7 +24,7 @@ pub struct FillPaint {
     // TODO a lot
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct LinePaint {
     #[serde(rename = "line-color")]
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -33,7 +33,7 @@ pub struct LinePaint {
 }
 
 /// The different types of paints.
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 #[serde(tag = "type", content = "paint")]
 pub enum LayerPaint {
     #[serde(rename = "background")]
@@ -83,6 +83,17 @@ pub struct StyleLayer {
     pub source_layer: Option<String>,
 }
 
+impl StyleLayer {
+    /// Whether `zoom` is within `minzoom` (inclusive) and `maxzoom` (exclusive) of this layer.
+    pub fn is_visible_at(&self, zoom: Zoom) -> bool {
+        self.minzoom
+            .map_or(true, |minzoom| zoom >= Zoom::new(minzoom as f64))
+            && self
+                .maxzoom
+                .map_or(true, |maxzoom| zoom < Zoom::new(maxzoom as f64))
+    }
+}
+
 impl Default for StyleLayer {
     fn default() -> Self {
         Self {
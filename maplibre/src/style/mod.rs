@@ -3,7 +3,10 @@
 pub use cint::*;
 pub use style::*;
 
+pub mod error;
 pub mod layer;
 pub mod raster;
+pub mod resolve;
 pub mod source;
 mod style;
+pub mod tilejson;
@@ -2,7 +2,7 @@
 
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub enum RasterResampling {
     #[serde(rename = "linear")]
     Linear,
@@ -10,7 +10,7 @@ pub enum RasterResampling {
     Nearest,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct RasterLayer {
     #[serde(rename = "raster-brightness-max")]
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -0,0 +1,206 @@
+//! Resolves the URLs of a style which are relative or use a custom scheme like `mapbox://`.
+
+use std::sync::Arc;
+
+use crate::style::{source::Source, Style};
+
+/// Rewrites a URL with a custom scheme into a fetchable URL. Returns `None` if the URL is not
+/// supported, in which case it is left unchanged.
+pub type SchemeResolver = dyn Fn(&str) -> Option<String> + Send + Sync;
+
+/// Configures how the URLs of a style are resolved, see [`Style::resolve_urls`].
+#[derive(Clone, Default)]
+pub struct StyleLoadOptions {
+    /// The URL from which the style was loaded. Relative URLs are resolved against it.
+    pub base_url: Option<String>,
+    /// Appended as `access_token` query parameter to the URLs which were rewritten by the
+    /// `scheme_resolver`.
+    pub access_token: Option<String>,
+    /// Rewrites URLs whose scheme is not `http`, `https` or `file`.
+    pub scheme_resolver: Option<Arc<SchemeResolver>>,
+}
+
+impl StyleLoadOptions {
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = Some(base_url.into());
+        self
+    }
+
+    pub fn with_access_token(mut self, access_token: impl Into<String>) -> Self {
+        self.access_token = Some(access_token.into());
+        self
+    }
+
+    pub fn with_scheme_resolver(
+        mut self,
+        scheme_resolver: impl Fn(&str) -> Option<String> + Send + Sync + 'static,
+    ) -> Self {
+        self.scheme_resolver = Some(Arc::new(scheme_resolver));
+        self
+    }
+
+    /// Returns the absolute URL for `url`. Placeholders like `{z}` are kept.
+    pub fn resolve_url(&self, url: &str) -> String {
+        match scheme(url) {
+            Some("http" | "https" | "file") => url.to_string(),
+            Some(_) => {
+                let Some(resolved) = self
+                    .scheme_resolver
+                    .as_ref()
+                    .and_then(|resolver| resolver(url)) else { return url.to_string(); };
+
+                match &self.access_token {
+                    Some(token) => {
+                        let separator = if resolved.contains('?') { '&' } else { '?' };
+                        format!("{resolved}{separator}access_token={token}")
+                    }
+                    None => resolved,
+                }
+            }
+            None => match &self.base_url {
+                Some(base_url) => join(base_url, url),
+                None => url.to_string(),
+            },
+        }
+    }
+}
+
+impl Style {
+    /// Parses the style in `data` and resolves its URLs with `options`.
+    pub fn load(data: &[u8], options: &StyleLoadOptions) -> Result<Self, serde_json::Error> {
+        let mut style: Style = serde_json::from_slice(data)?;
+        style.resolve_urls(options);
+        Ok(style)
+    }
+
+    /// Rewrites the URLs of the sprite, the glyphs and the tiles of all sources into absolute
+    /// URLs, see [`StyleLoadOptions::resolve_url`].
+    pub fn resolve_urls(&mut self, options: &StyleLoadOptions) {
+        for url in [&mut self.sprite, &mut self.glyphs].into_iter().flatten() {
+            *url = options.resolve_url(url);
+        }
+
+        for source in self.sources.values_mut() {
+            let (Source::Vector(source) | Source::Raster(source)) = source;
+            if let Some(tiles) = &mut source.tiles {
+                *tiles = options.resolve_url(tiles);
+            }
+        }
+    }
+}
+
+/// Returns the scheme of `url` or `None` if the URL is relative.
+fn scheme(url: &str) -> Option<&str> {
+    let (scheme, _) = url.split_once("://")?;
+    let valid = scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+        && scheme
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'));
+    valid.then_some(scheme)
+}
+
+/// Resolves the relative `url` against `base`, like a browser would. `.` and `..` segments of
+/// `url` are removed.
+fn join(base: &str, url: &str) -> String {
+    let Some((scheme, rest)) = base.split_once("://") else { return url.to_string(); };
+
+    if let Some(url) = url.strip_prefix("//") {
+        return format!("{scheme}://{url}");
+    }
+
+    let (authority, base_path) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+    let is_suffix = |c: char| c == '?' || c == '#';
+    let base_path = &base_path[..base_path.find(is_suffix).unwrap_or(base_path.len())];
+    let (path, suffix) = url.split_at(url.find(is_suffix).unwrap_or(url.len()));
+
+    let mut segments = Vec::new();
+    if !path.starts_with('/') {
+        // The directory of the base, i.e. without its last segment
+        segments.extend(base_path.split('/').skip(1));
+        segments.pop();
+    }
+
+    for segment in path.trim_start_matches('/').split('/') {
+        match segment {
+            "." => {}
+            ".." => {
+                segments.pop();
+            }
+            segment => segments.push(segment),
+        }
+    }
+
+    format!("{scheme}://{authority}/{}{suffix}", segments.join("/"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::StyleLoadOptions;
+    use crate::style::{source::Source, Style};
+
+    #[test]
+    fn test_resolve_url() {
+        let options = StyleLoadOptions::default()
+            .with_base_url("https://example.com/styles/basic/style.json?v=2")
+            .with_access_token("secret")
+            .with_scheme_resolver(|url| {
+                url.strip_prefix("mapbox://")
+                    .map(|path| format!("https://api.example.com/{path}"))
+            });
+
+        assert_eq!(
+            options.resolve_url("sprites/sprite"),
+            "https://example.com/styles/basic/sprites/sprite"
+        );
+        assert_eq!(
+            options.resolve_url("../../fonts/{fontstack}/{range}.pbf"),
+            "https://example.com/fonts/{fontstack}/{range}.pbf"
+        );
+        assert_eq!(
+            options.resolve_url("/tiles/{z}/{x}/{y}.pbf?key=1"),
+            "https://example.com/tiles/{z}/{x}/{y}.pbf?key=1"
+        );
+        assert_eq!(
+            options.resolve_url("//cdn.example.com/sprite"),
+            "https://cdn.example.com/sprite"
+        );
+        assert_eq!(
+            options.resolve_url("mapbox://sprites/streets"),
+            "https://api.example.com/sprites/streets?access_token=secret"
+        );
+        assert_eq!(
+            options.resolve_url("http://other.com/sprite"),
+            "http://other.com/sprite"
+        );
+        // Without a base and resolver nothing is rewritten
+        assert_eq!(
+            StyleLoadOptions::default().resolve_url("mapbox://sprites/streets"),
+            "mapbox://sprites/streets"
+        );
+
+        let mut style = Style {
+            sprite: Some("sprite".to_string()),
+            ..Style::default()
+        };
+        style.sources.insert(
+            "openmaptiles".to_string(),
+            serde_json::from_str::<Source>(
+                r#"{"type": "vector", "tiles": "tiles/{z}/{x}/{y}.pbf"}"#,
+            )
+            .unwrap(),
+        );
+        style.resolve_urls(&options);
+
+        assert_eq!(
+            style.sprite.as_deref(),
+            Some("https://example.com/styles/basic/sprite")
+        );
+        let Source::Vector(source) = &style.sources["openmaptiles"] else {
+            panic!("vector source expected");
+        };
+        assert_eq!(
+            source.tiles.as_deref(),
+            Some("https://example.com/styles/basic/tiles/{z}/{x}/{y}.pbf")
+        );
+    }
+}
@@ -2,6 +2,8 @@
 
 use serde::{Deserialize, Serialize};
 
+use crate::coords::WorldTileCoords;
+
 /// String url to a tile.
 pub type TileUrl = String;
 
@@ -9,7 +11,7 @@ pub type TileUrl = String;
 pub type TileJSONUrl = String;
 
 /// Tiles can be positioned using either the xyz coordinates or the TMS (Tile Map Service) protocol.
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
 pub enum TileAddressingScheme {
     #[serde(rename = "xyz")]
     XYZ,
@@ -24,7 +26,7 @@ impl Default for TileAddressingScheme {
 }
 
 /// Source properties for tiles or rasters.
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct VectorSource {
     /// String which contains attribution information for the used tiles.
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -49,7 +51,22 @@ pub struct VectorSource {
     // TODO volatile
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+impl VectorSource {
+    /// Replaces the placeholders `{z}`, `{x}` and `{y}` of the tile url with the tile
+    /// coordinates according to the [`scheme`](VectorSource::scheme) of this source.
+    pub fn tile_url(&self, coords: &WorldTileCoords) -> Option<String> {
+        let tiles = self.tiles.as_ref()?;
+        let tile_coords = coords.into_tile(self.scheme.unwrap_or_default())?;
+        Some(
+            tiles
+                .replace("{z}", &tile_coords.z.to_string())
+                .replace("{x}", &tile_coords.x.to_string())
+                .replace("{y}", &tile_coords.y.to_string()),
+        )
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 #[serde(tag = "type")]
 pub enum Source {
     #[serde(rename = "vector")]
@@ -57,3 +74,20 @@ pub enum Source {
     #[serde(rename = "raster")]
     Raster(VectorSource), // FIXME: Does it make sense that a raster have a VectorSource?
 }
+
+impl Source {
+    /// Returns whether both sources fetch the same tileset, i.e. they have the same type,
+    /// the same tile URL and the same addressing scheme. Sources without a tile URL are
+    /// never considered identical.
+    pub fn same_tileset(&self, other: &Source) -> bool {
+        let (a, b) = match (self, other) {
+            (Source::Vector(a), Source::Vector(b)) => (a, b),
+            (Source::Raster(a), Source::Raster(b)) => (a, b),
+            _ => return false,
+        };
+
+        a.tiles.is_some()
+            && a.tiles == b.tiles
+            && a.scheme.unwrap_or_default() == b.scheme.unwrap_or_default()
+    }
+}
@@ -1,16 +1,30 @@
 //! Default vector tile styles configuration.
 
-use std::{collections::HashMap, str::FromStr};
+use std::{
+    collections::{HashMap, HashSet},
+    str::FromStr,
+};
 
 use csscolorparser::Color;
 use serde::{Deserialize, Serialize};
 
-use crate::style::{
-    layer::{FillPaint, LayerPaint, LinePaint, StyleLayer},
-    raster::RasterLayer,
-    source::Source,
+use crate::{
+    io::source_client::HttpClient,
+    style::{
+        error::{StyleError, StyleResourceKind},
+        layer::{FillPaint, LayerPaint, LinePaint, StyleLayer},
+        raster::RasterLayer,
+        source::Source,
+    },
 };
 
+/// The key of [`Style::required_source_layers`] for the layers without a source. Their tiles are
+/// fetched by the source client of the kernel.
+pub const DEFAULT_SOURCE_ID: &str = "default";
+
+/// The glyph range which is used to check the reachability of the glyphs of a style.
+const VALIDATION_GLYPH_RANGE: &str = "0-255";
+
 /// Stores the style for a multi-layered map.
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Style {
@@ -22,6 +36,218 @@ pub struct Style {
     pub center: Option<[f64; 2]>, // TODO: Use LatLon type here
     pub zoom: Option<f64>,
     pub pitch: Option<f64>,
+    /// Base URL of the sprite. The sprite consists of `{sprite}.json` and `{sprite}.png`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sprite: Option<String>,
+    /// URL template for glyphs with the placeholders `{fontstack}` and `{range}`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub glyphs: Option<String>,
+}
+
+/// The result of [`Style::dedupe_sources`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SourceDeduplication {
+    /// Maps the id of each removed source to the id of the canonical source which replaced it.
+    pub replaced: HashMap<String, String>,
+}
+
+impl SourceDeduplication {
+    /// Returns the id of the source which is used instead of `source_id`.
+    pub fn canonical<'a>(&'a self, source_id: &'a str) -> &'a str {
+        self.replaced
+            .get(source_id)
+            .map(|id| id.as_str())
+            .unwrap_or(source_id)
+    }
+}
+
+/// The differences between two styles, as returned by [`Style::diff`]. All entries are
+/// layer ids.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StyleDiff {
+    /// Layers which only exist in the new style
+    pub added: Vec<String>,
+    /// Layers which only exist in the old style
+    pub removed: Vec<String>,
+    /// Layers of which only the paint changed. These do not need to be tessellated again.
+    pub paint_changed: Vec<String>,
+    /// Layers of which the features changed, for example because the source, the source
+    /// layer, the zoom range or the type of the layer changed. These need to be tessellated
+    /// again.
+    pub retessellate: Vec<String>,
+}
+
+impl StyleDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.paint_changed.is_empty()
+            && self.retessellate.is_empty()
+    }
+
+    /// Returns whether any layer needs to be tessellated.
+    pub fn requires_tessellation(&self) -> bool {
+        !self.added.is_empty() || !self.retessellate.is_empty()
+    }
+}
+
+impl Style {
+    /// Checks that the sprite and glyphs which are referenced by this style can be fetched
+    /// with `client`. This is opt-in, because it requires network access.
+    ///
+    /// The glyphs are checked with a single glyph range of each of the `font_stacks`, for
+    /// example `"Open Sans Regular,Arial Unicode MS Regular"`. Symbol layers are not supported,
+    /// so the font stacks which a style uses can not be read from it. Without font stacks, only
+    /// the URL template of the glyphs is checked.
+    pub async fn validate_resources<HC: HttpClient>(
+        &self,
+        client: &HC,
+        font_stacks: &[&str],
+    ) -> Result<(), StyleError> {
+        if let Some(sprite) = &self.sprite {
+            let url = format!("{sprite}.json");
+            client
+                .fetch(&url)
+                .await
+                .map_err(|source| StyleError::ResourceUnavailable {
+                    kind: StyleResourceKind::Sprite,
+                    url,
+                    source,
+                })?;
+        }
+
+        if let Some(glyphs) = &self.glyphs {
+            for placeholder in ["{fontstack}", "{range}"] {
+                if !glyphs.contains(placeholder) {
+                    return Err(StyleError::InvalidTemplate {
+                        kind: StyleResourceKind::Glyphs,
+                        url: glyphs.clone(),
+                        placeholder,
+                    });
+                }
+            }
+
+            for font_stack in font_stacks {
+                let url = glyphs
+                    .replace("{fontstack}", font_stack)
+                    .replace("{range}", VALIDATION_GLYPH_RANGE);
+                client
+                    .fetch(&url)
+                    .await
+                    .map_err(|source| StyleError::ResourceUnavailable {
+                        kind: StyleResourceKind::Glyphs,
+                        url,
+                        source,
+                    })?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Compares the layers of this style with the layers of `new`.
+    pub fn diff(&self, new: &Style) -> StyleDiff {
+        let mut diff = StyleDiff::default();
+
+        let source_changed = |source: &Option<String>| -> bool {
+            let Some(source) = source else { return false; };
+            self.sources.get(source) != new.sources.get(source)
+        };
+
+        for old_layer in &self.layers {
+            if !new.layers.iter().any(|layer| layer.id == old_layer.id) {
+                diff.removed.push(old_layer.id.clone());
+            }
+        }
+
+        for new_layer in &new.layers {
+            let Some(old_layer) = self.layers.iter().find(|layer| layer.id == new_layer.id) else {
+                diff.added.push(new_layer.id.clone());
+                continue;
+            };
+
+            let paint_type_changed = match (&old_layer.paint, &new_layer.paint) {
+                (Some(old), Some(new)) => {
+                    std::mem::discriminant(old) != std::mem::discriminant(new)
+                }
+                (None, None) => false,
+                _ => true,
+            };
+
+            if old_layer.source != new_layer.source
+                || old_layer.source_layer != new_layer.source_layer
+                || old_layer.minzoom != new_layer.minzoom
+                || old_layer.maxzoom != new_layer.maxzoom
+                || paint_type_changed
+                || source_changed(&new_layer.source)
+            {
+                diff.retessellate.push(new_layer.id.clone());
+            } else if old_layer.paint != new_layer.paint || old_layer.index != new_layer.index {
+                diff.paint_changed.push(new_layer.id.clone());
+            }
+        }
+
+        diff
+    }
+
+    /// Returns the source layers which each source has to provide for this style, keyed by the
+    /// source id. Layers without a source are keyed by [`DEFAULT_SOURCE_ID`] and layers without a
+    /// source layer are ignored.
+    pub fn required_source_layers(&self) -> HashMap<String, HashSet<String>> {
+        let mut required: HashMap<String, HashSet<String>> = HashMap::new();
+        for layer in &self.layers {
+            if let Some(source_layer) = &layer.source_layer {
+                let source = layer.source.as_deref().unwrap_or(DEFAULT_SOURCE_ID);
+                required
+                    .entry(source.to_string())
+                    .or_default()
+                    .insert(source_layer.clone());
+            }
+        }
+        required
+    }
+
+    /// Merges sources which fetch the same tileset, see [`Source::same_tileset`]. The
+    /// lexicographically smallest id of a group of identical sources is kept and all layers
+    /// are remapped to it, so that each tileset is fetched only once.
+    pub fn dedupe_sources(&mut self) -> SourceDeduplication {
+        let mut ids = self.sources.keys().cloned().collect::<Vec<_>>();
+        ids.sort();
+
+        let mut deduplication = SourceDeduplication::default();
+
+        for (i, canonical) in ids.iter().enumerate() {
+            if deduplication.replaced.contains_key(canonical) {
+                continue;
+            }
+
+            for duplicate in &ids[i + 1..] {
+                if deduplication.replaced.contains_key(duplicate) {
+                    continue;
+                }
+
+                if self.sources[canonical].same_tileset(&self.sources[duplicate]) {
+                    deduplication
+                        .replaced
+                        .insert(duplicate.clone(), canonical.clone());
+                }
+            }
+        }
+
+        for duplicate in deduplication.replaced.keys() {
+            self.sources.remove(duplicate);
+        }
+
+        for layer in &mut self.layers {
+            if let Some(source) = &mut layer.source {
+                if let Some(canonical) = deduplication.replaced.get(source) {
+                    *source = canonical.clone();
+                }
+            }
+        }
+
+        deduplication
+    }
 }
 
 impl Default for Style {
@@ -34,6 +260,8 @@ impl Default for Style {
             center: Some([46.5197, 6.6323]),
             pitch: Some(0.0),
             zoom: Some(13.0),
+            sprite: None,
+            glyphs: None,
             layers: vec![
                 StyleLayer {
                     index: 0,
@@ -205,4 +433,186 @@ mod tests {
 
         let _style: Style = serde_json::from_str(style_json_str).unwrap();
     }
+
+    #[tokio::test]
+    async fn test_validate_resources() {
+        use crate::{
+            io::fn_source::FnSource,
+            style::error::{StyleError, StyleResourceKind},
+        };
+
+        // Only URLs which end with a tile address can be fetched from a FnSource
+        let client = FnSource::new(|_coords| async move { Ok(Vec::new()) });
+
+        let mut style = Style::default();
+        assert!(style.validate_resources(&client, &[]).await.is_ok());
+
+        style.glyphs = Some("https://example.com/{fontstack}.pbf".to_string());
+        assert!(matches!(
+            style.validate_resources(&client, &[]).await,
+            Err(StyleError::InvalidTemplate {
+                kind: StyleResourceKind::Glyphs,
+                placeholder: "{range}",
+                ..
+            })
+        ));
+
+        style.glyphs = Some("https://example.com/{fontstack}/{range}.pbf".to_string());
+        assert!(style.validate_resources(&client, &[]).await.is_ok());
+        match style
+            .validate_resources(&client, &["Open Sans Regular", "Noto Sans Bold"])
+            .await
+        {
+            Err(StyleError::ResourceUnavailable { kind, url, .. }) => {
+                assert_eq!(kind, StyleResourceKind::Glyphs);
+                assert_eq!(url, "https://example.com/Open Sans Regular/0-255.pbf");
+            }
+            result => panic!("unexpected result {result:?}"),
+        }
+
+        style.glyphs = None;
+        style.sprite = Some("https://example.com/sprite".to_string());
+        match style.validate_resources(&client, &[]).await {
+            Err(StyleError::ResourceUnavailable { kind, url, .. }) => {
+                assert_eq!(kind, StyleResourceKind::Sprite);
+                assert_eq!(url, "https://example.com/sprite.json");
+            }
+            result => panic!("unexpected result {result:?}"),
+        }
+    }
+
+    #[test]
+    fn test_diff() {
+        let old = Style::default();
+        let mut new = Style::default();
+
+        new.layers.retain(|layer| layer.id != "boundary");
+        new.layers.push(StyleLayer {
+            id: "poi".to_string(),
+            source_layer: Some("poi".to_string()),
+            ..StyleLayer::default()
+        });
+        for layer in &mut new.layers {
+            match layer.id.as_str() {
+                "park" => {
+                    layer.paint = Some(LayerPaint::Fill(FillPaint {
+                        fill_color: Some(Color::from_str("#00ff00").unwrap()),
+                    }))
+                }
+                "water" => layer.source_layer = Some("ocean".to_string()),
+                "building" => layer.minzoom = Some(14),
+                _ => {}
+            }
+        }
+
+        assert!(old.diff(&old).is_empty());
+
+        let diff = old.diff(&new);
+        assert_eq!(diff.added, vec!["poi"]);
+        assert_eq!(diff.removed, vec!["boundary"]);
+        assert_eq!(diff.paint_changed, vec!["park"]);
+        assert_eq!(diff.retessellate, vec!["building", "water"]);
+        assert!(diff.requires_tessellation());
+    }
+
+    #[test]
+    fn test_dedupe_sources() {
+        // language=JSON
+        let style_json_str = r##"
+        {
+          "version": 8,
+          "name": "Test Style",
+          "metadata": {},
+          "sources": {
+            "b": {"type": "vector", "tiles": "https://example.com/{z}/{x}/{y}.pbf"},
+            "a": {"type": "vector", "tiles": "https://example.com/{z}/{x}/{y}.pbf"},
+            "c": {"type": "raster", "tiles": "https://example.com/{z}/{x}/{y}.pbf"},
+            "d": {"type": "vector", "tiles": "https://example.com/{z}/{x}/{y}.pbf", "scheme": "tms"}
+          },
+          "layers": [
+            {"id": "water", "type": "fill", "source": "b", "source-layer": "water", "paint": {"fill-color": "#aad3df"}},
+            {"id": "park", "type": "fill", "source": "a", "source-layer": "park", "paint": {"fill-color": "#c8facc"}},
+            {"id": "raster", "type": "fill", "source": "c", "paint": {"fill-color": "#000000"}}
+          ]
+        }
+        "##;
+
+        let mut style: Style = serde_json::from_str(style_json_str).unwrap();
+        let deduplication = style.dedupe_sources();
+
+        assert_eq!(deduplication.replaced.len(), 1);
+        assert_eq!(deduplication.canonical("b"), "a");
+        assert_eq!(deduplication.canonical("c"), "c");
+
+        let mut ids = style.sources.keys().cloned().collect::<Vec<_>>();
+        ids.sort();
+        assert_eq!(ids, vec!["a", "c", "d"]);
+
+        assert_eq!(style.layers[0].source.as_deref(), Some("a"));
+        assert_eq!(style.layers[1].source.as_deref(), Some("a"));
+        assert_eq!(style.layers[2].source.as_deref(), Some("c"));
+    }
+
+    #[test]
+    fn test_required_source_layers() {
+        // language=JSON
+        let style_json_str = r##"
+        {
+          "version": 8,
+          "name": "Test Style",
+          "metadata": {},
+          "sources": {
+            "openmaptiles": {"type": "vector", "tiles": "https://example.com/{z}/{x}/{y}.pbf"},
+            "contours": {"type": "vector", "tiles": "https://example.com/contours/{z}/{x}/{y}.pbf"}
+          },
+          "layers": [
+            {"id": "background", "type": "background", "paint": {"background-color": "#ffffff"}},
+            {"id": "water", "type": "fill", "source": "openmaptiles", "source-layer": "water", "paint": {"fill-color": "#aad3df"}},
+            {"id": "water_outline", "type": "line", "source": "openmaptiles", "source-layer": "water", "paint": {"line-color": "#000000"}},
+            {"id": "park", "type": "fill", "source": "openmaptiles", "source-layer": "park", "paint": {"fill-color": "#c8facc"}},
+            {"id": "contour", "type": "line", "source": "contours", "source-layer": "contour", "paint": {"line-color": "#000000"}}
+          ]
+        }
+        "##;
+
+        let style: Style = serde_json::from_str(style_json_str).unwrap();
+        let required = style.required_source_layers();
+
+        assert_eq!(required.len(), 2);
+        assert_eq!(
+            required["openmaptiles"],
+            ["water", "park"]
+                .map(String::from)
+                .into_iter()
+                .collect::<HashSet<_>>()
+        );
+        assert_eq!(
+            required["contours"],
+            ["contour"]
+                .map(String::from)
+                .into_iter()
+                .collect::<HashSet<_>>()
+        );
+
+        // The layers of the default style have no source
+        let required = Style::default().required_source_layers();
+        assert_eq!(required.len(), 1);
+        assert_eq!(
+            required[DEFAULT_SOURCE_ID],
+            [
+                "park",
+                "landuse",
+                "landcover",
+                "transportation",
+                "building",
+                "water",
+                "waterway",
+                "boundary",
+                "raster"
+            ]
+            .map(String::from)
+            .into_iter()
+            .collect::<HashSet<_>>()
+        );
+    }
 }
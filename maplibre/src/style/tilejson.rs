@@ -0,0 +1,148 @@
+//! Configures sources from [TileJSON](https://github.com/mapbox/tilejson-spec) documents.
+
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::{
+    io::source_client::{HttpClient, SourceFetchError},
+    style::source::{TileAddressingScheme, VectorSource},
+};
+
+#[derive(Error, Debug)]
+pub enum TileJsonError {
+    #[error("fetching the TileJSON document failed")]
+    Fetch(#[from] SourceFetchError),
+    #[error("the TileJSON document is invalid")]
+    Parse(#[from] serde_json::Error),
+    #[error("the TileJSON document does not contain any tile urls")]
+    MissingTiles,
+}
+
+/// The subset of a TileJSON document which is needed to configure a [`VectorSource`].
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct TileJson {
+    #[serde(default)]
+    pub tilejson: Option<String>,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub attribution: Option<String>,
+    pub tiles: Vec<String>,
+    #[serde(default)]
+    pub minzoom: Option<u8>,
+    #[serde(default)]
+    pub maxzoom: Option<u8>,
+    /// The bounds as `[left, bottom, right, top]` in WGS84.
+    #[serde(default)]
+    pub bounds: Option<[f64; 4]>,
+    #[serde(default)]
+    pub scheme: Option<TileAddressingScheme>,
+}
+
+impl TileJson {
+    pub fn from_slice(data: &[u8]) -> Result<Self, TileJsonError> {
+        Ok(serde_json::from_slice(data)?)
+    }
+
+    /// Creates a source which fetches the tiles that are advertised by this document. Only the
+    /// first tile url is used.
+    ///
+    /// The tiles of the style layers without a source are fetched from the source if it is added
+    /// to the style as [`DEFAULT_SOURCE_ID`](crate::style::DEFAULT_SOURCE_ID), see
+    /// [`SourceType::default_vector`](crate::io::source_type::SourceType::default_vector).
+    pub fn into_source(self) -> Result<VectorSource, TileJsonError> {
+        let tiles = self
+            .tiles
+            .into_iter()
+            .next()
+            .ok_or(TileJsonError::MissingTiles)?;
+
+        Ok(VectorSource {
+            attribution: self.attribution,
+            bounds: self
+                .bounds
+                .map(|[left, bottom, right, top]| (left, bottom, right, top)),
+            maxzoom: self.maxzoom,
+            minzoom: self.minzoom,
+            scheme: self.scheme,
+            tiles: Some(tiles),
+        })
+    }
+}
+
+impl VectorSource {
+    /// Fetches the TileJSON document at `url` and configures a source from its `tiles`,
+    /// `minzoom`, `maxzoom`, `bounds` and `scheme` fields.
+    pub async fn from_tilejson<HC: HttpClient>(
+        client: &HC,
+        url: &str,
+    ) -> Result<Self, TileJsonError> {
+        let data = client.fetch(url).await?;
+        TileJson::from_slice(&data)?.into_source()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TileJson, TileJsonError};
+    use crate::{
+        coords::{WorldTileCoords, ZoomLevel},
+        io::source_type::SourceType,
+        style::{
+            source::{Source, TileAddressingScheme},
+            Style, DEFAULT_SOURCE_ID,
+        },
+    };
+
+    #[test]
+    fn test_into_source() {
+        // language=json
+        let document = r#"
+        {
+          "tilejson": "2.2.0",
+          "tiles": ["https://example.com/{z}/{x}/{y}.pbf", "https://b.example.com/{z}/{x}/{y}.pbf"],
+          "minzoom": 0,
+          "maxzoom": 14,
+          "bounds": [-180, -85.05, 180, 85.05],
+          "scheme": "tms"
+        }
+        "#;
+
+        let source = TileJson::from_slice(document.as_bytes())
+            .unwrap()
+            .into_source()
+            .unwrap();
+
+        assert_eq!(source.maxzoom, Some(14));
+        assert_eq!(source.scheme, Some(TileAddressingScheme::TMS));
+        assert_eq!(source.bounds, Some((-180.0, -85.05, 180.0, 85.05)));
+
+        // The row is flipped for TMS
+        let coords = WorldTileCoords::from((1, 0, ZoomLevel::new(2)));
+        assert_eq!(
+            source.tile_url(&coords).unwrap(),
+            "https://example.com/2/1/3.pbf"
+        );
+
+        // The kernel fetches the tiles of the default source from the configured url
+        let mut style = Style::default();
+        assert_eq!(
+            SourceType::default_vector(&style).format(&coords),
+            "https://maps.tuerantuer.org/europe_germany/2/1/0.pbf"
+        );
+        style
+            .sources
+            .insert(DEFAULT_SOURCE_ID.to_string(), Source::Vector(source));
+        assert_eq!(
+            SourceType::default_vector(&style).format(&coords),
+            "https://example.com/2/1/3.pbf"
+        );
+
+        assert!(matches!(
+            TileJson::from_slice(br#"{"tiles": []}"#)
+                .unwrap()
+                .into_source(),
+            Err(TileJsonError::MissingTiles)
+        ));
+    }
+}
@@ -1,9 +1,12 @@
 //! Tessellation for lines and polygons is implemented here.
 
+use std::hash::Hasher;
+
 use bytemuck::Pod;
 use lyon::tessellation::{
     FillVertex, FillVertexConstructor, StrokeVertex, StrokeVertexConstructor, VertexBuffers,
 };
+use thiserror::Error;
 
 use crate::render::ShaderVertex;
 
@@ -11,8 +14,21 @@ pub mod zero_tessellator;
 
 const DEFAULT_TOLERANCE: f32 = 0.02;
 
-/// Vertex buffers index data type.
-pub type IndexDataType = u32; // Must match INDEX_FORMAT
+/// Vertex buffers index data type. The indices are narrowed to `u16` when they are uploaded if
+/// possible, see [`OverAlignedVertexBuffer::index_format`].
+pub type IndexDataType = u32;
+
+/// A feature which could not be tessellated. The feature is skipped while the remaining
+/// features of the layer are still tessellated.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("tessellation of feature {feature_index} in layer {layer} failed: {reason}")]
+pub struct TessellationError {
+    /// Name of the source layer
+    pub layer: String,
+    /// Index of the feature within the layer
+    pub feature_index: u64,
+    pub reason: String,
+}
 
 /// Constructor for Fill and Stroke vertices.
 pub struct VertexConstructor {}
@@ -66,6 +82,50 @@ impl<V, I> OverAlignedVertexBuffer<V, I> {
     }
 }
 
+impl<V, I: Pod + Into<u32>> OverAlignedVertexBuffer<V, I> {
+    /// The format in which the indices are stored on the GPU. If all vertices can be addressed
+    /// with 16 bits, the indices are narrowed to `u16`, which halves their memory.
+    pub fn index_format(&self) -> wgpu::IndexFormat {
+        if self.buffer.vertices.len() <= u16::MAX as usize + 1 {
+            wgpu::IndexFormat::Uint16
+        } else {
+            wgpu::IndexFormat::Uint32
+        }
+    }
+
+    /// The usable indices encoded in [`OverAlignedVertexBuffer::index_format`], padded to
+    /// `wgpu::COPY_BUFFER_ALIGNMENT`.
+    pub fn index_bytes(&self) -> Vec<u8> {
+        let usable_indices = &self.buffer.indices[..self.usable_indices as usize];
+        let mut bytes: Vec<u8> = match self.index_format() {
+            wgpu::IndexFormat::Uint16 => usable_indices
+                .iter()
+                .flat_map(|index| (Into::<u32>::into(*index) as u16).to_ne_bytes())
+                .collect(),
+            wgpu::IndexFormat::Uint32 => usable_indices
+                .iter()
+                .flat_map(|index| Into::<u32>::into(*index).to_ne_bytes())
+                .collect(),
+        };
+
+        let align = wgpu::COPY_BUFFER_ALIGNMENT as usize;
+        bytes.resize((bytes.len() + align - 1) / align * align, 0);
+        bytes
+    }
+}
+
+impl<V: Pod, I: Pod> OverAlignedVertexBuffer<V, I> {
+    /// Feeds the vertices and the usable indices into `hasher`. The padding indices are skipped,
+    /// so that equal geometry hashes equally.
+    pub fn hash_contents(&self, hasher: &mut impl Hasher) {
+        let usable_indices = &self.buffer.indices[..self.usable_indices as usize];
+        hasher.write_usize(self.buffer.vertices.len());
+        hasher.write(bytemuck::cast_slice(&self.buffer.vertices));
+        hasher.write_usize(usable_indices.len());
+        hasher.write(bytemuck::cast_slice(usable_indices));
+    }
+}
+
 impl<V: Pod, I: Pod> From<VertexBuffers<V, I>> for OverAlignedVertexBuffer<V, I> {
     fn from(mut buffer: VertexBuffers<V, I>) -> Self {
         let usable_indices = buffer.indices.len() as u32;
@@ -110,3 +170,57 @@ impl<V: Pod, I: Pod> Align<V, I> for VertexBuffers<V, I> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::hash::Hasher;
+
+    use crate::{tessellation::OverAlignedVertexBuffer, util::hash::Fnv1aHasher};
+
+    #[test]
+    fn test_hash_contents() {
+        let hash = |buffer: OverAlignedVertexBuffer<[f32; 2], u32>| {
+            let mut hasher = Fnv1aHasher::default();
+            buffer.hash_contents(&mut hasher);
+            hasher.finish()
+        };
+        let vertices = vec![[0.0_f32, 0.0], [1.0, 0.0], [0.0, 1.0]];
+
+        let unpadded = hash(OverAlignedVertexBuffer::from_iters(
+            vertices.clone(),
+            [0, 1, 2],
+            3,
+        ));
+        let padded = hash(OverAlignedVertexBuffer::from_iters(
+            vertices.clone(),
+            [0, 1, 2, 0],
+            3,
+        ));
+        assert_eq!(unpadded, padded);
+
+        let flipped = hash(OverAlignedVertexBuffer::from_iters(vertices, [0, 2, 1], 3));
+        assert_ne!(unpadded, flipped);
+    }
+
+    #[test]
+    fn test_narrow_indices() {
+        let small = OverAlignedVertexBuffer::from_iters(vec![[0.0_f32, 0.0]; 3], [0_u32, 1, 2], 3);
+
+        assert_eq!(small.index_format(), wgpu::IndexFormat::Uint16);
+        // Three u16 are padded to four u16
+        let indices = small
+            .index_bytes()
+            .chunks(2)
+            .map(|bytes| u16::from_ne_bytes([bytes[0], bytes[1]]))
+            .collect::<Vec<_>>();
+        assert_eq!(indices, vec![0, 1, 2, 0]);
+
+        let large = OverAlignedVertexBuffer::from_iters(
+            vec![[0.0_f32, 0.0]; u16::MAX as usize + 2],
+            [0_u32, 1, u16::MAX as u32 + 1],
+            3,
+        );
+        assert_eq!(large.index_format(), wgpu::IndexFormat::Uint32);
+        assert_eq!(large.index_bytes().len(), 12);
+    }
+}
@@ -9,7 +9,7 @@ use lyon::{
     path::{path::Builder, Path},
     tessellation::{
         geometry_builder::MaxIndex, BuffersBuilder, FillOptions, FillRule, FillTessellator,
-        StrokeOptions, StrokeTessellator,
+        StrokeOptions, StrokeTessellator, TessellationError,
     },
 };
 
@@ -30,6 +30,13 @@ pub struct ZeroTessellator<I: std::ops::Add + From<lyon::tessellation::VertexId>
 
     pub feature_indices: Vec<u32>,
     current_index: usize,
+
+    /// Features which failed to tessellate, together with the reason. These features are
+    /// skipped and have no indices.
+    pub feature_errors: Vec<(u64, TessellationError)>,
+    current_feature: u64,
+    current_feature_error: Option<TessellationError>,
+    current_vertex: usize,
 }
 
 impl<I: std::ops::Add + From<lyon::tessellation::VertexId> + MaxIndex> Default
@@ -41,6 +48,10 @@ impl<I: std::ops::Add + From<lyon::tessellation::VertexId> + MaxIndex> Default
             buffer: VertexBuffers::new(),
             feature_indices: Vec::new(),
             current_index: 0,
+            feature_errors: Vec::new(),
+            current_feature: 0,
+            current_feature_error: None,
+            current_vertex: 0,
             path_open: false,
             is_point: false,
         }
@@ -55,16 +66,31 @@ impl<I: std::ops::Add + From<lyon::tessellation::VertexId> + MaxIndex> ZeroTesse
         self.current_index = next_index;
     }
 
+    /// Drops the geometry of the current feature if the tessellation of it failed.
+    fn discard_failed_feature(&mut self) {
+        if let Some(error) = self.current_feature_error.take() {
+            self.buffer.vertices.truncate(self.current_vertex);
+            self.buffer.indices.truncate(self.current_index);
+            self.feature_errors.push((self.current_feature, error));
+        }
+    }
+
+    fn record_error(&mut self, result: Result<(), TessellationError>) {
+        if let Err(e) = result {
+            // Only the first error of a feature is kept
+            self.current_feature_error.get_or_insert(e);
+        }
+    }
+
     fn tessellate_strokes(&mut self) {
         let path_builder = self.path_builder.replace(Path::builder());
 
-        StrokeTessellator::new()
-            .tessellate_path(
-                &path_builder.build(),
-                &StrokeOptions::tolerance(DEFAULT_TOLERANCE),
-                &mut BuffersBuilder::new(&mut self.buffer, VertexConstructor {}),
-            )
-            .unwrap(); // TODO: Remove unwrap
+        let result = StrokeTessellator::new().tessellate_path(
+            &path_builder.build(),
+            &StrokeOptions::tolerance(DEFAULT_TOLERANCE),
+            &mut BuffersBuilder::new(&mut self.buffer, VertexConstructor {}),
+        );
+        self.record_error(result);
     }
 
     fn end(&mut self, close: bool) {
@@ -77,13 +103,12 @@ impl<I: std::ops::Add + From<lyon::tessellation::VertexId> + MaxIndex> ZeroTesse
     fn tessellate_fill(&mut self) {
         let path_builder = self.path_builder.replace(Path::builder());
 
-        FillTessellator::new()
-            .tessellate_path(
-                &path_builder.build(),
-                &FillOptions::tolerance(DEFAULT_TOLERANCE).with_fill_rule(FillRule::NonZero),
-                &mut BuffersBuilder::new(&mut self.buffer, VertexConstructor {}),
-            )
-            .unwrap(); // TODO: Remove unwrap
+        let result = FillTessellator::new().tessellate_path(
+            &path_builder.build(),
+            &FillOptions::tolerance(DEFAULT_TOLERANCE).with_fill_rule(FillRule::NonZero),
+            &mut BuffersBuilder::new(&mut self.buffer, VertexConstructor {}),
+        );
+        self.record_error(result);
     }
 }
 
@@ -193,7 +218,14 @@ impl<I: std::ops::Add + From<lyon::tessellation::VertexId> + MaxIndex> PropertyP
 impl<I: std::ops::Add + From<lyon::tessellation::VertexId> + MaxIndex> FeatureProcessor
     for ZeroTessellator<I>
 {
+    fn feature_begin(&mut self, idx: u64) -> geozero::error::Result<()> {
+        self.current_feature = idx;
+        self.current_vertex = self.buffer.vertices.len();
+        Ok(())
+    }
+
     fn feature_end(&mut self, _idx: u64) -> geozero::error::Result<()> {
+        self.discard_failed_feature();
         self.update_feature_indices();
         Ok(())
     }
@@ -0,0 +1,84 @@
+//! Hashes which are stable across releases and platforms, for example for file names or
+//! persisted records.
+
+use std::hash::Hasher;
+
+/// The 64-bit FNV-1a hash of `bytes`. Unlike the hasher of the standard library, it does not
+/// change between releases.
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hasher = Fnv1aHasher::default();
+    hasher.write(bytes);
+    hasher.finish()
+}
+
+/// Computes [`fnv1a`] incrementally. Integers are written in little-endian byte order and
+/// `usize` as `u64`, so that the result does not depend on the platform.
+#[derive(Debug, Clone, Copy)]
+pub struct Fnv1aHasher(u64);
+
+impl Default for Fnv1aHasher {
+    fn default() -> Self {
+        Self(0xcbf29ce484222325)
+    }
+}
+
+impl Hasher for Fnv1aHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 = (self.0 ^ *byte as u64).wrapping_mul(0x100000001b3);
+        }
+    }
+
+    fn write_u16(&mut self, i: u16) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_u32(&mut self, i: u32) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_u128(&mut self, i: u128) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_usize(&mut self, i: usize) {
+        self.write_u64(i as u64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::hash::Hasher;
+
+    use super::{fnv1a, Fnv1aHasher};
+
+    #[test]
+    fn test_fnv1a() {
+        // The reference values of the FNV test suite
+        assert_eq!(fnv1a(b""), 0xcbf29ce484222325);
+        assert_eq!(fnv1a(b"a"), 0xaf63dc4c8601ec8c);
+        assert_eq!(fnv1a(b"foobar"), 0x85944171f73967e8);
+    }
+
+    #[test]
+    fn test_integers_are_little_endian() {
+        let mut hasher = Fnv1aHasher::default();
+        hasher.write_usize(3);
+        hasher.write(b"abc");
+        hasher.write_u32(0x01020304);
+
+        assert_eq!(hasher.finish(), 0xda154f670af72a66);
+        assert_eq!(
+            hasher.finish(),
+            fnv1a(&[3, 0, 0, 0, 0, 0, 0, 0, b'a', b'b', b'c', 4, 3, 2, 1])
+        );
+    }
+}
@@ -8,6 +8,7 @@ use crate::coords::WorldTileCoords;
 
 mod fps_meter;
 pub mod grid;
+pub mod hash;
 pub mod label;
 pub mod math;
 
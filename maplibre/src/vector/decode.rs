@@ -0,0 +1,164 @@
+//! Decodes only the layers of a vector tile which are needed.
+//!
+//! The layers of a tile are scanned without parsing them: Only the name of each layer is read
+//! and the features, keys and values of layers which are not requested are skipped.
+
+use std::collections::HashSet;
+
+use geozero::mvt::{tile, Message, Tile};
+use thiserror::Error;
+
+/// The field number of the layers in the `Tile` message
+const TILE_LAYERS: u64 = 3;
+/// The field number of the name in the `Layer` message
+const LAYER_NAME: u64 = 1;
+
+#[derive(Error, Debug)]
+#[error("the vector tile is malformed")]
+pub struct TileDecodeError;
+
+/// The value of a protobuf field.
+enum WireValue<'a> {
+    Varint,
+    Fixed,
+    Bytes(&'a [u8]),
+}
+
+/// Iterates over the fields of a protobuf message without decoding nested messages.
+struct Fields<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Fields<'a> {
+    fn varint(&mut self) -> Result<u64, TileDecodeError> {
+        let data = self.data;
+        let mut value = 0;
+        for (i, byte) in data.iter().enumerate().take(10) {
+            value |= ((byte & 0x7f) as u64) << (7 * i);
+            if byte & 0x80 == 0 {
+                self.data = &data[i + 1..];
+                return Ok(value);
+            }
+        }
+        Err(TileDecodeError)
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], TileDecodeError> {
+        if len > self.data.len() {
+            return Err(TileDecodeError);
+        }
+        let (taken, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(taken)
+    }
+
+    fn field(&mut self) -> Result<(u64, WireValue<'a>), TileDecodeError> {
+        let key = self.varint()?;
+        let value = match key & 0x7 {
+            0 => self.varint().map(|_| WireValue::Varint)?,
+            1 => self.take(8).map(|_| WireValue::Fixed)?,
+            2 => {
+                let len = self.varint()?;
+                WireValue::Bytes(self.take(usize::try_from(len).map_err(|_| TileDecodeError)?)?)
+            }
+            5 => self.take(4).map(|_| WireValue::Fixed)?,
+            // Groups are not used by vector tiles
+            _ => return Err(TileDecodeError),
+        };
+        Ok((key >> 3, value))
+    }
+}
+
+impl<'a> Iterator for Fields<'a> {
+    type Item = Result<(u64, WireValue<'a>), TileDecodeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.data.is_empty() {
+            return None;
+        }
+        let field = self.field();
+        if field.is_err() {
+            self.data = &[];
+        }
+        Some(field)
+    }
+}
+
+/// Reads the name of the encoded `layer`.
+fn layer_name(layer: &[u8]) -> Result<Option<&str>, TileDecodeError> {
+    for field in (Fields { data: layer }) {
+        if let (LAYER_NAME, WireValue::Bytes(name)) = field? {
+            return std::str::from_utf8(name)
+                .map(Some)
+                .map_err(|_| TileDecodeError);
+        }
+    }
+    Ok(None)
+}
+
+/// Decodes the layers of the tile in `data` whose name is in `layers`. The other layers are
+/// skipped without decoding their features, so they are missing in the returned tile.
+pub fn decode_tile(data: &[u8], layers: &HashSet<String>) -> Result<Tile, TileDecodeError> {
+    let mut tile = Tile::default();
+    for field in (Fields { data }) {
+        let (TILE_LAYERS, WireValue::Bytes(layer)) = field? else { continue; };
+        if layer_name(layer)?.map_or(false, |name| layers.contains(name)) {
+            tile.layers
+                .push(tile::Layer::decode(layer).map_err(|_| TileDecodeError)?);
+        }
+    }
+    Ok(tile)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use geozero::mvt::{tile, Message, Tile};
+
+    use super::decode_tile;
+
+    fn layer(name: &str) -> tile::Layer {
+        tile::Layer {
+            version: 2,
+            name: name.to_string(),
+            features: vec![tile::Feature {
+                id: Some(1),
+                tags: vec![0, 0],
+                r#type: Some(tile::GeomType::Point as i32),
+                geometry: vec![9, 50, 34],
+            }],
+            keys: vec!["class".to_string()],
+            values: vec![tile::Value {
+                string_value: Some(name.to_string()),
+                ..Default::default()
+            }],
+            extent: Some(4096),
+        }
+    }
+
+    #[test]
+    fn test_decode_tile() {
+        let tile = Tile {
+            layers: vec![layer("water"), layer("transportation"), layer("building")],
+        };
+        let mut data = tile.encode_to_vec();
+        // A layer named `junk` whose features are corrupt
+        data.extend_from_slice(&[0x1a, 11, 0x0a, 4, b'j', b'u', b'n', b'k']);
+        data.extend_from_slice(&[0x12, 3, 0xff, 0xff, 0xff]);
+        assert!(Tile::decode(data.as_slice()).is_err());
+
+        let requested = |names: &[&str]| -> HashSet<String> {
+            names.iter().map(|name| name.to_string()).collect()
+        };
+        let decoded = decode_tile(&data, &requested(&["transportation", "missing"])).unwrap();
+        assert_eq!(decoded.layers, vec![layer("transportation")]);
+
+        assert!(decode_tile(&data, &HashSet::new())
+            .unwrap()
+            .layers
+            .is_empty());
+        assert!(decode_tile(&data[..data.len() - 1], &requested(&["water"])).is_err());
+        assert!(decode_tile(&data, &requested(&["junk"])).is_err());
+    }
+}
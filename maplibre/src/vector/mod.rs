@@ -21,6 +21,8 @@ use crate::{
     },
 };
 
+mod decode;
+pub mod picking;
 mod populate_world_system;
 mod process_vector;
 mod queue_system;
@@ -31,11 +33,14 @@ mod resource_system;
 mod transferables;
 mod upload_system;
 
+pub use decode::{decode_tile, TileDecodeError};
 pub use process_vector::*;
+pub use resource::{BackingBufferType, BufferPoolError};
 pub use transferables::{
-    DefaultVectorTransferables, LayerIndexed, LayerMissing, LayerTessellated, TileTessellated,
-    VectorTransferables,
+    DefaultLayerTesselated, DefaultVectorTransferables, LayerIndexed, LayerMissing,
+    LayerTessellated, TileTessellated, VectorTransferables,
 };
+pub use upload_system::UploadErrors;
 
 use crate::render::graph::RenderGraph;
 
@@ -48,6 +53,33 @@ impl Deref for VectorPipeline {
     }
 }
 
+/// A bind group which is bound as group 1 of the vector pipeline, next to the
+/// [`GlobalUniforms`](crate::render::shaders::GlobalUniforms) in group 0. It makes buffers,
+/// textures or samplers available to a replacement of the vector tile shader.
+pub struct ExtraBindGroup {
+    /// The entries of the layout which `bind_group` has been created with
+    pub layout: Vec<wgpu::BindGroupLayoutEntry>,
+    pub bind_group: wgpu::BindGroup,
+    /// Replaces `tile.vertex.wgsl`, which does not read the bind group. The replacement needs the
+    /// same inputs and outputs.
+    pub vertex_shader: Option<&'static str>,
+}
+
+/// Registers `extra` and recreates the vector pipeline for its layout.
+pub fn set_extra_bind_group(world: &mut World, extra: ExtraBindGroup) {
+    let resources = &mut world.resources;
+    resources.insert(extra);
+    resources.insert(Eventually::<VectorPipeline>::Uninitialized);
+    resources.insert(Eventually::<VectorUniforms>::Uninitialized);
+}
+
+/// The GPU buffer of the [`GlobalUniforms`](crate::render::shaders::GlobalUniforms) and its bind
+/// group for the vector pipeline.
+struct VectorUniforms {
+    buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+}
+
 pub type VectorBufferPool = BufferPool<
     wgpu::Queue,
     wgpu::Buffer,
@@ -91,6 +123,7 @@ impl<E: Environment, T: VectorTransferables> Plugin<E> for VectorPlugin<T> {
 
         resources.insert(Eventually::<VectorBufferPool>::Uninitialized);
         resources.insert(Eventually::<VectorPipeline>::Uninitialized);
+        resources.insert(Eventually::<VectorUniforms>::Uninitialized);
 
         resources
             .get_or_init_mut::<ViewTileSources>()
@@ -0,0 +1,156 @@
+//! Encoding of feature ids into colors for GPU picking.
+
+use crate::{
+    render::{
+        shaders::{ShaderFeatureStyle, Vec4f32},
+        ShaderVertex,
+    },
+    tessellation::{IndexDataType, OverAlignedVertexBuffer},
+};
+
+/// Defines what is written to the color target when rendering vector layers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VectorRenderMode {
+    /// The features are drawn with the colors of the style.
+    Color,
+    /// Each pixel holds the id of the frontmost feature, see [`FeatureId`].
+    FeatureIds,
+}
+
+impl Default for VectorRenderMode {
+    fn default() -> Self {
+        VectorRenderMode::Color
+    }
+}
+
+/// Identifies a feature within a frame which has been rendered with
+/// [`VectorRenderMode::FeatureIds`].
+///
+/// The red and green channels hold the index of the feature within its layer. The blue channel
+/// holds the position of the style layer within [`Style::layers`](crate::style::Style::layers).
+/// White pixels belong to the background. Therefore, only the first 255 layers and the first
+/// 65536 features of a layer can be encoded. Other features are drawn like the background, so
+/// that they are never picked as another feature.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FeatureId {
+    pub layer: usize,
+    pub feature: u32,
+}
+
+impl FeatureId {
+    const MAX_LAYERS: usize = u8::MAX as usize;
+    const MAX_FEATURES: u32 = u16::MAX as u32 + 1;
+
+    /// The color of pixels which do not belong to a feature that can be encoded.
+    pub const NONE: Vec4f32 = [1.0, 1.0, 1.0, 1.0];
+
+    /// Encodes the id as color. Returns `None` if the layer or feature is out of range.
+    pub fn encode(&self) -> Option<Vec4f32> {
+        if self.layer >= Self::MAX_LAYERS || self.feature >= Self::MAX_FEATURES {
+            return None;
+        }
+
+        let [low, high] = (self.feature as u16).to_le_bytes();
+        Some([
+            low as f32 / 255.0,
+            high as f32 / 255.0,
+            self.layer as f32 / 255.0,
+            1.0,
+        ])
+    }
+
+    /// Decodes a pixel of a frame. Returns `None` for the background.
+    pub fn decode(rgba: [u8; 4]) -> Option<Self> {
+        let [low, high, layer, alpha] = rgba;
+        if alpha != u8::MAX || layer == u8::MAX {
+            return None;
+        }
+
+        Some(Self {
+            layer: layer as usize,
+            feature: u16::from_le_bytes([low, high]) as u32,
+        })
+    }
+}
+
+/// Creates the feature metadata which colors each vertex of a layer with its [`FeatureId`], or
+/// with [`FeatureId::NONE`] if the id can not be encoded. `feature_indices` holds for each
+/// feature the count of indices.
+pub fn feature_id_metadata(
+    layer: usize,
+    buffer: &OverAlignedVertexBuffer<ShaderVertex, IndexDataType>,
+    feature_indices: &[u32],
+) -> Vec<ShaderFeatureStyle> {
+    let mut metadata = vec![
+        ShaderFeatureStyle {
+            color: [0.0, 0.0, 0.0, 0.0],
+        };
+        buffer.buffer.vertices.len()
+    ];
+
+    let mut start = 0;
+    for (feature, count) in feature_indices.iter().enumerate() {
+        let end = start + *count as usize;
+        let color = FeatureId {
+            layer,
+            feature: feature as u32,
+        }
+        .encode()
+        .unwrap_or(FeatureId::NONE);
+
+        for index in buffer.buffer.indices.get(start..end).unwrap_or_default() {
+            if let Some(vertex) = metadata.get_mut(*index as usize) {
+                vertex.color = color;
+            }
+        }
+        start = end;
+    }
+
+    metadata
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FeatureId;
+    use crate::render::shaders::Vec4f32;
+
+    fn to_rgba(color: Vec4f32) -> [u8; 4] {
+        color.map(|channel| (channel * 255.0).round() as u8)
+    }
+
+    #[test]
+    fn test_encode_decode() {
+        let id = FeatureId {
+            layer: 3,
+            feature: 1000,
+        };
+        let rgba = to_rgba(id.encode().unwrap());
+
+        assert_eq!(FeatureId::decode(rgba), Some(id));
+        assert_eq!(FeatureId::decode([255, 255, 255, 255]), None);
+        assert_eq!(FeatureId::decode([0, 0, 0, 0]), None);
+    }
+
+    #[test]
+    fn test_encode_out_of_range() {
+        let last = FeatureId {
+            layer: 254,
+            feature: 65535,
+        };
+        assert_eq!(
+            FeatureId::decode(to_rgba(last.encode().unwrap())),
+            Some(last)
+        );
+
+        let feature = FeatureId {
+            feature: 65536,
+            ..last
+        };
+        let layer = FeatureId { layer: 255, ..last };
+        assert_eq!(feature.encode(), None);
+        assert_eq!(layer.encode(), None);
+
+        // Features which can not be encoded are no hit
+        assert_eq!(FeatureId::decode(to_rgba(FeatureId::NONE)), None);
+    }
+}
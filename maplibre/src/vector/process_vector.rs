@@ -1,21 +1,24 @@
 use std::{collections::HashSet, marker::PhantomData};
 
-use geozero::{
-    mvt::{tile, Message},
-    GeozeroDatasource,
-};
+use geozero::{mvt::tile, GeozeroDatasource};
 use thiserror::Error;
 
 use crate::{
-    coords::WorldTileCoords,
+    coords::{WorldTileCoords, EXTENT, TILE_SIZE},
     io::{
         apc::{Context, SendError},
         geometry_index::{IndexProcessor, IndexedGeometry, TileIndex},
     },
     render::ShaderVertex,
-    tessellation::{zero_tessellator::ZeroTessellator, IndexDataType, OverAlignedVertexBuffer},
-    vector::transferables::{
-        LayerIndexed, LayerMissing, LayerTessellated, TileTessellated, VectorTransferables,
+    tessellation::{
+        zero_tessellator::ZeroTessellator, IndexDataType, OverAlignedVertexBuffer,
+        TessellationError,
+    },
+    vector::{
+        decode::decode_tile,
+        transferables::{
+            LayerIndexed, LayerMissing, LayerTessellated, TileTessellated, VectorTransferables,
+        },
     },
 };
 
@@ -29,6 +32,56 @@ pub enum ProcessVectorError {
     Processing(Box<dyn std::error::Error>),
 }
 
+/// The coordinate space of the vertex positions which are emitted by [`process_vector_tile`].
+/// In all spaces the origin is in the upper-left corner.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputSpace {
+    /// Coordinates within the tile in the range `0..EXTENT`, like they are stored in vector
+    /// tiles with the default extent. This is the space which is expected by the renderer and
+    /// therefore the default.
+    TileLocal,
+    /// Coordinates within the tile in the range `0..1`.
+    Normalized,
+    /// Coordinates in the world at the zoom level of the tile. Each tile spans `TILE_SIZE`
+    /// units, so the tile `x=1,y=2` starts at `(TILE_SIZE, 2 * TILE_SIZE)`.
+    World,
+}
+
+impl Default for OutputSpace {
+    fn default() -> Self {
+        OutputSpace::TileLocal
+    }
+}
+
+impl OutputSpace {
+    /// Transforms `vertices` of the tile at `coords` from the range `0..extent` of their layer
+    /// into this space.
+    fn transform(&self, coords: &WorldTileCoords, extent: f64, vertices: &mut [ShaderVertex]) {
+        let (scale, origin) = match self {
+            OutputSpace::TileLocal if extent == EXTENT => return,
+            OutputSpace::TileLocal => (EXTENT / extent, [0.0, 0.0]),
+            OutputSpace::Normalized => (1.0 / extent, [0.0, 0.0]),
+            OutputSpace::World => (
+                TILE_SIZE / extent,
+                [coords.x as f64 * TILE_SIZE, coords.y as f64 * TILE_SIZE],
+            ),
+        };
+
+        for vertex in vertices {
+            vertex.position = [
+                (origin[0] + vertex.position[0] as f64 * scale) as f32,
+                (origin[1] + vertex.position[1] as f64 * scale) as f32,
+            ];
+        }
+    }
+}
+
+/// Configures the output of [`process_vector_tile`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PipelineConfig {
+    pub output_space: OutputSpace,
+}
+
 /// A request for a tile at the given coordinates and in the given layers.
 pub struct VectorTileRequest {
     pub coords: WorldTileCoords,
@@ -40,21 +93,45 @@ pub fn process_vector_tile<T: VectorTransferables, C: Context>(
     tile_request: VectorTileRequest,
     context: &mut ProcessVectorContext<T, C>,
 ) -> Result<(), ProcessVectorError> {
+    let coords = &tile_request.coords;
+
     // Decode
 
-    let mut tile = geozero::mvt::Tile::decode(data).expect("failed to load tile");
+    let mut tile = {
+        #[cfg(feature = "trace")]
+        let _span = tracing::info_span!("decode", %coords).entered();
+        decode_tile(data, &tile_request.layers).expect("failed to load tile")
+    };
 
     // Available
 
-    let coords = &tile_request.coords;
-
     for layer in &mut tile.layers {
-        let cloned_layer = layer.clone();
-        let layer_name: &str = &cloned_layer.name;
-        if !tile_request.layers.contains(layer_name) {
+        if !tile_request.layers.contains(&layer.name) {
             continue;
         }
 
+        // A malformed geometry would abort the processing of the whole layer, so the feature
+        // is kept without geometry in order to keep the indices of the other features
+        for (feature_index, feature) in layer.features.iter_mut().enumerate() {
+            if is_well_formed(feature) {
+                continue;
+            }
+            let error = TessellationError {
+                layer: layer.name.clone(),
+                feature_index: feature_index as u64,
+                reason: "malformed geometry".to_string(),
+            };
+            tracing::warn!("skipping feature at {}: {}", &coords, error);
+            context.tessellation_errors.push(error);
+            feature.geometry.clear();
+        }
+
+        let cloned_layer = layer.clone();
+        let layer_name: &str = &cloned_layer.name;
+
+        #[cfg(feature = "trace")]
+        let _span = tracing::info_span!("tessellate", %coords, layer = layer_name).entered();
+
         let mut tessellator = ZeroTessellator::<IndexDataType>::default();
         if let Err(e) = layer.process(&mut tessellator) {
             context.layer_missing(coords, layer_name)?;
@@ -66,6 +143,22 @@ pub fn process_vector_tile<T: VectorTransferables, C: Context>(
                 e
             );
         } else {
+            for (feature_index, e) in tessellator.feature_errors.drain(..) {
+                let error = TessellationError {
+                    layer: layer_name.to_string(),
+                    feature_index,
+                    reason: format!("{:?}", e),
+                };
+                tracing::warn!("skipping feature at {}: {}", &coords, error);
+                context.tessellation_errors.push(error);
+            }
+
+            context.config.output_space.transform(
+                coords,
+                layer_extent(&cloned_layer),
+                &mut tessellator.buffer.vertices,
+            );
+
             context.layer_tesselation_finished(
                 coords,
                 tessellator.buffer.into(),
@@ -77,8 +170,6 @@ pub fn process_vector_tile<T: VectorTransferables, C: Context>(
 
     // Missing
 
-    let coords = &tile_request.coords;
-
     let available_layers: HashSet<_> = tile
         .layers
         .iter()
@@ -95,12 +186,17 @@ pub fn process_vector_tile<T: VectorTransferables, C: Context>(
         );
     }
 
-    // Indexing
+    // Indexing, only the requested layers were decoded
 
     let mut index = IndexProcessor::new();
 
-    for layer in &mut tile.layers {
-        layer.process(&mut index).unwrap();
+    {
+        #[cfg(feature = "trace")]
+        let _span = tracing::info_span!("index", %coords).entered();
+        for layer in &mut tile.layers {
+            index.set_scale(EXTENT / layer_extent(layer));
+            layer.process(&mut index).unwrap();
+        }
     }
 
     context.layer_indexing_finished(&tile_request.coords, index.get_geometries())?;
@@ -114,8 +210,45 @@ pub fn process_vector_tile<T: VectorTransferables, C: Context>(
     Ok(())
 }
 
+/// Whether the geometry commands of `feature` are well-formed, see section 4.3 of the vector tile
+/// specification: Every command is a `MoveTo`, `LineTo` or `ClosePath` with all of its
+/// parameters, and the first command is a `MoveTo`.
+fn is_well_formed(feature: &tile::Feature) -> bool {
+    const MOVE_TO: u32 = 1;
+    const LINE_TO: u32 = 2;
+    const CLOSE_PATH: u32 = 7;
+
+    let mut geometry = feature.geometry.iter();
+    let mut started = false;
+    while let Some(command) = geometry.next() {
+        let (id, count) = (command & 0x7, (command >> 3) as usize);
+        let parameters = match id {
+            MOVE_TO => count * 2,
+            LINE_TO if started => count * 2,
+            CLOSE_PATH if started && count == 1 => 0,
+            _ => return false,
+        };
+        if geometry.by_ref().take(parameters).count() != parameters {
+            return false;
+        }
+        started = true;
+    }
+    true
+}
+
+/// The extent of the coordinates in `layer`. Most tiles use the default extent of 4096, but some
+/// generators use for example 2048 or 8192.
+fn layer_extent(layer: &tile::Layer) -> f64 {
+    match layer.extent {
+        Some(extent) if extent > 0 => extent as f64,
+        _ => EXTENT,
+    }
+}
+
 pub struct ProcessVectorContext<T: VectorTransferables, C: Context> {
     context: C,
+    config: PipelineConfig,
+    tessellation_errors: Vec<TessellationError>,
     phantom_t: PhantomData<T>,
 }
 
@@ -123,9 +256,16 @@ impl<T: VectorTransferables, C: Context> ProcessVectorContext<T, C> {
     pub fn new(context: C) -> Self {
         Self {
             context,
+            config: PipelineConfig::default(),
+            tessellation_errors: Vec::new(),
             phantom_t: Default::default(),
         }
     }
+
+    pub fn with_config(mut self, config: PipelineConfig) -> Self {
+        self.config = config;
+        self
+    }
 }
 
 impl<T: VectorTransferables, C: Context> ProcessVectorContext<T, C> {
@@ -133,6 +273,11 @@ impl<T: VectorTransferables, C: Context> ProcessVectorContext<T, C> {
         self.context
     }
 
+    /// The features which have been skipped because their tessellation failed.
+    pub fn tessellation_errors(&self) -> &[TessellationError] {
+        &self.tessellation_errors
+    }
+
     fn tile_finished(&mut self, coords: &WorldTileCoords) -> Result<(), ProcessVectorError> {
         self.context
             .send(T::TileTessellated::build_from(*coords))
@@ -182,16 +327,33 @@ impl<T: VectorTransferables, C: Context> ProcessVectorContext<T, C> {
 
 #[cfg(test)]
 mod tests {
-    use super::ProcessVectorContext;
+    use std::cell::RefCell;
+
+    use geozero::mvt::{tile, Message as _, Tile};
+
+    use super::{OutputSpace, ProcessVectorContext};
     use crate::{
-        coords::ZoomLevel,
-        io::apc::tests::DummyContext,
+        coords::{WorldTileCoords, ZoomLevel, EXTENT, TILE_SIZE},
+        io::apc::{tests::DummyContext, Context, IntoMessage, Message, SendError},
+        render::ShaderVertex,
         vector::{
             process_vector::{process_vector_tile, VectorTileRequest},
-            DefaultVectorTransferables,
+            DefaultLayerTesselated, DefaultVectorTransferables, LayerTessellated,
         },
     };
 
+    #[derive(Default)]
+    struct MessageContext {
+        messages: RefCell<Vec<Message>>,
+    }
+
+    impl Context for MessageContext {
+        fn send<T: IntoMessage>(&self, message: T) -> Result<(), SendError> {
+            self.messages.borrow_mut().push(message.into());
+            Ok(())
+        }
+    }
+
     #[test] // TODO: Add proper tile byte array
     #[ignore]
     fn test() {
@@ -204,4 +366,78 @@ mod tests {
             &mut ProcessVectorContext::<DefaultVectorTransferables, _>::new(DummyContext),
         );
     }
+
+    #[test]
+    fn test_output_space() {
+        let coords = WorldTileCoords::from((1, 2, ZoomLevel::new(2)));
+        let transform = |space: OutputSpace| {
+            let mut vertices = [ShaderVertex::new([EXTENT as f32 / 2.0, 0.0], [1.0, 0.0])];
+            space.transform(&coords, EXTENT, &mut vertices);
+            vertices[0].position
+        };
+
+        assert_eq!(
+            transform(OutputSpace::TileLocal),
+            [EXTENT as f32 / 2.0, 0.0]
+        );
+        assert_eq!(transform(OutputSpace::Normalized), [0.5, 0.0]);
+        assert_eq!(
+            transform(OutputSpace::World),
+            [(1.5 * TILE_SIZE) as f32, (2.0 * TILE_SIZE) as f32]
+        );
+    }
+
+    #[test]
+    fn test_layer_extent() {
+        // The upper-left quarter of a tile with an extent of 2048
+        let tile = Tile {
+            layers: vec![tile::Layer {
+                version: 2,
+                name: "square".to_string(),
+                features: vec![tile::Feature {
+                    id: None,
+                    tags: Vec::new(),
+                    r#type: Some(tile::GeomType::Polygon as i32),
+                    // MoveTo(0, 0), LineTo(+1024, 0), (0, +1024), (-1024, 0), ClosePath
+                    geometry: vec![9, 0, 0, 26, 2048, 0, 0, 2048, 2047, 0, 15],
+                }],
+                keys: Vec::new(),
+                values: Vec::new(),
+                extent: Some(2048),
+            }],
+        };
+
+        let mut context =
+            ProcessVectorContext::<DefaultVectorTransferables, _>::new(MessageContext::default());
+        process_vector_tile(
+            &tile.encode_to_vec(),
+            VectorTileRequest {
+                coords: (0, 0, ZoomLevel::default()).into(),
+                layers: ["square".to_string()].into_iter().collect(),
+            },
+            &mut context,
+        )
+        .unwrap();
+
+        let layer = context
+            .take_context()
+            .messages
+            .take()
+            .into_iter()
+            .find(|message| message.tag() == DefaultLayerTesselated::message_tag())
+            .unwrap()
+            .into_transferable::<DefaultLayerTesselated>();
+
+        // The square covers the upper-left quarter of the default extent as well
+        let vertices = &layer.buffer.buffer.vertices;
+        assert!(!vertices.is_empty());
+        for vertex in vertices {
+            for coordinate in vertex.position {
+                assert!((0.0..=EXTENT as f32 / 2.0).contains(&coordinate));
+            }
+        }
+        assert!(vertices
+            .iter()
+            .any(|vertex| vertex.position == [EXTENT as f32 / 2.0; 2]));
+    }
 }
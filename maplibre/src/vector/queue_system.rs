@@ -11,7 +11,11 @@ use crate::{
     vector::{render_commands::DrawVectorTiles, VectorBufferPool},
 };
 
-pub fn queue_system(MapContext { world, .. }: &mut MapContext) {
+pub fn queue_system(
+    MapContext {
+        world, view_state, ..
+    }: &mut MapContext,
+) {
     let Some((
         Initialized(tile_view_pattern),
         Initialized(buffer_pool),
@@ -25,6 +29,7 @@ pub fn queue_system(MapContext { world, .. }: &mut MapContext) {
     )>() else { return; };
 
     let buffer_pool_index = buffer_pool.index();
+    let zoom = view_state.zoom();
 
     for view_tile in tile_view_pattern.iter() {
         let coords = &view_tile.coords();
@@ -40,6 +45,10 @@ pub fn queue_system(MapContext { world, .. }: &mut MapContext) {
 
             if let Some(layer_entries) = buffer_pool_index.get_layers(source_shape.coords()) {
                 for layer_entry in layer_entries {
+                    if !layer_entry.style_layer.is_visible_at(zoom) {
+                        continue;
+                    }
+
                     // Draw tile
                     layer_item_phase.add(LayerItem {
                         draw_function: Box::new(DrawState::<LayerItem, DrawVectorTiles>::new()),
@@ -2,14 +2,14 @@
 //! into a new render command which executes multiple instruction sets.
 use crate::{
     render::{
+        clip_mask::ClipMask,
         eventually::{Eventually, Eventually::Initialized},
         render_phase::{LayerItem, PhaseItem, RenderCommand, RenderCommandResult},
         resource::TrackedRenderPass,
         tile_view_pattern::WgpuTileViewPattern,
-        INDEX_FORMAT,
     },
     tcs::world::World,
-    vector::{VectorBufferPool, VectorPipeline},
+    vector::{ExtraBindGroup, VectorBufferPool, VectorPipeline, VectorUniforms},
 };
 
 pub struct SetVectorTilePipeline;
@@ -28,6 +28,37 @@ impl<P: PhaseItem> RenderCommand<P> for SetVectorTilePipeline {
     }
 }
 
+pub struct SetVectorUniformsBindGroup<const I: usize>;
+impl<P: PhaseItem, const I: usize> RenderCommand<P> for SetVectorUniformsBindGroup<I> {
+    fn render<'w>(
+        world: &'w World,
+        _item: &P,
+        pass: &mut TrackedRenderPass<'w>,
+    ) -> RenderCommandResult {
+        let Some(Initialized(uniforms)) = world
+            .resources
+            .get::<Eventually<VectorUniforms>>() else { return RenderCommandResult::Failure; };
+
+        pass.set_bind_group(I, &uniforms.bind_group, &[]);
+        RenderCommandResult::Success
+    }
+}
+
+/// Binds the [`ExtraBindGroup`] if one has been registered.
+pub struct SetVectorExtraBindGroup<const I: usize>;
+impl<P: PhaseItem, const I: usize> RenderCommand<P> for SetVectorExtraBindGroup<I> {
+    fn render<'w>(
+        world: &'w World,
+        _item: &P,
+        pass: &mut TrackedRenderPass<'w>,
+    ) -> RenderCommandResult {
+        if let Some(extra) = world.resources.get::<ExtraBindGroup>() {
+            pass.set_bind_group(I, &extra.bind_group, &[]);
+        }
+        RenderCommandResult::Success
+    }
+}
+
 pub struct DrawVectorTile;
 impl RenderCommand<LayerItem> for DrawVectorTile {
     fn render<'w>(
@@ -52,7 +83,12 @@ impl RenderCommand<LayerItem> for DrawVectorTile {
         let source_shape = &item.source_shape;
 
         // Uses stencil value of requested tile and the shape of the requested tile
-        let reference = source_shape.coords().stencil_reference_value_3d() as u32;
+        let tile_reference = source_shape.mask_coords().stencil_reference_value_3d();
+        let reference = world
+            .resources
+            .get::<ClipMask>()
+            .map(|clip_mask| clip_mask.stencil_reference(tile_reference))
+            .unwrap_or(tile_reference as u32);
 
         tracing::trace!(
             "Drawing layer {:?} at {}",
@@ -69,7 +105,10 @@ impl RenderCommand<LayerItem> for DrawVectorTile {
 
         pass.set_stencil_reference(reference);
 
-        pass.set_index_buffer(buffer_pool.indices().slice(index_range), INDEX_FORMAT);
+        pass.set_index_buffer(
+            buffer_pool.indices().slice(index_range),
+            entry.index_format(),
+        );
         pass.set_vertex_buffer(
             0,
             buffer_pool.vertices().slice(entry.vertices_buffer_range()),
@@ -99,4 +138,9 @@ impl RenderCommand<LayerItem> for DrawVectorTile {
     }
 }
 
-pub type DrawVectorTiles = (SetVectorTilePipeline, DrawVectorTile);
+pub type DrawVectorTiles = (
+    SetVectorTilePipeline,
+    SetVectorUniformsBindGroup<0>,
+    SetVectorExtraBindGroup<1>,
+    DrawVectorTile,
+);
@@ -7,7 +7,7 @@ use crate::{
     environment::{Environment, OffscreenKernelEnvironment},
     io::{
         apc::{AsyncProcedureCall, AsyncProcedureFuture, Context, Input, ProcedureError},
-        source_type::{SourceType, TessellateSource},
+        source_type::SourceType,
     },
     kernel::Kernel,
     style::layer::LayerPaint,
@@ -133,8 +133,12 @@ pub fn fetch_vector_apc<
 
         if !fill_layers.is_empty() {
             let context = context.clone();
-            let source = SourceType::Tessellate(TessellateSource::default());
-            match client.fetch(&coords, &source).await {
+            let source = SourceType::default_vector(&style);
+            let fetch = client.fetch(&coords, &source);
+            #[cfg(feature = "trace")]
+            let fetch =
+                tracing::Instrument::instrument(fetch, tracing::info_span!("fetch", %coords));
+            match fetch.await {
                 Ok(data) => {
                     let data = data.into_boxed_slice();
 
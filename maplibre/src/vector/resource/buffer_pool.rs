@@ -9,6 +9,7 @@ use std::{
 };
 
 use bytemuck::Pod;
+use thiserror::Error;
 
 use crate::{
     coords::{Quadkey, WorldTileCoords},
@@ -28,6 +29,18 @@ pub const INDICES_SIZE: wgpu::BufferAddress = 10 * 1_000_000;
 pub const FEATURE_METADATA_SIZE: wgpu::BufferAddress = 10 * 1024 * 1000;
 pub const LAYER_METADATA_SIZE: wgpu::BufferAddress = 10 * 1024;
 
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum BufferPoolError {
+    /// The geometry of a single layer does not fit into the backing buffer, even if all other
+    /// layers were evicted.
+    #[error("the {typ:?} of tile {coords} need {bytes} bytes, which exceeds the backing buffer")]
+    TileTooLarge {
+        coords: WorldTileCoords,
+        bytes: wgpu::BufferAddress,
+        typ: BackingBufferType,
+    },
+}
+
 /// This is inspired by the memory pool in Vulkan documented
 /// [here](https://gpuopen-librariesandsdks.github.io/VulkanMemoryAllocator/html/custom_memory_pools.html).
 #[derive(Debug)]
@@ -45,7 +58,7 @@ pub struct BufferPool<Q, B, V, I, TM, FM> {
     phantom_fm: PhantomData<FM>,
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum BackingBufferType {
     Vertices,
     Indices,
@@ -227,7 +240,8 @@ impl<Q: Queue<B>, B, V: Pod, I: Pod, TM: Pod, FM: Pod> BufferPool<Q, B, V, I, TM
     /// * `geometry`
     /// * `layer_metadata` and
     /// * `feature_metadata` for a layer. This function is able to dynamically evict layers if there
-    /// is not enough space available.
+    /// is not enough space available. Layers which do not fit into the backing buffers at all are
+    /// rejected with [`BufferPoolError::TileTooLarge`] and nothing is evicted.
     #[tracing::instrument(skip_all)]
     pub fn allocate_layer_geometry(
         &mut self,
@@ -237,9 +251,11 @@ impl<Q: Queue<B>, B, V: Pod, I: Pod, TM: Pod, FM: Pod> BufferPool<Q, B, V, I, TM
         geometry: &OverAlignedVertexBuffer<V, I>,
         layer_metadata: TM,
         feature_metadata: &[FM],
-    ) {
+    ) -> Result<(), BufferPoolError>
+    where
+        I: Into<u32>,
+    {
         let vertices_stride = size_of::<V>() as wgpu::BufferAddress;
-        let indices_stride = size_of::<I>() as wgpu::BufferAddress;
         let layer_metadata_stride = size_of::<TM>() as wgpu::BufferAddress;
         let feature_metadata_stride = size_of::<FM>() as wgpu::BufferAddress;
 
@@ -248,11 +264,9 @@ impl<Q: Queue<B>, B, V: Pod, I: Pod, TM: Pod, FM: Pod> BufferPool<Q, B, V, I, TM
             geometry.buffer.vertices.len() as wgpu::BufferAddress,
             geometry.buffer.vertices.len() as wgpu::BufferAddress,
         );
-        let (indices_bytes, aligned_indices_bytes) = Self::align(
-            indices_stride,
-            geometry.buffer.indices.len() as wgpu::BufferAddress,
-            geometry.usable_indices as wgpu::BufferAddress,
-        );
+        // The indices are narrowed to u16 if possible and are already aligned
+        let index_format = geometry.index_format();
+        let index_bytes = geometry.index_bytes();
         let (layer_metadata_bytes, aligned_layer_metadata_bytes) =
             Self::align(layer_metadata_stride, 1, 1);
 
@@ -269,6 +283,21 @@ impl<Q: Queue<B>, B, V: Pod, I: Pod, TM: Pod, FM: Pod> BufferPool<Q, B, V, I, TM
             )
         }
 
+        for (bytes, backing_buffer) in [
+            (vertices_bytes, &self.vertices),
+            (index_bytes.len() as wgpu::BufferAddress, &self.indices),
+            (layer_metadata_bytes, &self.layer_metadata),
+            (feature_metadata_bytes, &self.feature_metadata),
+        ] {
+            if bytes > backing_buffer.inner_size {
+                return Err(BufferPoolError::TileTooLarge {
+                    coords,
+                    bytes,
+                    typ: backing_buffer.typ,
+                });
+            }
+        }
+
         let maybe_entry = IndexEntry {
             coords,
             style_layer,
@@ -278,11 +307,12 @@ impl<Q: Queue<B>, B, V: Pod, I: Pod, TM: Pod, FM: Pod> BufferPool<Q, B, V, I, TM
                 self.vertices.inner_size,
             ),
             buffer_indices: self.index.make_room(
-                indices_bytes,
+                index_bytes.len() as wgpu::BufferAddress,
                 self.indices.typ,
                 self.indices.inner_size,
             ),
             usable_indices: geometry.usable_indices,
+            index_format,
             buffer_layer_metadata: self.index.make_room(
                 layer_metadata_bytes,
                 self.layer_metadata.typ,
@@ -305,7 +335,7 @@ impl<Q: Queue<B>, B, V: Pod, I: Pod, TM: Pod, FM: Pod> BufferPool<Q, B, V, I, TM
         queue.write_buffer(
             &self.indices.inner,
             maybe_entry.buffer_indices.start,
-            &bytemuck::cast_slice(&geometry.buffer.indices)[0..aligned_indices_bytes as usize],
+            &index_bytes,
         );
 
         queue.write_buffer(
@@ -321,6 +351,7 @@ impl<Q: Queue<B>, B, V: Pod, I: Pod, TM: Pod, FM: Pod> BufferPool<Q, B, V, I, TM
         );
 
         self.index.push_back(maybe_entry);
+        Ok(())
     }
 
     #[tracing::instrument(skip_all)]
@@ -389,9 +420,10 @@ pub struct IndexEntry {
     buffer_layer_metadata: Range<wgpu::BufferAddress>,
     // Range of bytes within the backing buffer for feature metadata
     buffer_feature_metadata: Range<wgpu::BufferAddress>,
-    // Amount of actually usable indices. Each index has the size/format `IndexDataType`.
+    // Amount of actually usable indices. Each index has the size/format `index_format`.
     // Can be lower than size(buffer_indices) / indices_stride because of alignment.
     usable_indices: u32,
+    index_format: wgpu::IndexFormat,
 }
 
 impl IndexEntry {
@@ -399,6 +431,11 @@ impl IndexEntry {
         0..self.usable_indices
     }
 
+    /// The format of the indices within [`IndexEntry::indices_buffer_range`].
+    pub fn index_format(&self) -> wgpu::IndexFormat {
+        self.index_format
+    }
+
     pub fn indices_buffer_range(&self) -> Range<wgpu::BufferAddress> {
         self.buffer_indices.clone()
     }
@@ -593,7 +630,7 @@ mod tests {
         coords::ZoomLevel,
         render::resource::{BackingBufferDescriptor, Queue},
         style::layer::StyleLayer,
-        vector::resource::{BackingBufferType, BufferPool},
+        vector::resource::{BackingBufferType, BufferPool, BufferPoolError},
     };
 
     #[derive(Debug)]
@@ -655,7 +692,8 @@ mod tests {
                 &data48bytes_aligned,
                 2,
                 &[],
-            );
+            )
+            .unwrap();
         }
         assert_eq!(
             128 - 2 * 48,
@@ -669,7 +707,8 @@ mod tests {
             &data24bytes_aligned,
             2,
             &[],
-        );
+        )
+        .unwrap();
         assert_eq!(
             128 - 2 * 48 - 24,
             pool.available_space(BackingBufferType::Vertices)
@@ -683,7 +722,8 @@ mod tests {
             &data24bytes_aligned,
             2,
             &[],
-        );
+        )
+        .unwrap();
         // appended now at the beginning
         println!("{:?}", &pool.index);
         assert_eq!(24, pool.available_space(BackingBufferType::Vertices));
@@ -695,7 +735,8 @@ mod tests {
             &data24bytes_aligned,
             2,
             &[],
-        );
+        )
+        .unwrap();
         println!("{:?}", &pool.index);
         assert_eq!(0, pool.available_space(BackingBufferType::Vertices));
 
@@ -706,7 +747,8 @@ mod tests {
             &data24bytes_aligned,
             2,
             &[],
-        );
+        )
+        .unwrap();
         println!("{:?}", &pool.index);
         assert_eq!(24, pool.available_space(BackingBufferType::Vertices));
 
@@ -717,8 +759,41 @@ mod tests {
             &data24bytes_aligned,
             2,
             &[],
-        );
+        )
+        .unwrap();
         println!("{:?}", &pool.index);
         assert_eq!(0, pool.available_space(BackingBufferType::Vertices));
+
+        // The four indices of each layer are stored as u16
+        let entry = pool.index().back().unwrap();
+        assert_eq!(entry.index_format(), wgpu::IndexFormat::Uint16);
+        assert_eq!(
+            entry.indices_buffer_range().end - entry.indices_buffer_range().start,
+            8
+        );
+
+        // Layers which exceed the backing buffer are rejected without evicting other layers
+        let mut too_large = VertexBuffers::new();
+        too_large
+            .vertices
+            .append(&mut vec![TestVertex::default(); 6]);
+        too_large.indices.append(&mut vec![1, 2, 3, 4]);
+        let result = pool.allocate_layer_geometry(
+            &queue,
+            (0, 0, ZoomLevel::default()).into(),
+            StyleLayer::default(),
+            &too_large.into(),
+            2,
+            &[],
+        );
+        assert!(matches!(
+            result,
+            Err(BufferPoolError::TileTooLarge {
+                bytes: 144,
+                typ: BackingBufferType::Vertices,
+                ..
+            })
+        ));
+        assert_eq!(0, pool.available_space(BackingBufferType::Vertices));
     }
 }
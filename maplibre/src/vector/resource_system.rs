@@ -1,22 +1,61 @@
 //! Prepares GPU-owned resources by initializing them if they are uninitialized or out-of-date.
+use std::mem;
+
 use crate::{
     context::MapContext,
     render::{
-        eventually::Eventually,
+        eventually::{Eventually, Eventually::Initialized},
         resource::{RenderPipeline, TilePipeline},
         shaders,
-        shaders::Shader,
+        shaders::{GlobalUniforms, Shader},
         RenderResources, Renderer,
     },
-    vector::{resource::BufferPool, VectorBufferPool, VectorPipeline},
+    tcs::world::World,
+    vector::{
+        picking::VectorRenderMode, resource::BufferPool, ExtraBindGroup, VectorBufferPool,
+        VectorPipeline, VectorUniforms,
+    },
 };
 
+/// The uniforms which are written for the vector pipeline.
+fn global_uniforms(world: &World) -> GlobalUniforms {
+    // Feature ids must be written unchanged
+    match world.resources.get::<VectorRenderMode>() {
+        Some(VectorRenderMode::FeatureIds) => GlobalUniforms::default(),
+        _ => world
+            .resources
+            .get::<GlobalUniforms>()
+            .copied()
+            .unwrap_or_default(),
+    }
+}
+
+/// The layouts of the bind groups of the vector pipeline. The [`GlobalUniforms`] are in group 0,
+/// followed by the `extra` layout of an [`ExtraBindGroup`].
+fn bind_group_layouts(
+    extra: Option<Vec<wgpu::BindGroupLayoutEntry>>,
+) -> Vec<Vec<wgpu::BindGroupLayoutEntry>> {
+    let mut layouts = vec![vec![wgpu::BindGroupLayoutEntry {
+        binding: 0,
+        visibility: wgpu::ShaderStages::VERTEX,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    }]];
+    layouts.extend(extra);
+    layouts
+}
+
 pub fn resource_system(
     MapContext {
         world,
         renderer:
             Renderer {
                 device,
+                queue,
                 resources: RenderResources { surface, .. },
                 settings,
                 ..
@@ -24,12 +63,20 @@ pub fn resource_system(
         ..
     }: &mut MapContext,
 ) {
+    let global_uniforms = global_uniforms(world);
+    let extra = world
+        .resources
+        .get::<ExtraBindGroup>()
+        .map(|extra| (extra.layout.clone(), extra.vertex_shader));
+
     let Some((
         buffer_pool,
-        vector_pipeline
+        vector_pipeline,
+        vector_uniforms
     )) = world.resources.query_mut::<(
         &mut Eventually<VectorBufferPool>,
-        &mut Eventually<VectorPipeline>
+        &mut Eventually<VectorPipeline>,
+        &mut Eventually<VectorUniforms>
     )>() else { return; };
 
     buffer_pool.initialize(|| BufferPool::from_device(device));
@@ -37,12 +84,18 @@ pub fn resource_system(
     vector_pipeline.initialize(|| {
         let tile_shader = shaders::VectorTileShader {
             format: surface.surface_format(),
+            alpha_blending: settings.tile_fade.enabled,
         };
 
-        let pipeline = TilePipeline::new(
+        let mut vertex = tile_shader.describe_vertex();
+        if let Some(source) = extra.as_ref().and_then(|(_, vertex_shader)| *vertex_shader) {
+            vertex.source = source;
+        }
+
+        let mut descriptor = TilePipeline::new(
             "vector_pipeline".into(),
             *settings,
-            tile_shader.describe_vertex(),
+            vertex,
             tile_shader.describe_fragment(),
             true,
             false,
@@ -51,9 +104,85 @@ pub fn resource_system(
             true,
             false,
         )
-        .describe_render_pipeline()
-        .initialize(device);
+        .describe_render_pipeline();
+        descriptor.layout = Some(bind_group_layouts(extra.map(|(layout, _)| layout)));
 
-        VectorPipeline(pipeline)
+        VectorPipeline(descriptor.initialize(device))
     });
+
+    let Initialized(pipeline) = vector_pipeline else { return; };
+
+    vector_uniforms.initialize(|| {
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("vector uniforms buffer"),
+            size: mem::size_of::<GlobalUniforms>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("vector uniforms bind group"),
+            layout: &pipeline.get_bind_group_layout(0),
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding(),
+            }],
+        });
+
+        VectorUniforms { buffer, bind_group }
+    });
+
+    let Initialized(vector_uniforms) = vector_uniforms else { return; };
+    queue.write_buffer(
+        &vector_uniforms.buffer,
+        0,
+        bytemuck::bytes_of(&global_uniforms),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{bind_group_layouts, global_uniforms};
+    use crate::{
+        render::shaders::GlobalUniforms, tcs::world::World, vector::picking::VectorRenderMode,
+    };
+
+    #[test]
+    fn test_global_uniforms() {
+        let mut world = World::default();
+        assert_eq!(global_uniforms(&world), GlobalUniforms::default());
+
+        let tinted = GlobalUniforms {
+            tint: [1.0, 0.8, 0.8, 1.0],
+            ..GlobalUniforms::default()
+        };
+        world.resources.insert(tinted);
+        assert_eq!(global_uniforms(&world), tinted);
+        world.resources.insert(VectorRenderMode::Color);
+        assert_eq!(global_uniforms(&world), tinted);
+        world.resources.insert(VectorRenderMode::FeatureIds);
+        assert_eq!(global_uniforms(&world), GlobalUniforms::default());
+
+        // The layout of the uniform block in `tile.vertex.wgsl`
+        assert_eq!(
+            bytemuck::cast::<_, [f32; 8]>(tinted),
+            [1.0, 0.8, 0.8, 1.0, 0.0, 0.0, 0.0, 0.0]
+        );
+    }
+
+    #[test]
+    fn test_bind_group_layouts() {
+        let globals = bind_group_layouts(None);
+        assert_eq!(globals.len(), 1);
+
+        let extra = vec![wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+            count: None,
+        }];
+        assert_eq!(
+            bind_group_layouts(Some(extra.clone())),
+            vec![globals[0].clone(), extra]
+        );
+    }
 }
@@ -1,4 +1,7 @@
-use std::fmt::{Debug, Formatter};
+use std::{
+    fmt::{Debug, Formatter},
+    hash::Hasher,
+};
 
 use geozero::mvt::tile::Layer;
 
@@ -10,6 +13,7 @@ use crate::{
     },
     render::ShaderVertex,
     tessellation::{IndexDataType, OverAlignedVertexBuffer},
+    util::hash::Fnv1aHasher,
     vector::{AvailableVectorLayerData, MissingVectorLayerData},
 };
 
@@ -163,6 +167,23 @@ pub struct DefaultLayerTesselated {
     pub layer_data: Layer, // FIXME (perf): Introduce a better structure for this
 }
 
+impl DefaultLayerTesselated {
+    /// A hash of the layer name and the tessellated geometry. Tessellating the same data again
+    /// yields the same fingerprint, so that a re-fetched tile can be checked for changed
+    /// geometry.
+    pub fn fingerprint(&self) -> u64 {
+        let mut hasher = Fnv1aHasher::default();
+        hasher.write_usize(self.layer_data.name.len());
+        hasher.write(self.layer_data.name.as_bytes());
+        hasher.write_usize(self.feature_indices.len());
+        for count in &self.feature_indices {
+            hasher.write_u32(*count);
+        }
+        self.buffer.hash_contents(&mut hasher);
+        hasher.finish()
+    }
+}
+
 impl Debug for DefaultLayerTesselated {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(f, "DefaultLayerTesselated({})", self.coords)
@@ -263,3 +284,47 @@ impl VectorTransferables for DefaultVectorTransferables {
     type LayerTessellated = DefaultLayerTesselated;
     type LayerIndexed = DefaultLayerIndexed;
 }
+
+#[cfg(test)]
+mod tests {
+    use geozero::mvt::tile::Layer;
+
+    use super::DefaultLayerTesselated;
+    use crate::{
+        coords::WorldTileCoords, render::ShaderVertex, tessellation::OverAlignedVertexBuffer,
+    };
+
+    fn layer(name: &str, x: f32, indices: Vec<u32>) -> DefaultLayerTesselated {
+        let vertices = [[0.0, 0.0], [x, 0.0], [0.0, 1.0]]
+            .map(|position| ShaderVertex::new(position, [0.0, 0.0]));
+        let usable_indices = indices.len() as u32;
+        DefaultLayerTesselated {
+            coords: WorldTileCoords::default(),
+            buffer: OverAlignedVertexBuffer::from_iters(vertices, indices, usable_indices),
+            feature_indices: vec![3],
+            layer_data: Layer {
+                name: name.to_string(),
+                ..Layer::default()
+            },
+        }
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        let fingerprint = layer("water", 1.0, vec![0, 1, 2]).fingerprint();
+        assert_eq!(
+            layer("water", 1.0, vec![0, 1, 2]).fingerprint(),
+            fingerprint
+        );
+
+        assert_ne!(layer("park", 1.0, vec![0, 1, 2]).fingerprint(), fingerprint);
+        assert_ne!(
+            layer("water", 2.0, vec![0, 1, 2]).fingerprint(),
+            fingerprint
+        );
+        assert_ne!(
+            layer("water", 1.0, vec![0, 2, 1]).fingerprint(),
+            fingerprint
+        );
+    }
+}
@@ -1,10 +1,10 @@
 //! Uploads data to the GPU which is needed for rendering.
 
-use std::iter;
+use std::{collections::HashMap, iter};
 
 use crate::{
     context::MapContext,
-    coords::ViewRegion,
+    coords::{ViewRegion, WorldTileCoords},
     render::{
         eventually::{Eventually, Eventually::Initialized},
         shaders::{ShaderFeatureStyle, ShaderLayerMetadata, Vec4f32},
@@ -13,10 +13,33 @@ use crate::{
     style::Style,
     tcs::tiles::Tiles,
     vector::{
-        AvailableVectorLayerData, VectorBufferPool, VectorLayerData, VectorLayersDataComponent,
+        picking::{feature_id_metadata, VectorRenderMode},
+        AvailableVectorLayerData, BufferPoolError, VectorBufferPool, VectorLayerData,
+        VectorLayersDataComponent,
     },
 };
 
+/// The style layers in view which are rejected by the buffer pool, together with the reason.
+/// These layers are not drawn.
+#[derive(Debug, Default)]
+pub struct UploadErrors(pub Vec<(String, BufferPoolError)>);
+
+/// The style layers of tiles which were rejected by the buffer pool. The geometry of these
+/// layers can never be stored, so they are not allocated again and the rejection is only logged
+/// once.
+#[derive(Debug, Default)]
+struct RejectedLayers(HashMap<WorldTileCoords, HashMap<String, BufferPoolError>>);
+
+impl RejectedLayers {
+    fn get(&self, coords: &WorldTileCoords, style_layer: &str) -> Option<&BufferPoolError> {
+        self.0.get(coords)?.get(style_layer)
+    }
+
+    fn insert(&mut self, coords: WorldTileCoords, style_layer: String, error: BufferPoolError) {
+        self.0.entry(coords).or_default().insert(style_layer, error);
+    }
+}
+
 pub fn upload_system(
     MapContext {
         world,
@@ -26,25 +49,34 @@ pub fn upload_system(
         ..
     }: &mut MapContext,
 ) {
-    let Some(
-        Initialized(buffer_pool)
-    ) = world.resources.query_mut::<
-        &mut Eventually<VectorBufferPool>,
-    >() else { return; };
+    let render_mode = world
+        .resources
+        .get::<VectorRenderMode>()
+        .copied()
+        .unwrap_or_default();
+
+    world.resources.get_or_init_mut::<RejectedLayers>();
+    let Some((Initialized(buffer_pool), rejected_layers)) = world
+        .resources
+        .query_mut::<(&mut Eventually<VectorBufferPool>, &mut RejectedLayers)>() else { return; };
 
     let view_region = view_state.create_view_region();
 
+    let mut errors = Vec::new();
     if let Some(view_region) = &view_region {
-        upload_tesselated_layer(
+        errors = upload_tesselated_layer(
             buffer_pool,
+            rejected_layers,
             device,
             queue,
             &mut world.tiles,
             style,
             view_region,
+            render_mode,
         );
         // self.update_metadata(state, tile_repository, queue);
     }
+    world.resources.get_or_init_mut::<UploadErrors>().0 = errors;
 }
 
 /* FIXME tcs fn update_metadata(
@@ -120,12 +152,16 @@ pub fn upload_system(
 
 fn upload_tesselated_layer(
     buffer_pool: &mut VectorBufferPool,
+    rejected_layers: &mut RejectedLayers,
     _device: &wgpu::Device,
     queue: &wgpu::Queue,
     tiles: &mut Tiles,
     style: &Style,
     view_region: &ViewRegion,
-) {
+    render_mode: VectorRenderMode,
+) -> Vec<(String, BufferPoolError)> {
+    let mut errors = Vec::new();
+
     // Upload all tessellated layers which are in view
     for coords in view_region.iter() {
         let Some(vector_layers) = tiles.query_mut::<&VectorLayersDataComponent>(coords) else { continue; };
@@ -144,7 +180,7 @@ fn upload_tesselated_layer(
             .filter(|data| !loaded_layers.contains(data.source_layer.as_str()))
             .collect::<Vec<_>>();
 
-        for style_layer in &style.layers {
+        for (layer_position, style_layer) in style.layers.iter().enumerate() {
             let source_layer = style_layer.source_layer.as_ref().unwrap(); // TODO: Unwrap
 
             let Some(AvailableVectorLayerData {
@@ -156,31 +192,75 @@ fn upload_tesselated_layer(
                 .iter()
                 .find(|layer| source_layer.as_str() == layer.source_layer) else { continue; };
 
-            let color: Option<Vec4f32> = style_layer
+            if let Some(e) = rejected_layers.get(coords, &style_layer.id) {
+                errors.push((style_layer.id.clone(), e.clone()));
+                continue;
+            }
+
+            // Layers without a color are invisible
+            let Some(color): Option<Vec4f32> = style_layer
                 .paint
                 .as_ref()
                 .and_then(|paint| paint.get_color())
-                .map(|color| color.into());
+                .map(|color| color.into()) else { continue; };
 
-            let feature_metadata = (0..feature_indices.len()) // FIXME: Iterate over actual features
-                .enumerate()
-                .flat_map(|(i, _feature)| {
-                    iter::repeat(ShaderFeatureStyle {
-                        color: color.unwrap(),
-                    })
-                    .take(feature_indices[i] as usize)
-                })
-                .collect::<Vec<_>>();
+            let feature_metadata = match render_mode {
+                VectorRenderMode::Color => {
+                    (0..feature_indices.len()) // FIXME: Iterate over actual features
+                        .enumerate()
+                        .flat_map(|(i, _feature)| {
+                            iter::repeat(ShaderFeatureStyle { color })
+                                .take(feature_indices[i] as usize)
+                        })
+                        .collect::<Vec<_>>()
+                }
+                VectorRenderMode::FeatureIds => {
+                    feature_id_metadata(layer_position, buffer, feature_indices)
+                }
+            };
 
             log::debug!("Allocating geometry at {}", &coords);
-            buffer_pool.allocate_layer_geometry(
+            if let Err(e) = buffer_pool.allocate_layer_geometry(
                 queue,
                 *coords,
                 style_layer.clone(),
                 buffer,
                 ShaderLayerMetadata::new(style_layer.index as f32),
                 &feature_metadata,
-            );
+            ) {
+                log::error!("skipping layer {}: {e}", style_layer.id);
+                rejected_layers.insert(*coords, style_layer.id.clone(), e.clone());
+                errors.push((style_layer.id.clone(), e));
+            }
         }
     }
+
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RejectedLayers;
+    use crate::{
+        coords::{WorldTileCoords, ZoomLevel},
+        vector::{BackingBufferType, BufferPoolError},
+    };
+
+    #[test]
+    fn test_rejected_layers() {
+        let coords = WorldTileCoords::from((1, 2, ZoomLevel::new(3)));
+        let other = WorldTileCoords::from((2, 2, ZoomLevel::new(3)));
+        let error = BufferPoolError::TileTooLarge {
+            coords,
+            bytes: 1024,
+            typ: BackingBufferType::Vertices,
+        };
+
+        let mut rejected = RejectedLayers::default();
+        rejected.insert(coords, "water".to_string(), error.clone());
+
+        assert_eq!(rejected.get(&coords, "water"), Some(&error));
+        assert_eq!(rejected.get(&coords, "park"), None);
+        assert_eq!(rejected.get(&other, "water"), None);
+    }
 }
@@ -77,6 +77,24 @@ impl ViewState {
             })
     }
 
+    /// Same as [`ViewState::create_view_region`], but the region is extended by `radius` tiles
+    /// in every direction.
+    pub fn create_padded_view_region(&self, radius: u32) -> Option<ViewRegion> {
+        let padding = VIEW_REGION_PADDING + radius as i32;
+        let side = (1 + 2 * radius) as usize;
+        self.camera
+            .view_region_bounding_box(&self.view_projection().invert())
+            .map(|bounding_box| {
+                ViewRegion::new(
+                    bounding_box,
+                    padding,
+                    32 * side * side,
+                    *self.zoom,
+                    self.visible_level(),
+                )
+            })
+    }
+
     pub fn view_projection(&self) -> ViewProjection {
         self.camera.calc_view_proj(&self.perspective)
     }
@@ -60,4 +60,10 @@ impl WindowSize {
     pub fn height_non_zero(&self) -> NonZeroU32 {
         self.height
     }
+
+    /// Multiplies both dimensions by `factor` and rounds them. Each dimension is at least 1.
+    pub fn scaled(&self, factor: f64) -> Self {
+        let scale = |value: u32| ((value as f64 * factor).round() as u32).max(1);
+        Self::new(scale(self.width()), scale(self.height())).expect("dimensions are at least 1")
+    }
 }